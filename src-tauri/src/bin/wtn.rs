@@ -0,0 +1,6 @@
+//! Command line companion: `wtn cat`, `wtn append "text"`, `wtn export`,
+//! `wtn sync` and `wtn prefs get/set`.
+
+fn main() {
+    std::process::exit(what_the_note_lib::cli::run())
+}
@@ -0,0 +1,368 @@
+//! `wtn`, the command line companion. Commands go through the running app's
+//! scripting API when it is enabled, so the open window updates; otherwise
+//! they work on the note and preference files directly. Reading never
+//! migrates, repairs or tidies those files, which the app may be writing.
+
+use crate::error::{AppError, ErrorCode};
+use crate::models::preferences::Preferences;
+use crate::services::{
+    api::ApiClient,
+    html_text::{InputFormat, NoteInput},
+    instance,
+    preferences::PreferencesService,
+    storage::StorageService,
+    sync::{ExportFormat, SyncService},
+};
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+const USAGE: &str = "Usage: wtn <command>
+
+Commands:
+  cat [--html]                        Print the active note as Markdown (or stored HTML)
+  append [--format text|md|html] [TEXT...]
+                                      Append TEXT, or standard input when omitted
+  export [--format md|html|text] [--output FILE]
+                                      Render the note as the sync targets would
+  sync                                Export the note to the enabled sync targets
+  prefs get [KEY]                     Print all preferences, or one such as sync.markdown_path
+  prefs set KEY VALUE                 Change a preference; VALUE is JSON or plain text
+";
+
+/// Runs the command in the process arguments and returns the exit code.
+pub fn run() -> i32 {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match execute(args) {
+        Ok(output) => {
+            print!("{}", output);
+            0
+        }
+        Err(err) => {
+            eprintln!("wtn: {}", err);
+            1
+        }
+    }
+}
+
+fn execute(mut args: Vec<String>) -> Result<String, String> {
+    if args.is_empty() || matches!(args[0].as_str(), "-h" | "--help" | "help") {
+        return Ok(USAGE.to_string());
+    }
+
+    let command = args.remove(0);
+    let context = Context::open()?;
+
+    match command.as_str() {
+        "cat" => {
+            let html = take_flag(&mut args, "--html");
+            expect_no_more(&args)?;
+
+            let content = context.read_content()?;
+            Ok(with_newline(if html {
+                content
+            } else {
+                html2md::parse_html(&content)
+            }))
+        }
+        "append" => {
+            let format = match take_option(&mut args, "--format")?.as_deref() {
+                None | Some("text") => InputFormat::Text,
+                Some("md") | Some("markdown") => InputFormat::Markdown,
+                Some("html") => InputFormat::Html,
+                Some(other) => return Err(format!("Unknown input format: {}", other)),
+            };
+
+            let content = if args.is_empty() || args == ["-"] {
+                let mut input = String::new();
+                io::stdin()
+                    .read_to_string(&mut input)
+                    .map_err(|e| format!("Failed to read standard input: {}", e))?;
+                input
+            } else {
+                args.join(" ")
+            };
+
+            context.append(NoteInput { content, format })?;
+            Ok(String::new())
+        }
+        "export" => {
+            let format = match take_option(&mut args, "--format")?.as_deref() {
+                None | Some("md") | Some("markdown") => ExportFormat::Markdown,
+                Some("html") => ExportFormat::Html,
+                Some("text") | Some("txt") => ExportFormat::Text,
+                Some(other) => return Err(format!("Unknown export format: {}", other)),
+            };
+            let output = take_option(&mut args, "--output")?;
+            expect_no_more(&args)?;
+
+            let rendered = context.export(format)?;
+            match output {
+                Some(path) => {
+                    fs::write(&path, rendered)
+                        .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+                    Ok(String::new())
+                }
+                None => Ok(with_newline(rendered)),
+            }
+        }
+        "sync" => {
+            expect_no_more(&args)?;
+            context.sync()?;
+            Ok(String::new())
+        }
+        "prefs" => match args.first().map(String::as_str) {
+            Some("get") if args.len() <= 2 => {
+                let value = get_preference(&context.prefs.get()?, args.get(1).map(String::as_str))?;
+                Ok(with_newline(match value {
+                    Value::String(text) => text,
+                    other => serde_json::to_string_pretty(&other)
+                        .map_err(|e| format!("Failed to serialize preference: {}", e))?,
+                }))
+            }
+            Some("set") if args.len() == 3 => {
+                // The running app picks up the edited file like a hand edit
+                let updated = set_preference(&context.prefs.get()?, &args[1], &args[2])?;
+                context.prefs.update(updated)?;
+                Ok(String::new())
+            }
+            _ => Err(format!("Unknown prefs usage\n\n{}", USAGE)),
+        },
+        other => Err(format!("Unknown command: {}\n\n{}", other, USAGE)),
+    }
+}
+
+/// The same folders the app uses, derived from its bundle identifier.
+struct Context {
+    data_dir: PathBuf,
+    running: bool,
+    prefs: PreferencesService,
+}
+
+impl Context {
+    fn open() -> Result<Self, String> {
+        let identifier = app_identifier()?;
+        let data_dir = dirs::data_dir()
+            .ok_or_else(|| "Failed to get data directory".to_string())?
+            .join(&identifier);
+        let config_dir = dirs::config_dir()
+            .ok_or_else(|| "Failed to get config directory".to_string())?
+            .join(&identifier);
+
+        Ok(Self {
+            running: instance::is_running(&data_dir)?,
+            prefs: PreferencesService::open_read_only(&config_dir)?,
+            data_dir,
+        })
+    }
+
+    /// Sends the request to the running app, or returns `None` when it is not
+    /// running or not reachable and the caller should fall back to the files.
+    fn via_api(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<Value>,
+    ) -> Result<Option<Value>, String> {
+        if !self.running {
+            return Ok(None);
+        }
+
+        match ApiClient::for_preferences(&self.prefs.get()?, &self.data_dir) {
+            Some(client) => client.send(method, path, body.as_ref()),
+            None => Ok(None),
+        }
+    }
+
+    fn storage(&self) -> Result<StorageService, String> {
+        Ok(StorageService::open_read_only(&self.data_dir)?)
+    }
+
+    fn read_content(&self) -> Result<String, String> {
+        if let Some(reply) = self.via_api("GET", "/note", None)? {
+            return Ok(reply["content"].as_str().unwrap_or_default().to_string());
+        }
+
+        self.storage()?.read_note().map_err(locked_hint)
+    }
+
+    fn append(&self, input: NoteInput) -> Result<(), String> {
+        let body = serde_json::to_value(&input)
+            .map_err(|e| format!("Failed to serialize note input: {}", e))?;
+        if self.via_api("POST", "/note/append", Some(body))?.is_some() {
+            return Ok(());
+        }
+
+        // The open editor would overwrite the append with its next save
+        if self.running {
+            return Err(
+                "The app is running; enable the scripting API in its settings to append"
+                    .to_string(),
+            );
+        }
+
+        // Without the app, save and export the way the editor would
+        let storage = StorageService::open(&self.data_dir)?;
+        let note = storage.active_note()?;
+        let existing = storage.read_note_by_id(&note.id).map_err(locked_hint)?;
+        let content = format!("{}{}", existing, input.to_html());
+        storage.write_note_by_id(&note.id, &content)?;
+
+        SyncService::sync_all(&storage.active_note()?, &content, &self.prefs.get()?)
+            .map_err(|err| format!("Saved, but sync failed: {}", err))
+    }
+
+    fn export(&self, format: ExportFormat) -> Result<String, String> {
+        let note = self.storage()?.active_note()?;
+        let content = self.read_content()?;
+
+        SyncService::render(format, &note, &content, &self.prefs.get()?)
+            .map_err(|err| err.to_string())
+    }
+
+    fn sync(&self) -> Result<(), String> {
+        if self.via_api("POST", "/sync", None)?.is_some() {
+            return Ok(());
+        }
+
+        let storage = self.storage()?;
+        let note = storage.active_note()?;
+        let content = storage.read_note_by_id(&note.id).map_err(locked_hint)?;
+
+        SyncService::sync_all(&note, &content, &self.prefs.get()?).map_err(|err| err.to_string())
+    }
+}
+
+fn app_identifier() -> Result<String, String> {
+    let config: Value = serde_json::from_str(include_str!("../tauri.conf.json"))
+        .map_err(|e| format!("Failed to parse app config: {}", e))?;
+
+    config["identifier"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| "App config has no identifier".to_string())
+}
+
+fn locked_hint(err: AppError) -> String {
+    if err.is(ErrorCode::Locked) {
+        format!(
+            "{}; unlock them in the app with the scripting API enabled",
+            err
+        )
+    } else {
+        err.message
+    }
+}
+
+/// Looks up a dotted key such as `sync.markdown_path`; no key returns everything.
+fn get_preference(prefs: &Preferences, key: Option<&str>) -> Result<Value, String> {
+    let mut value = serde_json::to_value(prefs)
+        .map_err(|e| format!("Failed to serialize preferences: {}", e))?;
+
+    for part in key.into_iter().flat_map(|key| key.split('.')) {
+        value = value
+            .get_mut(part)
+            .map(Value::take)
+            .ok_or_else(|| format!("Unknown preference: {}", key.unwrap_or_default()))?;
+    }
+
+    Ok(value)
+}
+
+/// Returns `prefs` with one dotted key replaced. `raw` is read as JSON when
+/// it parses, so `true` and `42` keep their types, and as a string otherwise.
+/// The caller validates the result.
+fn set_preference(prefs: &Preferences, key: &str, raw: &str) -> Result<Preferences, String> {
+    let mut document = serde_json::to_value(prefs)
+        .map_err(|e| format!("Failed to serialize preferences: {}", e))?;
+
+    let slot = key
+        .split('.')
+        .try_fold(&mut document, |value, part| value.get_mut(part))
+        .ok_or_else(|| format!("Unknown preference: {}", key))?;
+    *slot = serde_json::from_str(raw).unwrap_or_else(|_| json!(raw));
+
+    serde_json::from_value(document).map_err(|e| format!("Invalid value for {}: {}", key, e))
+}
+
+fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    let before = args.len();
+    args.retain(|arg| arg != name);
+    args.len() != before
+}
+
+fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>, String> {
+    let Some(position) = args.iter().position(|arg| arg == name) else {
+        return Ok(None);
+    };
+
+    if position + 1 >= args.len() {
+        return Err(format!("{} needs a value", name));
+    }
+
+    let value = args.remove(position + 1);
+    args.remove(position);
+    Ok(Some(value))
+}
+
+fn expect_no_more(args: &[String]) -> Result<(), String> {
+    match args.first() {
+        Some(extra) => Err(format!("Unexpected argument: {}", extra)),
+        None => Ok(()),
+    }
+}
+
+fn with_newline(mut text: String) -> String {
+    if !text.ends_with('\n') {
+        text.push('\n');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn gets_and_sets_dotted_preferences() {
+        let dir = tempdir().expect("tempdir");
+        let service = PreferencesService::open(dir.path()).expect("open prefs");
+        let prefs = service.get().expect("get");
+
+        let updated = set_preference(&prefs, "sync.markdown_path", "~/Notes").expect("set path");
+        let updated = set_preference(&updated, "text_size", "18").expect("set size");
+        service.update(updated).expect("update");
+
+        let reopened = PreferencesService::open(dir.path()).expect("reopen prefs");
+        let prefs = reopened.get().expect("get");
+        assert_eq!(
+            get_preference(&prefs, Some("sync.markdown_path")).expect("get path"),
+            json!("~/Notes")
+        );
+        assert_eq!(prefs.text_size, 18);
+
+        assert!(set_preference(&prefs, "no_such_key", "1").is_err());
+        assert!(set_preference(&prefs, "text_size", "large").is_err());
+        let too_big = set_preference(&prefs, "text_size", "99").expect("parses");
+        assert!(reopened.update(too_big).is_err());
+    }
+
+    #[test]
+    fn parses_options_out_of_arguments() {
+        let mut args: Vec<String> = ["--format", "md", "hello", "--html"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+
+        assert_eq!(
+            take_option(&mut args, "--format").expect("option"),
+            Some("md".to_string())
+        );
+        assert!(take_flag(&mut args, "--html"));
+        assert_eq!(args, ["hello"]);
+        assert!(take_option(&mut vec!["--output".to_string()], "--output").is_err());
+    }
+}
@@ -0,0 +1,21 @@
+use crate::error::AppError;
+use crate::services::{logging, preferences::PreferencesService, storage::StorageService};
+use tauri::{AppHandle, State};
+
+/// Writes a zip of logs, redacted preferences and version details to the
+/// Downloads folder for attaching to a bug report. Returns its path.
+#[tauri::command]
+pub fn export_diagnostics(
+    prefs_service: State<PreferencesService>,
+    storage: State<StorageService>,
+    app: AppHandle,
+) -> Result<String, AppError> {
+    let prefs = prefs_service.get()?;
+    let titles: Vec<String> = storage
+        .list_notes()?
+        .into_iter()
+        .map(|note| note.title)
+        .collect();
+    let path = logging::export_diagnostics(&app, &prefs, &titles)?;
+    Ok(path.to_string_lossy().to_string())
+}
@@ -1,3 +1,4 @@
+pub mod diagnostics;
 pub mod note;
 pub mod preferences;
 pub mod sync;
@@ -1,31 +1,286 @@
+use crate::error::AppError;
+use crate::models::note::{EncryptionStatus, NoteMeta, Revision, RevisionDiff};
+use crate::models::search::SearchHit;
 use crate::services::{
-    preferences::PreferencesService, storage::StorageService, sync::SyncService,
+    auto_lock,
+    preferences::PreferencesService,
+    search::{self, SearchService},
+    storage::StorageService,
+    sync::{SyncQueue, SyncService},
 };
-use tauri::State;
+use log::warn;
+use shellexpand::tilde;
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter, Manager, State};
 
 #[tauri::command]
-pub fn get_note(storage: State<StorageService>) -> Result<String, String> {
-    storage.read_note()
+pub fn get_note(
+    note_id: Option<String>,
+    storage: State<StorageService>,
+) -> Result<String, AppError> {
+    match note_id {
+        Some(id) => storage.read_note_by_id(&id),
+        None => storage.read_note(),
+    }
 }
 
 #[tauri::command]
 pub fn save_note(
     content: String,
+    note_id: Option<String>,
+    storage: State<StorageService>,
+    app: AppHandle,
+) -> Result<(), AppError> {
+    let id = match note_id {
+        Some(id) => id,
+        None => storage.active_note_id()?,
+    };
+
+    storage.write_note_by_id(&id, &content)?;
+    after_save(&app, &id, &content)
+}
+
+#[tauri::command]
+pub fn list_notes(storage: State<StorageService>) -> Result<Vec<NoteMeta>, AppError> {
+    storage.list_notes()
+}
+
+#[tauri::command]
+pub fn get_active_note(storage: State<StorageService>) -> Result<NoteMeta, AppError> {
+    storage.active_note()
+}
+
+#[tauri::command]
+pub fn create_note(
+    title: String,
+    activate: Option<bool>,
+    storage: State<StorageService>,
+    app: AppHandle,
+) -> Result<NoteMeta, AppError> {
+    let note = storage.create_note(&title)?;
+
+    if activate.unwrap_or(true) {
+        switch_to(&storage, &app, &note.id)?;
+    }
+
+    Ok(note)
+}
+
+#[tauri::command]
+pub fn rename_note(
+    note_id: String,
+    title: String,
+    storage: State<StorageService>,
+    prefs_service: State<PreferencesService>,
+) -> Result<NoteMeta, AppError> {
+    // Read first, so nothing can fail once the rename is saved
+    let prefs = prefs_service.get()?;
+    let before = storage.note(&note_id)?;
+    let renamed = storage.rename_note(&note_id, &title)?;
+
+    // Exported files named after the title follow the note
+    SyncService::rename_all(&before, &renamed, &prefs);
+
+    Ok(renamed)
+}
+
+#[tauri::command]
+pub fn delete_note(
+    note_id: String,
+    storage: State<StorageService>,
+    app: AppHandle,
+) -> Result<(), AppError> {
+    let was_active = storage.active_note_id()? == note_id;
+
+    storage.delete_note(&note_id)?;
+
+    if was_active {
+        let active = storage.active_note()?;
+        app.emit("active-note-changed", active).map_err(|e| {
+            AppError::internal(format!("Failed to emit active-note-changed event: {}", e))
+        })?;
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn switch_note(
+    note_id: String,
+    storage: State<StorageService>,
+    app: AppHandle,
+) -> Result<NoteMeta, AppError> {
+    switch_to(&storage, &app, &note_id)
+}
+
+#[tauri::command]
+pub fn list_revisions(
+    note_id: Option<String>,
+    storage: State<StorageService>,
+) -> Result<Vec<Revision>, AppError> {
+    let id = match note_id {
+        Some(id) => id,
+        None => storage.active_note_id()?,
+    };
+
+    storage.list_revisions(&id)
+}
+
+#[tauri::command]
+pub fn diff_revision(
+    note_id: Option<String>,
+    revision_id: String,
+    storage: State<StorageService>,
+) -> Result<RevisionDiff, AppError> {
+    let id = match note_id {
+        Some(id) => id,
+        None => storage.active_note_id()?,
+    };
+
+    storage.diff_revision(&id, &revision_id)
+}
+
+#[tauri::command]
+pub fn restore_revision(
+    note_id: Option<String>,
+    revision_id: String,
+    storage: State<StorageService>,
+    app: AppHandle,
+) -> Result<String, AppError> {
+    let id = match note_id {
+        Some(id) => id,
+        None => storage.active_note_id()?,
+    };
+
+    let content = storage.restore_revision(&id, &revision_id)?;
+
+    app.emit("note-content-changed", id.clone()).map_err(|e| {
+        AppError::internal(format!("Failed to emit note-content-changed event: {}", e))
+    })?;
+
+    after_save(&app, &id, &content)?;
+
+    Ok(content)
+}
+
+#[tauri::command]
+pub fn search_notes(
+    query: String,
+    limit: Option<usize>,
     storage: State<StorageService>,
+    search: State<SearchService>,
     prefs_service: State<PreferencesService>,
-) -> Result<(), String> {
-    storage.write_note(&content)?;
-
-    match prefs_service.get() {
-        Ok(preferences) => {
-            if let Err(err) = SyncService::sync_all(&content, &preferences.sync) {
-                eprintln!("Sync failed: {}", err);
-            }
-        }
-        Err(err) => {
-            eprintln!("Failed to load preferences for sync: {}", err);
-        }
+) -> Result<Vec<SearchHit>, AppError> {
+    search.refresh_notes(&storage)?;
+
+    let preferences = prefs_service.get()?;
+    let export_dir = preferences
+        .sync
+        .markdown_path
+        .as_deref()
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(|path| PathBuf::from(tilde(path).into_owned()));
+    search.refresh_files(export_dir.as_deref())?;
+
+    Ok(search.search(&query, limit.unwrap_or(search::DEFAULT_LIMIT))?)
+}
+
+#[tauri::command]
+pub fn get_encryption_status(storage: State<StorageService>) -> Result<EncryptionStatus, AppError> {
+    storage.encryption_status()
+}
+
+#[tauri::command]
+pub fn enable_encryption(
+    passphrase: String,
+    storage: State<StorageService>,
+) -> Result<(), AppError> {
+    storage.enable_encryption(&passphrase)
+}
+
+#[tauri::command]
+pub fn disable_encryption(
+    passphrase: String,
+    storage: State<StorageService>,
+) -> Result<(), AppError> {
+    storage.disable_encryption(&passphrase)
+}
+
+#[tauri::command]
+pub fn unlock_notes(
+    passphrase: String,
+    storage: State<StorageService>,
+    search: State<SearchService>,
+    app: AppHandle,
+) -> Result<(), AppError> {
+    storage.unlock(&passphrase)?;
+
+    if let Err(err) = search.refresh_notes(&storage) {
+        warn!("Failed to rebuild search index: {}", err);
+    }
+
+    app.emit("notes-unlocked", ())
+        .map_err(|e| AppError::internal(format!("Failed to emit notes-unlocked event: {}", e)))
+}
+
+#[tauri::command]
+pub fn lock_notes(storage: State<StorageService>, app: AppHandle) -> Result<(), AppError> {
+    storage.lock()?;
+    auto_lock::notify_locked(&app);
+    Ok(())
+}
+
+/// Brings search and the sync targets up to date with a note that was just
+/// written, whether by the editor or by an import.
+pub(crate) fn after_save(app: &AppHandle, note_id: &str, content: &str) -> Result<(), AppError> {
+    let storage = app.state::<StorageService>();
+    reindex(&storage, &app.state::<SearchService>(), note_id, content);
+    sync_if_active(&storage, &app.state::<SyncQueue>(), app, note_id)
+}
+
+/// Keeps the search index current after a save. Searching re-checks storage
+/// anyway, so a failure here only costs a re-read later.
+fn reindex(storage: &StorageService, search: &SearchService, note_id: &str, content: &str) {
+    let result = storage
+        .note(note_id)
+        .map_err(String::from)
+        .and_then(|note| search.index_note(&note, content));
+
+    if let Err(err) = result {
+        warn!("Failed to update search index: {}", err);
+    }
+}
+
+fn sync_if_active(
+    storage: &StorageService,
+    sync_queue: &SyncQueue,
+    app: &AppHandle,
+    note_id: &str,
+) -> Result<(), AppError> {
+    // Sync targets mirror whichever note is currently shown in the window
+    if storage.active_note_id()? != note_id {
+        return Ok(());
+    }
+
+    // Exporting happens in the background; a failure there must not fail the save
+    if let Err(err) = sync_queue.enqueue(app, note_id) {
+        warn!("Failed to queue sync: {}", err);
     }
 
     Ok(())
 }
+
+fn switch_to(
+    storage: &StorageService,
+    app: &AppHandle,
+    note_id: &str,
+) -> Result<NoteMeta, AppError> {
+    let note = storage.switch_note(note_id)?;
+
+    app.emit("active-note-changed", note.clone()).map_err(|e| {
+        AppError::internal(format!("Failed to emit active-note-changed event: {}", e))
+    })?;
+
+    Ok(note)
+}
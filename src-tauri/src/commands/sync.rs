@@ -1,69 +1,125 @@
+use crate::error::AppError;
 use crate::services::preferences::PreferencesService;
 use crate::services::storage::StorageService;
-use crate::services::sync::{self, SyncService};
+use crate::services::sync::{self, SyncCapabilities, SyncQueue, SyncService, SyncStatus};
 use serde::Serialize;
-use tauri::State;
+use tauri::{AppHandle, State};
 
+/// Queues the active note for export; progress arrives as `sync-status` events.
 #[tauri::command]
 pub fn trigger_sync(
     storage: State<StorageService>,
-    prefs_service: State<PreferencesService>,
-) -> Result<(), String> {
-    let content = storage.read_note()?;
-    let preferences = prefs_service.get()?;
+    sync_queue: State<SyncQueue>,
+    app: AppHandle,
+) -> Result<(), AppError> {
+    let id = storage.active_note_id()?;
+    Ok(sync_queue.enqueue(&app, &id)?)
+}
 
-    SyncService::sync_all(&content, &preferences.sync).map_err(|err| err.to_string())
+#[tauri::command]
+pub fn get_sync_status(sync_queue: State<SyncQueue>) -> Result<SyncStatus, AppError> {
+    Ok(sync_queue.status()?)
 }
 
 #[tauri::command]
-pub fn check_apple_notes_permission() -> Result<(), String> {
-    sync::check_apple_notes_permission().map_err(|err| err.to_string())
+pub fn check_apple_notes_permission() -> Result<(), AppError> {
+    sync::check_apple_notes_permission().map_err(AppError::from)
 }
 
 #[tauri::command]
-pub fn list_apple_notes_folders() -> Result<Vec<String>, String> {
-    sync::list_apple_notes_folders().map_err(|err| err.to_string())
+pub fn list_apple_notes_folders() -> Result<Vec<String>, AppError> {
+    sync::list_apple_notes_folders().map_err(AppError::from)
+}
+
+#[derive(Serialize)]
+pub struct SyncTargetResult {
+    pub target: String,
+    pub label: String,
+    pub success: bool,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<AppError>,
 }
 
 #[derive(Serialize)]
 pub struct SyncTestResponse {
     pub success: bool,
-    pub target: Option<String>,
     pub message: String,
+    pub results: Vec<SyncTargetResult>,
 }
 
 #[tauri::command]
 pub fn test_sync(
     storage: State<StorageService>,
     prefs_service: State<PreferencesService>,
-) -> Result<SyncTestResponse, String> {
-    let content = storage.read_note()?;
+) -> Result<SyncTestResponse, AppError> {
+    let note = storage.active_note()?;
+    let content = storage.read_note_by_id(&note.id)?;
     let preferences = prefs_service.get()?;
 
-    let outcomes = SyncService::sync_outcomes(&content, &preferences.sync);
+    let results: Vec<SyncTargetResult> = SyncService::test_outcomes(&note, &content, &preferences)
+        .into_iter()
+        .map(|outcome| {
+            let error = outcome
+                .result
+                .err()
+                .map(|err| AppError::from(err).with_target(outcome.target));
+
+            SyncTargetResult {
+                target: outcome.target.to_string(),
+                label: outcome.label.to_string(),
+                success: error.is_none(),
+                message: match &error {
+                    None => "Sync completed successfully".to_string(),
+                    Some(err) => err.message.clone(),
+                },
+                error,
+            }
+        })
+        .collect();
 
-    if outcomes.is_empty() {
+    if results.is_empty() {
         return Ok(SyncTestResponse {
             success: false,
-            target: None,
             message: "No sync targets are enabled".to_string(),
+            results,
         });
     }
 
-    let outcome = outcomes.into_iter().next().unwrap();
+    let failed = results.iter().filter(|result| !result.success).count();
+    let message = match failed {
+        0 => "Sync completed successfully".to_string(),
+        n => format!("{} of {} sync targets failed", n, results.len()),
+    };
 
-    let target_label = Some(outcome.target.label().to_string());
+    Ok(SyncTestResponse {
+        success: failed == 0,
+        message,
+        results,
+    })
+}
 
-    match outcome.result {
-        Ok(()) => Ok(SyncTestResponse {
-            success: true,
-            target: target_label,
-            message: "Sync completed successfully".to_string(),
-        }),
-        Err(err) => Ok(SyncTestResponse {
-            success: false,
-            target: target_label,
-            message: err.to_string(),
-        }),
-    }
+#[derive(Serialize)]
+pub struct SyncTargetInfo {
+    pub id: String,
+    pub label: String,
+    pub capabilities: SyncCapabilities,
+    pub enabled: bool,
+}
+
+#[tauri::command]
+pub fn list_sync_targets(
+    prefs_service: State<PreferencesService>,
+) -> Result<Vec<SyncTargetInfo>, AppError> {
+    let preferences = prefs_service.get()?;
+
+    Ok(SyncService::registry()
+        .targets()
+        .map(|target| SyncTargetInfo {
+            id: target.id().to_string(),
+            label: target.label().to_string(),
+            capabilities: target.capabilities(),
+            enabled: target.is_enabled(&preferences.sync),
+        })
+        .collect())
 }
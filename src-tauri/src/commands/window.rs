@@ -1,17 +1,20 @@
+use crate::error::AppError;
+use crate::models::preferences::Preferences;
+use crate::services::placement::{self, Rect, Screen};
 use crate::services::preferences::PreferencesService;
 use tauri::{AppHandle, Manager, PhysicalPosition, PhysicalSize, State, WebviewUrl, WebviewWindow};
 
 #[tauri::command]
-pub fn toggle_window(app: AppHandle) -> Result<(), String> {
+pub fn toggle_window(app: AppHandle) -> Result<(), AppError> {
     let window = app
         .get_webview_window("main")
-        .ok_or("Main window not found")?;
+        .ok_or_else(|| AppError::window_not_found("main"))?;
 
     let prefs_service = app.state::<PreferencesService>();
 
     if window
         .is_visible()
-        .map_err(|e| format!("Failed to check visibility: {}", e))?
+        .map_err(|e| AppError::internal(format!("Failed to check visibility: {}", e)))?
     {
         hide_window(window)
     } else {
@@ -20,106 +23,92 @@ pub fn toggle_window(app: AppHandle) -> Result<(), String> {
 }
 
 #[tauri::command]
-pub fn show_window_command(app: AppHandle) -> Result<(), String> {
+pub fn show_window_command(app: AppHandle) -> Result<(), AppError> {
     let window = app
         .get_webview_window("main")
-        .ok_or("Main window not found")?;
+        .ok_or_else(|| AppError::window_not_found("main"))?;
 
     let prefs_service = app.state::<PreferencesService>();
     show_window(window, &prefs_service)
 }
 
 #[tauri::command]
-pub fn hide_window_command(app: AppHandle) -> Result<(), String> {
+pub fn hide_window_command(app: AppHandle) -> Result<(), AppError> {
     let window = app
         .get_webview_window("main")
-        .ok_or("Main window not found")?;
+        .ok_or_else(|| AppError::window_not_found("main"))?;
     hide_window(window)
 }
 
-fn show_window(window: WebviewWindow, prefs_service: &PreferencesService) -> Result<(), String> {
+fn show_window(window: WebviewWindow, prefs_service: &PreferencesService) -> Result<(), AppError> {
     let prefs = prefs_service.get()?;
 
-    // Try to restore saved position and size
-    let positioned = if let (Some(x), Some(y), Some(width), Some(height)) = (
-        prefs.window_x,
-        prefs.window_y,
-        prefs.window_width,
-        prefs.window_height,
-    ) {
-        // Check if position is on-screen
-        if is_position_on_screen(&window, x, y) {
-            window
-                .set_position(PhysicalPosition::new(x, y))
-                .map_err(|e| format!("Failed to set window position: {}", e))?;
-            window
-                .set_size(PhysicalSize::new(width, height))
-                .map_err(|e| format!("Failed to set window size: {}", e))?;
-            true
-        } else {
-            false
-        }
-    } else {
-        false
-    };
+    // The bounds used last time these monitors were connected, or failing
+    // that the last bounds on any setup
+    let screens = screens(&window);
+    let saved = placement::recall(&prefs.window_placements, &placement::layout_key(&screens))
+        .or_else(|| last_bounds(&prefs));
 
-    // If no saved position or position was off-screen, use smart positioning
-    if !positioned {
-        position_window_smartly(&window)?;
+    let position = window
+        .outer_position()
+        .map_err(|e| AppError::internal(format!("Failed to get window position: {}", e)))?;
+    let size = window
+        .outer_size()
+        .map_err(|e| AppError::internal(format!("Failed to get window size: {}", e)))?;
+    let current = placement::Window {
+        saved,
+        current: Rect::new(position.x, position.y, size.width, size.height),
+        scale: window.scale_factor().unwrap_or(1.0),
+    };
+    let cursor = window
+        .cursor_position()
+        .ok()
+        .map(|point| (point.x.round() as i32, point.y.round() as i32));
+
+    // With no monitor to go by, leave the window where it is
+    if let Some(bounds) = placement::place(
+        prefs.placement_policy,
+        prefs.placement_corner,
+        &current,
+        &screens,
+        cursor,
+    ) {
+        window
+            .set_position(PhysicalPosition::new(bounds.x, bounds.y))
+            .map_err(|e| AppError::internal(format!("Failed to set window position: {}", e)))?;
+        window
+            .set_size(PhysicalSize::new(bounds.width, bounds.height))
+            .map_err(|e| AppError::internal(format!("Failed to set window size: {}", e)))?;
     }
 
     window
         .show()
-        .map_err(|e| format!("Failed to show window: {}", e))?;
+        .map_err(|e| AppError::internal(format!("Failed to show window: {}", e)))?;
 
     window
         .set_focus()
-        .map_err(|e| format!("Failed to focus window: {}", e))?;
+        .map_err(|e| AppError::internal(format!("Failed to focus window: {}", e)))?;
 
     Ok(())
 }
 
-fn hide_window(window: WebviewWindow) -> Result<(), String> {
+fn hide_window(window: WebviewWindow) -> Result<(), AppError> {
     window
         .hide()
-        .map_err(|e| format!("Failed to hide window: {}", e))
-}
-
-fn position_window_smartly(window: &WebviewWindow) -> Result<(), String> {
-    // Get screen dimensions
-    let monitor = window
-        .current_monitor()
-        .map_err(|e| format!("Failed to get current monitor: {}", e))?
-        .ok_or("No monitor found")?;
-
-    let monitor_size = monitor.size();
-    let window_size = window
-        .outer_size()
-        .map_err(|e| format!("Failed to get window size: {}", e))?;
-
-    // Position in top-right corner with some padding
-    let padding = 20;
-    let x = (monitor_size.width as i32) - (window_size.width as i32) - padding;
-    let y = padding;
-
-    window
-        .set_position(PhysicalPosition::new(x, y))
-        .map_err(|e| format!("Failed to set window position: {}", e))?;
-
-    Ok(())
+        .map_err(|e| AppError::internal(format!("Failed to hide window: {}", e)))
 }
 
 #[tauri::command]
-pub fn open_preferences_window(app: AppHandle) -> Result<(), String> {
+pub fn open_preferences_window(app: AppHandle) -> Result<(), AppError> {
     // Check if preferences window already exists
     if let Some(window) = app.get_webview_window("preferences") {
         // Window exists, just bring it to front
         window
             .show()
-            .map_err(|e| format!("Failed to show preferences window: {}", e))?;
-        window
-            .set_focus()
-            .map_err(|e| format!("Failed to focus preferences window: {}", e))?;
+            .map_err(|e| AppError::internal(format!("Failed to show preferences window: {}", e)))?;
+        window.set_focus().map_err(|e| {
+            AppError::internal(format!("Failed to focus preferences window: {}", e))
+        })?;
         return Ok(());
     }
 
@@ -137,62 +126,76 @@ pub fn open_preferences_window(app: AppHandle) -> Result<(), String> {
     .always_on_top(true)
     .center()
     .build()
-    .map_err(|e| format!("Failed to create preferences window: {}", e))?;
+    .map_err(|e| AppError::internal(format!("Failed to create preferences window: {}", e)))?;
 
     window
         .show()
-        .map_err(|e| format!("Failed to show preferences window: {}", e))?;
+        .map_err(|e| AppError::internal(format!("Failed to show preferences window: {}", e)))?;
 
     window
         .set_focus()
-        .map_err(|e| format!("Failed to focus preferences window: {}", e))?;
+        .map_err(|e| AppError::internal(format!("Failed to focus preferences window: {}", e)))?;
 
     Ok(())
 }
 
-fn is_position_on_screen(window: &WebviewWindow, x: i32, y: i32) -> bool {
-    // Get all available monitors
-    if let Ok(monitors) = window.available_monitors() {
-        for monitor in monitors {
-            let pos = monitor.position();
-            let size = monitor.size();
-
-            // Check if the position is within this monitor's bounds
-            if x >= pos.x
-                && x < pos.x + size.width as i32
-                && y >= pos.y
-                && y < pos.y + size.height as i32
-            {
-                return true;
-            }
-        }
-    }
-    false
+fn last_bounds(prefs: &Preferences) -> Option<Rect> {
+    Some(Rect::new(
+        prefs.window_x?,
+        prefs.window_y?,
+        prefs.window_width?,
+        prefs.window_height?,
+    ))
+}
+
+/// The connected monitors, or none if they can't be listed.
+fn screens(window: &WebviewWindow) -> Vec<Screen> {
+    window
+        .available_monitors()
+        .unwrap_or_default()
+        .iter()
+        .map(|monitor| Screen {
+            name: monitor.name().cloned(),
+            frame: Rect::new(
+                monitor.position().x,
+                monitor.position().y,
+                monitor.size().width,
+                monitor.size().height,
+            ),
+            scale: monitor.scale_factor(),
+        })
+        .collect()
 }
 
 #[tauri::command]
 pub fn save_window_bounds(
     app: AppHandle,
     prefs_service: State<PreferencesService>,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     let window = app
         .get_webview_window("main")
-        .ok_or("Main window not found")?;
+        .ok_or_else(|| AppError::window_not_found("main"))?;
 
     let position = window
         .outer_position()
-        .map_err(|e| format!("Failed to get window position: {}", e))?;
+        .map_err(|e| AppError::internal(format!("Failed to get window position: {}", e)))?;
     let size = window
         .outer_size()
-        .map_err(|e| format!("Failed to get window size: {}", e))?;
-
-    let mut prefs = prefs_service.get()?;
-    prefs.window_x = Some(position.x);
-    prefs.window_y = Some(position.y);
-    prefs.window_width = Some(size.width);
-    prefs.window_height = Some(size.height);
-
-    prefs_service.update(prefs)?;
+        .map_err(|e| AppError::internal(format!("Failed to get window size: {}", e)))?;
+
+    let bounds = Rect::new(position.x, position.y, size.width, size.height);
+    let layout = placement::layout_key(&screens(&window));
+
+    // Changed under the preferences lock rather than read, modified and
+    // written back, so a preferences change saved at the same moment isn't
+    // overwritten
+    prefs_service.modify(&app, |prefs| {
+        prefs.window_x = Some(bounds.x);
+        prefs.window_y = Some(bounds.y);
+        prefs.window_width = Some(bounds.width);
+        prefs.window_height = Some(bounds.height);
+        placement::remember(&mut prefs.window_placements, &layout, bounds);
+    })?;
 
     Ok(())
 }
@@ -0,0 +1,203 @@
+//! The error every command rejects with. The frontend branches on `code`,
+//! which stays stable; `message` is for people and may change wording.
+
+use crate::models::preferences::FieldError;
+use crate::services::sync::SyncError;
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorCode {
+    /// A note, revision or window that doesn't exist; `target` names it.
+    NotFound,
+    /// Notes are encrypted and need the passphrase first.
+    Locked,
+    /// A wrong passphrase, or the system refused access.
+    PermissionDenied,
+    /// Rejected input. `field` names the value, and `fields` lists every
+    /// problem when preferences fail validation.
+    Invalid,
+    /// Not possible in the current state, such as deleting the only note.
+    Conflict,
+    /// A sync target that is enabled but not set up.
+    NotConfigured,
+    /// A feature this platform doesn't have.
+    NotImplemented,
+    /// Reading or writing a file failed.
+    Io,
+    /// An AppleScript run by sync failed.
+    Script,
+    /// Anything else; only the message says what.
+    Internal,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct AppError {
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    /// The note id, window label or sync target the error is about.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldError>,
+}
+
+impl AppError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            field: None,
+            target: None,
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Internal, message)
+    }
+
+    pub fn not_found(kind: &str, target: impl Into<String>) -> Self {
+        let target = target.into();
+        Self::new(
+            ErrorCode::NotFound,
+            format!("{} not found: {}", kind, target),
+        )
+        .with_target(target)
+    }
+
+    pub fn window_not_found(label: &str) -> Self {
+        let name = match label {
+            "main" => "Main window",
+            "preferences" => "Preferences window",
+            _ => "Window",
+        };
+        Self::new(ErrorCode::NotFound, format!("{} not found", name)).with_target(label)
+    }
+
+    pub fn invalid(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Invalid, message).with_field(field)
+    }
+
+    pub fn io(context: &str, err: std::io::Error) -> Self {
+        Self::new(ErrorCode::Io, format!("{}: {}", context, err))
+    }
+
+    pub fn with_field(mut self, field: impl Into<String>) -> Self {
+        self.field = Some(field.into());
+        self
+    }
+
+    pub fn with_target(mut self, target: impl Into<String>) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    pub fn is(&self, code: ErrorCode) -> bool {
+        self.code == code
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+/// Services that haven't been given codes still report plain messages.
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        Self::internal(message)
+    }
+}
+
+impl From<AppError> for String {
+    fn from(err: AppError) -> Self {
+        err.message
+    }
+}
+
+/// Each variant keeps its own code, so `is_transient` can still be told
+/// from the code alone.
+impl From<SyncError> for AppError {
+    fn from(err: SyncError) -> Self {
+        let code = match &err {
+            SyncError::Io(_) => ErrorCode::Io,
+            SyncError::NotConfigured(_) => ErrorCode::NotConfigured,
+            #[cfg(not(target_os = "macos"))]
+            SyncError::NotImplemented(_) => ErrorCode::NotImplemented,
+            SyncError::PermissionDenied(_) => ErrorCode::PermissionDenied,
+            SyncError::AppleScript(_) => ErrorCode::Script,
+        };
+        Self::new(code, err.to_string())
+    }
+}
+
+/// Preferences that failed validation, or whose side effects failed.
+impl From<Vec<FieldError>> for AppError {
+    fn from(fields: Vec<FieldError>) -> Self {
+        let message = fields
+            .iter()
+            .map(|error| error.message.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let field = match fields.as_slice() {
+            [only] => only.field.clone(),
+            _ => None,
+        };
+
+        Self {
+            code: ErrorCode::Invalid,
+            message,
+            field,
+            target: None,
+            fields,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn sync_errors_keep_their_kind_and_message() {
+        let cases = [
+            (
+                SyncError::Io(std::io::Error::other("disk full")),
+                ErrorCode::Io,
+            ),
+            (
+                SyncError::NotConfigured("Markdown path"),
+                ErrorCode::NotConfigured,
+            ),
+            (
+                SyncError::PermissionDenied("Notes".to_string()),
+                ErrorCode::PermissionDenied,
+            ),
+            (
+                SyncError::AppleScript("syntax".to_string()),
+                ErrorCode::Script,
+            ),
+        ];
+
+        for (err, code) in cases {
+            let message = err.to_string();
+            let converted = AppError::from(err);
+            assert_eq!(converted.code, code);
+            assert_eq!(converted.message, message);
+        }
+
+        let serialized = serde_json::to_value(AppError::window_not_found("main")).unwrap();
+        assert_eq!(
+            serialized,
+            json!({ "code": "not-found", "message": "Main window not found", "target": "main" })
+        );
+    }
+}
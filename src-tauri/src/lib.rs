@@ -1,23 +1,37 @@
+pub mod cli;
 mod commands;
+mod error;
 mod models;
 mod services;
 
-use commands::{note, preferences, sync, window};
+use commands::{diagnostics, note, preferences, sync, window};
 use services::{
-    preferences::PreferencesService, shortcuts::ShortcutsService, storage::StorageService, tray,
+    api::ApiServer,
+    auto_lock::AutoLock,
+    instance::InstanceLock,
+    logging,
+    preferences::PreferencesService,
+    preferences_watcher::PreferencesWatcher,
+    search::SearchService,
+    shortcuts::ShortcutsService,
+    storage::StorageService,
+    sync::{MarkdownWatcher, SyncQueue},
+    tray,
 };
 
-#[cfg(target_os = "macos")]
-use services::hotcorner::HotCornerService;
+use services::hotcorner::{self, HotCornerService};
 
+use log::{error, warn};
 use tauri::{Listener, Manager};
 use tauri_plugin_autostart::ManagerExt;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
+        .plugin(logging::plugin_builder().build())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_global_shortcut::Builder::new().build())
+        .plugin(tauri_plugin_clipboard_manager::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
@@ -40,6 +54,19 @@ pub fn run() {
                 }
             }
 
+            // Tell wtn the app is running, so it doesn't write notes behind the editor's back.
+            // A second instance would write the same notes, so it quits before opening them.
+            match InstanceLock::new(&app_handle) {
+                Ok(Some(lock)) => {
+                    app.manage(lock);
+                }
+                Ok(None) => {
+                    warn!("Another instance of the app is already running, exiting");
+                    std::process::exit(0);
+                }
+                Err(e) => warn!("Failed to take app lock: {}", e),
+            }
+
             // Initialize services
             let storage = StorageService::new(&app_handle)
                 .expect("Failed to initialize storage service");
@@ -47,17 +74,32 @@ pub fn run() {
             let prefs_service = PreferencesService::new(&app_handle)
                 .expect("Failed to initialize preferences service");
 
+            // Encrypted notes start locked; the index is built once they are unlocked
+            let search = SearchService::default();
+            let locked = storage.encryption_status().is_ok_and(|status| status.locked);
+            if !locked {
+                if let Err(e) = search.refresh_notes(&storage) {
+                    warn!("Failed to build search index: {}", e);
+                }
+            }
+
+            let sync_queue = SyncQueue::new(&app_handle)
+                .expect("Failed to initialize sync queue");
+
             let prefs = prefs_service.get()
                 .expect("Failed to load preferences");
 
+            // Keep only what the log level preference asks for
+            logging::set_level(prefs.log_level);
+            log::info!("Starting What The Note {}", app.package_info().version);
+
             // Set up system tray
             tray::create_tray(&app_handle)
                 .expect("Failed to create system tray");
 
-            // Register keyboard shortcut if enabled
-            if prefs.shortcut_enabled {
-                ShortcutsService::register(&app_handle, &prefs.keyboard_shortcut)
-                    .expect("Failed to register keyboard shortcut");
+            // Register keyboard shortcuts if enabled; bindings that fail are logged and skipped
+            if let Err(e) = ShortcutsService::apply(&app_handle, &prefs) {
+                error!("Failed to register keyboard shortcuts: {}", e);
             }
 
             // Enable or disable launch on startup
@@ -68,53 +110,78 @@ pub fn run() {
                 let _ = autostart_manager.disable();
             }
 
-            // Set up hot corner service (macOS only)
-            #[cfg(target_os = "macos")]
-            {
-                let hotcorner = HotCornerService::new();
-                hotcorner.update_config(
-                    prefs.hotcorner_corner,
-                    prefs.hotcorner_size,
-                    prefs.hotcorner_enabled,
-                );
-                hotcorner.start(app_handle.clone());
-                app.manage(hotcorner);
+            // Set up hot corner service; it only watches the pointer on
+            // platforms with a cursor source
+            let hotcorner = HotCornerService::new();
+            hotcorner.update_config(
+                prefs.hotcorner_corner,
+                prefs.hotcorner_size,
+                prefs.hotcorner_enabled,
+            );
+            if let Some(cursor) = hotcorner::system_cursor() {
+                hotcorner.start(app_handle.clone(), cursor);
             }
+            app.manage(hotcorner);
 
             // Manage services state
             app.manage(storage);
             app.manage(prefs_service);
+            app.manage(search);
+
+            // Export saved notes in the background, picking up work left from the last run
+            sync_queue.start(app_handle.clone());
+            app.manage(sync_queue);
+
+            // Watch the Markdown export for outside edits when two-way sync is on
+            let markdown_watcher = MarkdownWatcher::default();
+            markdown_watcher.start(app_handle.clone());
+            app.manage(markdown_watcher);
+
+            // Apply edits made to preferences.json outside the app
+            let preferences_watcher = PreferencesWatcher::default();
+            preferences_watcher.start(app_handle.clone());
+            app.manage(preferences_watcher);
+
+            // Lock encrypted notes again after they sit unused
+            let auto_lock = AutoLock::default();
+            auto_lock.start(app_handle.clone());
+            app.manage(auto_lock);
+
+            // Serve the local scripting API if the user turned it on
+            let api_server = ApiServer::new(&app_handle)
+                .expect("Failed to initialize API server");
+            if let Err(e) = api_server.apply(&app_handle, &prefs) {
+                error!("Failed to start API server: {}", e);
+            }
+            app.manage(api_server);
 
             // Set up event listeners
             let app_handle_clone = app_handle.clone();
             app.listen("toggle-window", move |_event| {
                 if let Err(e) = window::toggle_window(app_handle_clone.clone()) {
-                    eprintln!("Failed to toggle window: {}", e);
+                    error!("Failed to toggle window: {}", e);
                 }
             });
 
-            #[cfg(target_os = "macos")]
-            {
-                let app_handle_clone = app_handle.clone();
-                app.listen("hotcorner-triggered", move |_event| {
-                    if let Err(e) = window::show_window_command(app_handle_clone.clone()) {
-                        eprintln!("Failed to show window from hotcorner: {}", e);
-                    }
-                });
-            }
+            let app_handle_clone = app_handle.clone();
+            app.listen("hotcorner-triggered", move |_event| {
+                if let Err(e) = window::show_window_command(app_handle_clone.clone()) {
+                    error!("Failed to show window from hotcorner: {}", e);
+                }
+            });
 
             // Listen for preferences window event
             let app_handle_clone = app_handle.clone();
             app.listen("open-preferences", move |_event| {
                 if let Err(e) = window::open_preferences_window(app_handle_clone.clone()) {
-                    eprintln!("Failed to open preferences window: {}", e);
+                    error!("Failed to open preferences window: {}", e);
                 }
             });
 
             // Show window on launch if configured
             if prefs.show_on_launch {
                 if let Err(e) = window::show_window_command(app_handle.clone()) {
-                    eprintln!("Failed to show window on launch: {}", e);
+                    error!("Failed to show window on launch: {}", e);
                 }
             }
 
@@ -123,10 +190,29 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             note::get_note,
             note::save_note,
+            note::list_notes,
+            note::get_active_note,
+            note::create_note,
+            note::rename_note,
+            note::delete_note,
+            note::switch_note,
+            note::list_revisions,
+            note::diff_revision,
+            note::restore_revision,
+            note::search_notes,
+            note::get_encryption_status,
+            note::enable_encryption,
+            note::disable_encryption,
+            note::unlock_notes,
+            note::lock_notes,
             preferences::get_preferences,
             preferences::update_preferences,
+            preferences::patch_preferences,
+            diagnostics::export_diagnostics,
             sync::trigger_sync,
+            sync::get_sync_status,
             sync::test_sync,
+            sync::list_sync_targets,
             sync::check_apple_notes_permission,
             sync::list_apple_notes_folders,
             window::toggle_window,
@@ -1 +1,3 @@
+pub mod note;
 pub mod preferences;
+pub mod search;
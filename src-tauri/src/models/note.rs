@@ -0,0 +1,99 @@
+use crate::error::AppError;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NoteMeta {
+    pub id: String,
+    pub title: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl NoteMeta {
+    pub fn new(id: String, title: String) -> Self {
+        let now = Utc::now();
+        Self {
+            id,
+            title,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// On-disk index of every note plus the pointer to the one shown in the window.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotesIndex {
+    pub active_note_id: String,
+    pub notes: Vec<NoteMeta>,
+}
+
+impl NotesIndex {
+    pub fn find(&self, id: &str) -> Option<&NoteMeta> {
+        self.notes.iter().find(|note| note.id == id)
+    }
+
+    pub fn find_mut(&mut self, id: &str) -> Option<&mut NoteMeta> {
+        self.notes.iter_mut().find(|note| note.id == id)
+    }
+
+    pub fn active(&self) -> Option<&NoteMeta> {
+        self.find(&self.active_note_id)
+    }
+}
+
+pub fn normalize_title(title: &str) -> Result<String, AppError> {
+    let trimmed = title.trim();
+
+    if trimmed.is_empty() {
+        return Err(AppError::invalid("title", "Note title cannot be empty"));
+    }
+
+    if trimmed.chars().count() > 200 {
+        return Err(AppError::invalid(
+            "title",
+            "Note title must be 200 characters or less",
+        ));
+    }
+
+    Ok(trimmed.to_string())
+}
+
+/// Whether note content is encrypted at rest, and whether it can be read now.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+pub struct EncryptionStatus {
+    pub enabled: bool,
+    pub locked: bool,
+}
+
+/// A stored snapshot of a note's content.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Revision {
+    pub id: String,
+    pub saved_at: DateTime<Utc>,
+    pub bytes: usize,
+    /// Sealed revisions are never overwritten by later saves in the same bucket.
+    #[serde(default)]
+    pub sealed: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum DiffKind {
+    Equal,
+    Insert,
+    Delete,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffLine {
+    pub kind: DiffKind,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RevisionDiff {
+    pub revision_id: String,
+    pub lines: Vec<DiffLine>,
+}
@@ -1,22 +1,40 @@
+use log::warn;
 use serde::{Deserialize, Serialize};
+use tauri_plugin_global_shortcut::Shortcut;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+/// Bump when the on-disk layout changes and add a step to
+/// `services::migrations::MIGRATIONS`.
+pub const CURRENT_SCHEMA_VERSION: u32 = 5;
+pub const DEFAULT_EXPORT_FILENAME: &str = "What The Note";
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
 pub enum Corner {
     TopLeft,
+    #[default]
     TopRight,
     BottomLeft,
     BottomRight,
 }
 
-impl Default for Corner {
-    fn default() -> Self {
-        Corner::TopRight
-    }
+/// Where the note window appears when it is shown.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PlacementPolicy {
+    /// Where it was last left on this monitor setup.
+    #[default]
+    LastPosition,
+    /// At the same spot, but on the monitor under the pointer.
+    MonitorUnderCursor,
+    /// Beside the pointer.
+    NearCursor,
+    /// In `placement_corner` of the monitor under the pointer.
+    PinnedCorner,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub enum Theme {
+    #[default]
     LiquidGlass,
     GradientCosmic,
     Minimal,
@@ -24,22 +42,37 @@ pub enum Theme {
     StickyNote,
 }
 
-impl Default for Theme {
-    fn default() -> Self {
-        Theme::LiquidGlass
+impl Theme {
+    const ALL: [Theme; 5] = [
+        Theme::LiquidGlass,
+        Theme::GradientCosmic,
+        Theme::Minimal,
+        Theme::MinimalDark,
+        Theme::StickyNote,
+    ];
+
+    /// The theme after this one, wrapping around at the end.
+    pub fn next(self) -> Self {
+        let index = Self::ALL
+            .iter()
+            .position(|theme| *theme == self)
+            .unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(default)]
 pub struct Preferences {
+    pub schema_version: u32,
     pub show_on_launch: bool,
     pub launch_on_startup: bool,
     pub hotcorner_enabled: bool,
     pub hotcorner_corner: Corner,
     pub hotcorner_size: u32,
+    /// Turns every global shortcut in `shortcuts` on or off.
     pub shortcut_enabled: bool,
-    pub keyboard_shortcut: String,
+    pub shortcuts: Vec<ShortcutBinding>,
     pub auto_focus: bool,
     pub auto_hide_enabled: bool,
     pub auto_hide_delay_ms: u32,
@@ -49,22 +82,283 @@ pub struct Preferences {
     pub formatting_size: u32,
     pub theme: Theme,
     pub transparency: u32,
+    /// Minutes without reading or saving before encrypted notes lock again;
+    /// 0 keeps them unlocked until quit.
+    pub auto_lock_minutes: u32,
+    /// Serve the local scripting API; every request must carry the token
+    /// from `api-token` in the app data folder.
+    pub api_enabled: bool,
+    pub api_transport: ApiTransport,
+    pub api_port: u32,
+    /// The most detailed messages written to the log files.
+    pub log_level: LogLevel,
+    pub placement_policy: PlacementPolicy,
+    pub placement_corner: Corner,
     pub window_x: Option<i32>,
     pub window_y: Option<i32>,
     pub window_width: Option<u32>,
     pub window_height: Option<u32>,
+    /// Bounds saved for each monitor setup, most recently used first. The
+    /// `window_*` fields above are the last bounds on any setup.
+    pub window_placements: Vec<WindowPlacement>,
     pub sync: SyncPreferences,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Where the window was on one monitor setup, in physical pixels.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WindowPlacement {
+    /// `placement::layout_key` of the monitors connected when it was saved.
+    pub layout: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A preference that failed to validate or to take effect.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct FieldError {
+    /// Dotted key such as `text_size` or `shortcuts.1`; `None` when the
+    /// failure is not about one field, such as the file failing to save.
+    pub field: Option<String>,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: Some(field.into()),
+            message: message.into(),
+        }
+    }
+
+    pub fn general(message: impl Into<String>) -> Self {
+        Self {
+            field: None,
+            message: message.into(),
+        }
+    }
+}
+
+/// Preferences as saved, with the changes that couldn't take effect and were
+/// left at their previous values.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SavedPreferences {
+    pub preferences: Preferences,
+    pub warnings: Vec<FieldError>,
+}
+
+/// A global shortcut and what it does.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ShortcutBinding {
+    pub shortcut: String,
+    pub action: ShortcutAction,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ShortcutAction {
+    ToggleWindow,
+    ShowWindow,
+    HideWindow,
+    OpenPreferences,
+    /// Queues a sync of the active note to the enabled targets.
+    SyncNow,
+    /// Moves to the next theme in the list.
+    SwitchTheme,
+    /// Appends the clipboard text to the active note.
+    CaptureClipboard,
+    /// Appends the clipboard text as a bullet starting with the time.
+    CaptureBullet,
+}
+
+impl ShortcutAction {
+    pub fn label(self) -> &'static str {
+        match self {
+            ShortcutAction::ToggleWindow => "Show/hide note",
+            ShortcutAction::ShowWindow => "Show note",
+            ShortcutAction::HideWindow => "Hide note",
+            ShortcutAction::OpenPreferences => "Open preferences",
+            ShortcutAction::SyncNow => "Sync now",
+            ShortcutAction::SwitchTheme => "Switch theme",
+            ShortcutAction::CaptureClipboard => "Append clipboard",
+            ShortcutAction::CaptureBullet => "Append clipboard as bullet",
+        }
+    }
+}
+
+/// Modifier names in the order a normalized shortcut lists them, with the
+/// spellings the shortcut parser accepts for each.
+const SHORTCUT_MODIFIERS: [(&str, &[&str]); 5] = [
+    (
+        "CommandOrControl",
+        &[
+            "COMMANDORCONTROL",
+            "COMMANDORCTRL",
+            "CMDORCTRL",
+            "CMDORCONTROL",
+        ],
+    ),
+    ("Ctrl", &["CTRL", "CONTROL"]),
+    ("Alt", &["ALT", "OPTION"]),
+    ("Command", &["COMMAND", "CMD", "SUPER"]),
+    ("Shift", &["SHIFT"]),
+];
+
+/// Parses a key combination such as `cmd+alt+n` and returns it the way the
+/// shortcut recorder writes it, `Alt+Command+N`, so equal combinations
+/// compare equal.
+pub fn normalize_shortcut(shortcut: &str) -> Result<String, String> {
+    let trimmed = shortcut.trim();
+    if trimmed.is_empty() {
+        return Err("cannot be empty".to_string());
+    }
+
+    let mut modifiers = [false; SHORTCUT_MODIFIERS.len()];
+    let mut key = None;
+
+    for token in trimmed.split('+').map(str::trim) {
+        if token.is_empty() {
+            return Err(format!("\"{}\" has an empty part", trimmed));
+        }
+        if key.is_some() {
+            return Err(format!(
+                "\"{}\" must end with a single key after its modifiers",
+                trimmed
+            ));
+        }
+
+        let upper = token.to_uppercase();
+        match SHORTCUT_MODIFIERS
+            .iter()
+            .position(|(_, aliases)| aliases.contains(&upper.as_str()))
+        {
+            Some(index) => modifiers[index] = true,
+            None => key = Some(token),
+        }
+    }
+
+    let key = key.ok_or_else(|| format!("\"{}\" has no key besides its modifiers", trimmed))?;
+    let is_function_key =
+        key.len() > 1 && key.starts_with(['F', 'f']) && key[1..].parse::<u8>().is_ok();
+
+    // A bare letter would fire on every keystroke in every app
+    if !modifiers.contains(&true) && !is_function_key {
+        return Err(format!(
+            "\"{}\" needs a modifier such as Command or Ctrl",
+            trimmed
+        ));
+    }
+
+    let key = if key.chars().count() == 1 || is_function_key {
+        key.to_uppercase()
+    } else {
+        key.to_string()
+    };
+    let normalized = SHORTCUT_MODIFIERS
+        .iter()
+        .zip(modifiers)
+        .filter(|(_, used)| *used)
+        .map(|((name, _), _)| *name)
+        .chain([key.as_str()])
+        .collect::<Vec<_>>()
+        .join("+");
+
+    normalized
+        .parse::<Shortcut>()
+        .map_err(|_| format!("\"{}\" uses an unknown key {}", trimmed, key))?;
+
+    Ok(normalized)
+}
+
+/// Where the local scripting API listens.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ApiTransport {
+    /// `api/api.sock` in the app data folder, readable only by the current user.
+    #[default]
+    Socket,
+    /// `127.0.0.1` on `api_port`, for tools that cannot use a Unix socket.
+    Loopback,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub fn filter(self) -> log::LevelFilter {
+        match self {
+            LogLevel::Error => log::LevelFilter::Error,
+            LogLevel::Warn => log::LevelFilter::Warn,
+            LogLevel::Info => log::LevelFilter::Info,
+            LogLevel::Debug => log::LevelFilter::Debug,
+            LogLevel::Trace => log::LevelFilter::Trace,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum MarkdownMode {
+    /// The app overwrites the file; edits made elsewhere are discarded.
+    #[default]
+    OneWay,
+    /// Edits made to the file outside the app are imported back into the note.
+    TwoWay,
+    /// Each day's content goes to its own `YYYY-MM-DD.md` file.
+    Journal,
+}
+
+/// How a file export records where it came from.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum MetadataStyle {
+    /// `---` front matter with real keys, as read by Obsidian, Jekyll and Hugo.
+    /// HTML pages carry the same keys as `<meta>` tags.
+    Yaml,
+    /// A single `<!-- source: ... -->` line above the body. HTML pages get a
+    /// generator tag and a banner instead, and text files a plain line.
+    #[default]
+    HtmlComment,
+    None,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(default)]
 pub struct SyncPreferences {
     pub markdown_enabled: bool,
     pub markdown_path: Option<String>,
-    pub include_metadata: bool,
+    pub markdown_mode: MarkdownMode,
+    /// File name for the Markdown export; `{title}`, `{id}` and `{date}` are
+    /// replaced with the note's title, ID and creation date.
+    pub markdown_filename: String,
+    pub markdown_metadata: MetadataStyle,
+    /// In journal mode, keep the day's file and update this note's entry in
+    /// it rather than replacing the whole file.
+    pub markdown_journal_append: bool,
+    pub html_enabled: bool,
+    pub html_path: Option<String>,
+    /// Like `markdown_filename`, for the HTML export.
+    pub html_filename: String,
+    pub html_metadata: MetadataStyle,
+    pub text_enabled: bool,
+    pub text_path: Option<String>,
+    /// Like `markdown_filename`, for the plain text export.
+    pub text_filename: String,
+    pub text_metadata: MetadataStyle,
     pub apple_notes_enabled: bool,
     pub apple_notes_title: String,
     pub apple_notes_folder: String,
+    /// Adds a source and sync time line to the Apple Notes copy.
+    pub apple_notes_metadata: bool,
 }
 
 impl Default for SyncPreferences {
@@ -72,10 +366,22 @@ impl Default for SyncPreferences {
         Self {
             markdown_enabled: false,
             markdown_path: None,
-            include_metadata: true,
+            markdown_mode: MarkdownMode::default(),
+            markdown_filename: DEFAULT_EXPORT_FILENAME.to_string(),
+            markdown_metadata: MetadataStyle::default(),
+            markdown_journal_append: false,
+            html_enabled: false,
+            html_path: None,
+            html_filename: DEFAULT_EXPORT_FILENAME.to_string(),
+            html_metadata: MetadataStyle::default(),
+            text_enabled: false,
+            text_path: None,
+            text_filename: DEFAULT_EXPORT_FILENAME.to_string(),
+            text_metadata: MetadataStyle::default(),
             apple_notes_enabled: false,
             apple_notes_title: "What The Note".to_string(),
             apple_notes_folder: "Notes".to_string(),
+            apple_notes_metadata: true,
         }
     }
 }
@@ -83,13 +389,17 @@ impl Default for SyncPreferences {
 impl Default for Preferences {
     fn default() -> Self {
         Self {
+            schema_version: CURRENT_SCHEMA_VERSION,
             show_on_launch: false,
             launch_on_startup: true,
             hotcorner_enabled: true,
             hotcorner_corner: Corner::TopRight,
             hotcorner_size: 10,
             shortcut_enabled: true,
-            keyboard_shortcut: "Alt+Command+N".to_string(),
+            shortcuts: vec![ShortcutBinding {
+                shortcut: "Alt+Command+N".to_string(),
+                action: ShortcutAction::ToggleWindow,
+            }],
             auto_focus: true,
             auto_hide_enabled: false,
             auto_hide_delay_ms: 5000,
@@ -99,10 +409,18 @@ impl Default for Preferences {
             formatting_size: 100,
             theme: Theme::default(),
             transparency: 10,
+            auto_lock_minutes: 15,
+            api_enabled: false,
+            api_transport: ApiTransport::default(),
+            api_port: 47321,
+            log_level: LogLevel::default(),
+            placement_policy: PlacementPolicy::default(),
+            placement_corner: Corner::TopRight,
             window_x: None,
             window_y: None,
             window_width: None,
             window_height: None,
+            window_placements: Vec::new(),
             sync: SyncPreferences::default(),
         }
     }
@@ -110,58 +428,306 @@ impl Default for Preferences {
 
 impl Preferences {
     pub fn validate(&self) -> Result<(), String> {
-        if self.hotcorner_size == 0 || self.hotcorner_size > 100 {
-            return Err("Hot corner size must be between 1 and 100 pixels".to_string());
+        match self.field_errors().into_iter().next() {
+            Some(error) => Err(error.message),
+            None => Ok(()),
         }
+    }
 
-        if self.auto_hide_delay_ms < 250 || self.auto_hide_delay_ms > 300000 {
-            return Err("Auto-hide delay must be between 250ms and 300000ms".to_string());
-        }
+    /// Every field `validate` would reject, in the order it checks them.
+    pub fn field_errors(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+
+        require(
+            &mut errors,
+            (1..=100).contains(&self.hotcorner_size),
+            "hotcorner_size",
+            "Hot corner size must be between 1 and 100 pixels",
+        );
+        require(
+            &mut errors,
+            (250..=300000).contains(&self.auto_hide_delay_ms),
+            "auto_hide_delay_ms",
+            "Auto-hide delay must be between 250ms and 300000ms",
+        );
+        require(
+            &mut errors,
+            self.fade_duration_ms <= 2000,
+            "fade_duration_ms",
+            "Fade duration must be 2000ms or less",
+        );
 
-        if self.fade_duration_ms > 2000 {
-            return Err("Fade duration must be 2000ms or less".to_string());
+        if let Err(error) = self.normalized_shortcuts() {
+            errors.push(error);
         }
 
-        if self.keyboard_shortcut.is_empty() {
-            return Err("Keyboard shortcut cannot be empty".to_string());
+        require(
+            &mut errors,
+            (8..=32).contains(&self.text_size),
+            "text_size",
+            "Text size must be between 8px and 32px",
+        );
+        require(
+            &mut errors,
+            (50..=150).contains(&self.formatting_size),
+            "formatting_size",
+            "Formatting size must be between 50% and 150%",
+        );
+        require(
+            &mut errors,
+            self.transparency <= 100,
+            "transparency",
+            "Transparency must be between 0 and 100",
+        );
+        require(
+            &mut errors,
+            self.auto_lock_minutes <= 1440,
+            "auto_lock_minutes",
+            "Auto-lock must be 1440 minutes or less",
+        );
+        require(
+            &mut errors,
+            (1024..=65535).contains(&self.api_port),
+            "api_port",
+            "API port must be between 1024 and 65535",
+        );
+
+        errors.extend(self.sync.field_errors());
+        errors
+    }
+
+    /// Rewrites every shortcut in its normalized form, failing on the first
+    /// binding that `validate` would reject.
+    pub fn normalize_shortcuts(&mut self) -> Result<(), String> {
+        let normalized = self.normalized_shortcuts().map_err(|error| error.message)?;
+        for (binding, shortcut) in self.shortcuts.iter_mut().zip(normalized) {
+            binding.shortcut = shortcut;
         }
+        Ok(())
+    }
+
+    /// Normalizes each binding's shortcut, naming the first that does not
+    /// parse or repeats an earlier combination.
+    fn normalized_shortcuts(&self) -> Result<Vec<String>, FieldError> {
+        let mut seen: Vec<(String, ShortcutAction)> = Vec::new();
 
-        if self.text_size < 8 || self.text_size > 32 {
-            return Err("Text size must be between 8px and 32px".to_string());
+        for (index, binding) in self.shortcuts.iter().enumerate() {
+            let field = format!("shortcuts.{}", index);
+            let label = binding.action.label();
+            let normalized = normalize_shortcut(&binding.shortcut)
+                .map_err(|err| FieldError::new(&field, format!("{} shortcut {}", label, err)))?;
+
+            if let Some((_, first)) = seen.iter().find(|(shortcut, _)| *shortcut == normalized) {
+                return Err(FieldError::new(
+                    &field,
+                    format!(
+                        "Shortcut {} is set for both {} and {}",
+                        normalized,
+                        first.label(),
+                        label
+                    ),
+                ));
+            }
+            seen.push((normalized, binding.action));
         }
 
-        if self.formatting_size < 50 || self.formatting_size > 150 {
-            return Err("Formatting size must be between 50% and 150%".to_string());
+        Ok(seen.into_iter().map(|(shortcut, _)| shortcut).collect())
+    }
+
+    /// Clamps or resets every field that `validate` would reject, returning a
+    /// description of each change. Used when loading a file written by hand or
+    /// by an older version, so a bad value degrades instead of blocking startup.
+    pub fn sanitize(&mut self) -> Vec<String> {
+        let mut changes = Vec::new();
+
+        clamp_field(
+            &mut self.hotcorner_size,
+            1,
+            100,
+            "hotcorner_size",
+            &mut changes,
+        );
+        clamp_field(
+            &mut self.auto_hide_delay_ms,
+            250,
+            300000,
+            "auto_hide_delay_ms",
+            &mut changes,
+        );
+        clamp_field(
+            &mut self.fade_duration_ms,
+            0,
+            2000,
+            "fade_duration_ms",
+            &mut changes,
+        );
+        clamp_field(&mut self.text_size, 8, 32, "text_size", &mut changes);
+        clamp_field(
+            &mut self.formatting_size,
+            50,
+            150,
+            "formatting_size",
+            &mut changes,
+        );
+        clamp_field(&mut self.transparency, 0, 100, "transparency", &mut changes);
+        clamp_field(
+            &mut self.auto_lock_minutes,
+            0,
+            1440,
+            "auto_lock_minutes",
+            &mut changes,
+        );
+        clamp_field(&mut self.api_port, 1024, 65535, "api_port", &mut changes);
+
+        let mut shortcuts: Vec<ShortcutBinding> = Vec::new();
+        for mut binding in std::mem::take(&mut self.shortcuts) {
+            let label = binding.action.label();
+            match normalize_shortcut(&binding.shortcut) {
+                Ok(normalized) if shortcuts.iter().any(|kept| kept.shortcut == normalized) => {
+                    changes.push(format!(
+                        "shortcut {} for {} repeats an earlier one, removed",
+                        normalized, label
+                    ));
+                }
+                Ok(normalized) => {
+                    if normalized != binding.shortcut {
+                        changes.push(format!(
+                            "shortcut {:?} for {} normalized to {}",
+                            binding.shortcut, label, normalized
+                        ));
+                        binding.shortcut = normalized;
+                    }
+                    shortcuts.push(binding);
+                }
+                Err(err) => changes.push(format!("{} shortcut {}, removed", label, err)),
+            }
         }
+        self.shortcuts = shortcuts;
 
-        if self.transparency > 100 {
-            return Err("Transparency must be between 0 and 100".to_string());
+        changes.extend(self.sync.sanitize());
+
+        for change in &changes {
+            warn!("Preferences: {}", change);
         }
 
-        self.sync.validate()?;
+        changes
+    }
+}
 
-        Ok(())
+fn require(errors: &mut Vec<FieldError>, ok: bool, field: &str, message: &str) {
+    if !ok {
+        errors.push(FieldError::new(field, message));
+    }
+}
+
+fn clamp_field(value: &mut u32, min: u32, max: u32, name: &str, changes: &mut Vec<String>) {
+    let clamped = (*value).clamp(min, max);
+    if clamped != *value {
+        changes.push(format!(
+            "{} {} out of range, clamped to {}",
+            name, value, clamped
+        ));
+        *value = clamped;
     }
 }
 
 impl SyncPreferences {
     pub fn is_any_enabled(&self) -> bool {
-        self.markdown_enabled || self.apple_notes_enabled
+        self.markdown_enabled || self.html_enabled || self.text_enabled || self.apple_notes_enabled
+    }
+
+    pub fn is_markdown_two_way(&self) -> bool {
+        self.markdown_enabled && self.markdown_mode == MarkdownMode::TwoWay
     }
 
-    fn validate(&self) -> Result<(), String> {
-        if self.markdown_enabled && self.apple_notes_enabled {
-            return Err("Only one sync target can be enabled at a time".to_string());
+    fn sanitize(&mut self) -> Vec<String> {
+        let defaults = SyncPreferences::default();
+        let mut changes = Vec::new();
+
+        for (name, filename) in [
+            ("markdown_filename", &mut self.markdown_filename),
+            ("html_filename", &mut self.html_filename),
+            ("text_filename", &mut self.text_filename),
+        ] {
+            if filename.trim().is_empty() {
+                *filename = DEFAULT_EXPORT_FILENAME.to_string();
+                changes.push(format!("sync.{} was empty, reset to default", name));
+            }
         }
 
         if self.apple_notes_enabled && self.apple_notes_title.trim().is_empty() {
-            return Err("Apple Notes title cannot be empty".to_string());
+            self.apple_notes_title = defaults.apple_notes_title;
+            changes.push("sync.apple_notes_title was empty, reset to default".to_string());
         }
 
         if self.apple_notes_enabled && self.apple_notes_folder.trim().is_empty() {
-            return Err("Apple Notes folder cannot be empty".to_string());
+            self.apple_notes_folder = defaults.apple_notes_folder;
+            changes.push("sync.apple_notes_folder was empty, reset to default".to_string());
         }
 
-        Ok(())
+        changes
+    }
+
+    fn field_errors(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+
+        require(
+            &mut errors,
+            !(self.apple_notes_enabled && self.apple_notes_title.trim().is_empty()),
+            "sync.apple_notes_title",
+            "Apple Notes title cannot be empty",
+        );
+        require(
+            &mut errors,
+            !(self.apple_notes_enabled && self.apple_notes_folder.trim().is_empty()),
+            "sync.apple_notes_folder",
+            "Apple Notes folder cannot be empty",
+        );
+
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_shortcuts_and_names_bad_bindings() {
+        assert_eq!(normalize_shortcut("cmd+alt+n").unwrap(), "Alt+Command+N");
+        assert_eq!(
+            normalize_shortcut(" shift + ctrl + f5 ").unwrap(),
+            "Ctrl+Shift+F5"
+        );
+        assert_eq!(normalize_shortcut("F13").unwrap(), "F13");
+        for bad in [
+            "",
+            "N",
+            "Ctrl+C+Shift",
+            "Command+",
+            "Command+Nope",
+            "Alt+Shift",
+        ] {
+            assert!(normalize_shortcut(bad).is_err(), "{:?} should fail", bad);
+        }
+
+        let mut prefs = Preferences::default();
+        prefs.shortcuts.push(ShortcutBinding {
+            shortcut: "Command+Nope".to_string(),
+            action: ShortcutAction::SyncNow,
+        });
+        let err = prefs.validate().unwrap_err();
+        assert!(err.starts_with("Sync now shortcut"), "{}", err);
+
+        prefs.shortcuts[1].shortcut = "cmd+option+n".to_string();
+        let err = prefs.normalize_shortcuts().unwrap_err();
+        assert_eq!(
+            err,
+            "Shortcut Alt+Command+N is set for both Show/hide note and Sync now"
+        );
+
+        prefs.shortcuts[1].shortcut = "ctrl+alt+s".to_string();
+        prefs.normalize_shortcuts().expect("normalize");
+        assert_eq!(prefs.shortcuts[1].shortcut, "Ctrl+Alt+S");
     }
 }
@@ -0,0 +1,26 @@
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SearchSource {
+    Note,
+    File,
+}
+
+/// A run of snippet text; `highlight` marks the parts that matched the query.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SnippetPart {
+    pub text: String,
+    pub highlight: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub source: SearchSource,
+    /// Set for notes; files are identified by `path`.
+    pub note_id: Option<String>,
+    pub path: Option<String>,
+    pub title: String,
+    pub score: f32,
+    pub snippet: Vec<SnippetPart>,
+}
@@ -0,0 +1,694 @@
+use crate::commands::window;
+use crate::error::{AppError, ErrorCode};
+use crate::models::preferences::{ApiTransport, Preferences};
+use crate::services::{
+    capture, html_text::NoteInput, persist, storage::StorageService, sync::SyncQueue,
+};
+use log::error;
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use uuid::Uuid;
+
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+
+/// The socket sits in its own owner-only folder, so it is never reachable
+/// by other users, even between `bind` and any later permission change.
+pub const SOCKET_DIRNAME: &str = "api";
+pub const SOCKET_FILENAME: &str = "api.sock";
+pub const TOKEN_FILENAME: &str = "api-token";
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(100);
+/// Every request is answered without waiting on exports, so the server and
+/// its clients give up after the same time.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+const MAX_LINE_BYTES: u64 = 8 * 1024;
+const MAX_HEADERS: usize = 64;
+
+/// Where a running server listens.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Endpoint {
+    Socket(PathBuf),
+    Loopback(u16),
+}
+
+impl Endpoint {
+    pub fn from_preferences(prefs: &Preferences, data_dir: &Path) -> Self {
+        match prefs.api_transport {
+            ApiTransport::Socket => {
+                Self::Socket(data_dir.join(SOCKET_DIRNAME).join(SOCKET_FILENAME))
+            }
+            ApiTransport::Loopback => Self::Loopback(prefs.api_port as u16),
+        }
+    }
+}
+
+struct Running {
+    endpoint: Endpoint,
+    stop: Arc<AtomicBool>,
+}
+
+/// Opt-in local server that lets scripts and editors read and change the
+/// active note. It speaks a small subset of HTTP/1.1 with JSON bodies, and
+/// every request must send `Authorization: Bearer <token>`.
+pub struct ApiServer {
+    data_dir: PathBuf,
+    running: Mutex<Option<Running>>,
+}
+
+impl ApiServer {
+    pub fn new(app_handle: &AppHandle) -> Result<Self, String> {
+        let data_dir = app_handle
+            .path()
+            .app_data_dir()
+            .map_err(|e| format!("Failed to get data directory: {}", e))?;
+
+        Ok(Self {
+            data_dir,
+            running: Mutex::new(None),
+        })
+    }
+
+    /// Starts, stops or moves the server to match `prefs`. Does nothing when
+    /// it is already serving the configured endpoint.
+    pub fn apply(&self, app: &AppHandle, prefs: &Preferences) -> Result<(), String> {
+        let mut running = self
+            .running
+            .lock()
+            .map_err(|e| format!("Failed to lock API server state: {}", e))?;
+
+        let wanted = prefs
+            .api_enabled
+            .then(|| Endpoint::from_preferences(prefs, &self.data_dir));
+
+        if running.as_ref().map(|r| &r.endpoint) == wanted.as_ref() {
+            return Ok(());
+        }
+
+        if let Some(previous) = running.take() {
+            previous.stop.store(true, Ordering::SeqCst);
+            // Unlinked here rather than by the exiting thread, which could
+            // otherwise remove a socket just bound at the same path
+            if let Endpoint::Socket(path) = previous.endpoint {
+                let _ = fs::remove_file(path);
+            }
+        }
+
+        let Some(endpoint) = wanted else {
+            return Ok(());
+        };
+
+        let token = load_or_create_token(&self.data_dir)?;
+        let listener = Listener::bind(&endpoint)?;
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread_stop = Arc::clone(&stop);
+        let app = app.clone();
+        thread::spawn(move || listener.serve(&app, token.into(), &thread_stop));
+
+        *running = Some(Running { endpoint, stop });
+        Ok(())
+    }
+}
+
+/// Reads the shared secret, generating one the first time the API is enabled.
+pub fn load_or_create_token(data_dir: &Path) -> Result<String, String> {
+    let path = data_dir.join(TOKEN_FILENAME);
+
+    if let Ok(token) = fs::read_to_string(&path) {
+        let token = token.trim();
+        if !token.is_empty() {
+            return Ok(token.to_string());
+        }
+    }
+
+    fs::create_dir_all(data_dir).map_err(|e| format!("Failed to create data directory: {}", e))?;
+
+    let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+    persist::write_atomic_private(&path, &token)
+        .map_err(|e| format!("Failed to write API token: {}", e))?;
+
+    Ok(token)
+}
+
+/// Creates `dir` readable only by the current user, tightening it if an
+/// earlier run left it more open.
+#[cfg(unix)]
+fn create_private_dir(dir: &Path) -> Result<(), String> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+        .and_then(|_| fs::set_permissions(dir, fs::Permissions::from_mode(0o700)))
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))
+}
+
+/// Talks to a running app's API, as the `wtn` command line tool does.
+pub struct ApiClient {
+    endpoint: Endpoint,
+    token: String,
+}
+
+impl ApiClient {
+    /// `None` when the API is turned off or has never been started.
+    pub fn for_preferences(prefs: &Preferences, data_dir: &Path) -> Option<Self> {
+        if !prefs.api_enabled {
+            return None;
+        }
+
+        let token = fs::read_to_string(data_dir.join(TOKEN_FILENAME)).ok()?;
+        let token = token.trim();
+
+        (!token.is_empty()).then(|| Self {
+            endpoint: Endpoint::from_preferences(prefs, data_dir),
+            token: token.to_string(),
+        })
+    }
+
+    /// Sends one request and returns the JSON reply. `Ok(None)` means nothing
+    /// is listening, i.e. the app is not running.
+    pub fn send(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&Value>,
+    ) -> Result<Option<Value>, String> {
+        let Some(mut stream) = self.connect() else {
+            return Ok(None);
+        };
+
+        let body = body.map(Value::to_string).unwrap_or_default();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            method,
+            path,
+            self.token,
+            body.len(),
+            body
+        )
+        .and_then(|_| stream.flush())
+        .map_err(|e| format!("Failed to send API request: {}", e))?;
+
+        let mut response = String::new();
+        stream
+            .read_to_string(&mut response)
+            .map_err(|e| format!("Failed to read API response: {}", e))?;
+
+        let (head, body) = response
+            .split_once("\r\n\r\n")
+            .ok_or_else(|| "Malformed API response".to_string())?;
+        let status: u16 = head
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(|| "Malformed API response status".to_string())?;
+        let value: Value = serde_json::from_str(body)
+            .map_err(|e| format!("Failed to parse API response: {}", e))?;
+
+        if status == 200 {
+            return Ok(Some(value));
+        }
+
+        Err(value["error"]
+            .as_str()
+            .unwrap_or("API request failed")
+            .to_string())
+    }
+
+    fn connect(&self) -> Option<Box<dyn Stream>> {
+        let stream: Box<dyn Stream> = match &self.endpoint {
+            Endpoint::Loopback(port) => {
+                let stream = TcpStream::connect((Ipv4Addr::LOCALHOST, *port)).ok()?;
+                stream.set_read_timeout(Some(READ_TIMEOUT)).ok()?;
+                Box::new(stream)
+            }
+            #[cfg(unix)]
+            Endpoint::Socket(path) => {
+                let stream = UnixStream::connect(path).ok()?;
+                stream.set_read_timeout(Some(READ_TIMEOUT)).ok()?;
+                Box::new(stream)
+            }
+            #[cfg(not(unix))]
+            Endpoint::Socket(_) => return None,
+        };
+
+        Some(stream)
+    }
+}
+
+trait Stream: Read + Write + Send {}
+impl<T: Read + Write + Send> Stream for T {}
+
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+impl Listener {
+    fn bind(endpoint: &Endpoint) -> Result<Self, String> {
+        let listener = match endpoint {
+            Endpoint::Loopback(port) => TcpListener::bind((Ipv4Addr::LOCALHOST, *port))
+                .map(Self::Tcp)
+                .map_err(|e| format!("Failed to listen on port {}: {}", port, e))?,
+            #[cfg(unix)]
+            Endpoint::Socket(path) => {
+                if let Some(dir) = path.parent() {
+                    create_private_dir(dir)?;
+                }
+                // A socket left by a previous run would make bind fail
+                let _ = fs::remove_file(path);
+                let listener = UnixListener::bind(path)
+                    .map_err(|e| format!("Failed to listen on {}: {}", path.display(), e))?;
+                Self::Unix(listener)
+            }
+            #[cfg(not(unix))]
+            Endpoint::Socket(_) => {
+                return Err("Unix sockets are not supported here; use loopback".to_string())
+            }
+        };
+
+        // Non-blocking so the loop can notice a stop request between clients
+        let result = match &listener {
+            Self::Tcp(listener) => listener.set_nonblocking(true),
+            #[cfg(unix)]
+            Self::Unix(listener) => listener.set_nonblocking(true),
+        };
+        result.map_err(|e| format!("Failed to configure API listener: {}", e))?;
+
+        Ok(listener)
+    }
+
+    fn accept(&self) -> io::Result<Box<dyn Stream>> {
+        match self {
+            Self::Tcp(listener) => {
+                let (stream, _) = listener.accept()?;
+                stream.set_nonblocking(false)?;
+                stream.set_read_timeout(Some(READ_TIMEOUT))?;
+                Ok(Box::new(stream))
+            }
+            #[cfg(unix)]
+            Self::Unix(listener) => {
+                let (stream, _) = listener.accept()?;
+                stream.set_nonblocking(false)?;
+                stream.set_read_timeout(Some(READ_TIMEOUT))?;
+                Ok(Box::new(stream))
+            }
+        }
+    }
+
+    fn serve(self, app: &AppHandle, token: Arc<str>, stop: &AtomicBool) {
+        while !stop.load(Ordering::SeqCst) {
+            match self.accept() {
+                Ok(stream) => {
+                    // Each client gets its own thread so a slow one cannot
+                    // hold up the rest until its read times out
+                    let app = app.clone();
+                    let token = Arc::clone(&token);
+                    thread::spawn(move || {
+                        if let Err(e) = handle_connection(stream, &app, &token) {
+                            error!("API request failed: {}", e);
+                        }
+                    });
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(ACCEPT_INTERVAL),
+                Err(e) => {
+                    error!("Failed to accept API connection: {}", e);
+                    thread::sleep(ACCEPT_INTERVAL);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Request {
+    method: String,
+    path: String,
+    bearer: Option<String>,
+    body: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Route {
+    GetNote,
+    SetNote,
+    AppendNote,
+    ClearNote,
+    ShowWindow,
+    HideWindow,
+    Sync,
+}
+
+impl Route {
+    fn parse(method: &str, path: &str) -> Option<Self> {
+        let path = path.split('?').next().unwrap_or(path).trim_end_matches('/');
+
+        match (method, path) {
+            ("GET", "/note") => Some(Self::GetNote),
+            ("PUT", "/note") => Some(Self::SetNote),
+            ("POST", "/note/append") => Some(Self::AppendNote),
+            ("DELETE", "/note") => Some(Self::ClearNote),
+            ("POST", "/window/show") => Some(Self::ShowWindow),
+            ("POST", "/window/hide") => Some(Self::HideWindow),
+            ("POST", "/sync") => Some(Self::Sync),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ApiError {
+    status: u16,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<String> for ApiError {
+    fn from(message: String) -> Self {
+        Self::new(500, message)
+    }
+}
+
+impl From<AppError> for ApiError {
+    fn from(err: AppError) -> Self {
+        let status = match err.code {
+            ErrorCode::Invalid => 400,
+            ErrorCode::PermissionDenied => 403,
+            ErrorCode::NotFound => 404,
+            ErrorCode::Conflict | ErrorCode::NotConfigured => 409,
+            ErrorCode::Locked => 423,
+            ErrorCode::NotImplemented => 501,
+            ErrorCode::Io | ErrorCode::Script | ErrorCode::Internal => 500,
+        };
+        Self::new(status, err.message)
+    }
+}
+
+fn handle_connection(stream: Box<dyn Stream>, app: &AppHandle, token: &str) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+
+    let (status, body) = match read_request(&mut reader) {
+        Ok(request) => match respond(&request, app, token) {
+            Ok(value) => (200, value),
+            Err(err) => (err.status, json!({ "error": err.message })),
+        },
+        Err(err) => (err.status, json!({ "error": err.message })),
+    };
+
+    write_response(reader.get_mut(), status, &body)
+}
+
+fn respond(request: &Request, app: &AppHandle, token: &str) -> Result<Value, ApiError> {
+    if !request
+        .bearer
+        .as_deref()
+        .is_some_and(|bearer| tokens_match(bearer, token))
+    {
+        return Err(ApiError::new(401, "Missing or invalid API token"));
+    }
+
+    let route = Route::parse(&request.method, &request.path)
+        .ok_or_else(|| ApiError::new(404, format!("No such endpoint: {}", request.path)))?;
+
+    let storage = app.state::<StorageService>();
+
+    match route {
+        Route::GetNote => {
+            let note = storage.active_note()?;
+            let content = storage.read_note_by_id(&note.id)?;
+            Ok(json!({ "id": note.id, "title": note.title, "content": content }))
+        }
+        Route::SetNote => {
+            let input = parse_input(&request.body)?;
+            write_active(app, &input.to_html())
+        }
+        Route::AppendNote => {
+            let html = parse_input(&request.body)?.to_html();
+            let (id, content) =
+                capture::update_active(app, |existing| format!("{}{}", existing, html))?;
+            Ok(json!({ "id": id, "bytes": content.len() }))
+        }
+        Route::ClearNote => write_active(app, ""),
+        Route::ShowWindow => {
+            window::show_window_command(app.clone())?;
+            Ok(json!({ "ok": true }))
+        }
+        Route::HideWindow => {
+            window::hide_window_command(app.clone())?;
+            Ok(json!({ "ok": true }))
+        }
+        Route::Sync => {
+            // Queued like the Sync Now shortcut, so it's retried and reported
+            // like any other export
+            let id = storage.active_note_id()?;
+            app.state::<SyncQueue>().enqueue(app, &id)?;
+            Ok(json!({ "ok": true }))
+        }
+    }
+}
+
+fn write_active(app: &AppHandle, content: &str) -> Result<Value, ApiError> {
+    let id = capture::write_active(app, content)?;
+    Ok(json!({ "id": id, "bytes": content.len() }))
+}
+
+fn parse_input(body: &[u8]) -> Result<NoteInput, ApiError> {
+    serde_json::from_slice(body).map_err(|e| ApiError::new(400, format!("Invalid body: {}", e)))
+}
+
+/// Reads one line of at most `MAX_LINE_BYTES`, so a client cannot grow the
+/// buffer by never sending a newline.
+fn read_line(reader: &mut impl BufRead, line: &mut String) -> Result<usize, ApiError> {
+    line.clear();
+    let read = reader
+        .by_ref()
+        .take(MAX_LINE_BYTES + 1)
+        .read_line(line)
+        .map_err(|e| ApiError::new(400, e.to_string()))?;
+
+    if read as u64 > MAX_LINE_BYTES {
+        return Err(ApiError::new(431, "Request line or header is too long"));
+    }
+    Ok(read)
+}
+
+fn read_request(reader: &mut impl BufRead) -> Result<Request, ApiError> {
+    let invalid = |message: &str| ApiError::new(400, message);
+
+    let mut line = String::new();
+    read_line(reader, &mut line)?;
+    let mut parts = line.split_whitespace();
+    let method = parts
+        .next()
+        .ok_or_else(|| invalid("Empty request"))?
+        .to_string();
+    let path = parts
+        .next()
+        .ok_or_else(|| invalid("Missing request path"))?
+        .to_string();
+
+    let mut bearer = None;
+    let mut content_length = 0;
+    let mut headers = 0;
+
+    loop {
+        if read_line(reader, &mut line)? == 0 {
+            break;
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+
+        headers += 1;
+        if headers > MAX_HEADERS {
+            return Err(ApiError::new(431, "Too many request headers"));
+        }
+
+        let Some((name, value)) = header.split_once(':') else {
+            return Err(invalid("Malformed header"));
+        };
+        let value = value.trim();
+
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value
+                .parse()
+                .map_err(|_| invalid("Invalid Content-Length"))?;
+        } else if name.eq_ignore_ascii_case("authorization") {
+            bearer = value.strip_prefix("Bearer ").map(str::to_string);
+        }
+    }
+
+    if content_length > MAX_BODY_BYTES {
+        return Err(invalid("Request body is too large"));
+    }
+
+    let mut body = vec![0; content_length];
+    reader
+        .read_exact(&mut body)
+        .map_err(|e| ApiError::new(400, e.to_string()))?;
+
+    Ok(Request {
+        method,
+        path,
+        bearer,
+        body,
+    })
+}
+
+fn write_response(stream: &mut impl Write, status: u16, body: &Value) -> io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        409 => "Conflict",
+        423 => "Locked",
+        431 => "Request Header Fields Too Large",
+        501 => "Not Implemented",
+        _ => "Internal Server Error",
+    };
+    let body = body.to_string();
+
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// Compares without stopping at the first difference, so response timing
+/// does not reveal how much of a guessed token was right.
+fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use tempfile::tempdir;
+
+    #[test]
+    fn parses_request_with_token_and_body() {
+        let raw = "POST /note/append HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer abc\r\ncontent-length: 16\r\n\r\n{\"content\":\"hi\"}";
+        let request = read_request(&mut Cursor::new(raw)).expect("parse");
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(
+            Route::parse(&request.method, &request.path),
+            Some(Route::AppendNote)
+        );
+        assert_eq!(request.bearer.as_deref(), Some("abc"));
+        assert_eq!(request.body, br#"{"content":"hi"}"#);
+        assert_eq!(Route::parse("GET", "/note/"), Some(Route::GetNote));
+        assert_eq!(Route::parse("GET", "/sync"), None);
+    }
+
+    #[test]
+    fn oversized_lines_and_header_counts_are_refused_with_431() {
+        let long_path = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(10_000));
+        let err = read_request(&mut Cursor::new(long_path)).expect_err("long line");
+        assert_eq!(err.status, 431);
+
+        let many_headers = format!("GET /note HTTP/1.1\r\n{}\r\n", "X-A: b\r\n".repeat(100));
+        let err = read_request(&mut Cursor::new(many_headers)).expect_err("many headers");
+        assert_eq!(err.status, 431);
+    }
+
+    #[test]
+    fn writes_to_locked_notes_are_refused_with_423() {
+        let dir = tempdir().expect("tempdir");
+        let storage = StorageService::open(dir.path()).expect("open storage");
+        let id = storage.active_note_id().expect("active note");
+        storage.enable_encryption("correct horse").expect("encrypt");
+        storage.lock().expect("lock");
+
+        let written = storage.write_note_by_id(&id, "<p>hi</p>");
+        assert_eq!(ApiError::from(written.expect_err("locked")).status, 423);
+
+        let appended = storage.update_note_by_id(&id, |existing| format!("{}<p>hi</p>", existing));
+        assert_eq!(ApiError::from(appended.expect_err("locked")).status, 423);
+    }
+
+    #[test]
+    fn token_is_created_once_and_compared_exactly() {
+        let dir = tempdir().expect("tempdir");
+        let token = load_or_create_token(dir.path()).expect("create");
+
+        assert_eq!(token.len(), 64);
+        assert_eq!(load_or_create_token(dir.path()).expect("reload"), token);
+        assert!(tokens_match(&token, &token));
+        assert!(!tokens_match(&token[..63], &token));
+        assert!(!tokens_match(&token.replace(&token[..1], "-"), &token));
+    }
+
+    #[test]
+    fn client_round_trips_through_a_server() {
+        let dir = tempdir().expect("tempdir");
+        let token = load_or_create_token(dir.path()).expect("token");
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let port = listener.local_addr().expect("addr").port();
+
+        let expected = token.clone();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().expect("accept");
+            let mut reader = BufReader::new(stream);
+            let request = read_request(&mut reader).expect("request");
+            let (status, body) = if request.bearer.as_deref() == Some(expected.as_str()) {
+                let input: NoteInput = serde_json::from_slice(&request.body).expect("body");
+                (200, json!({ "html": input.to_html() }))
+            } else {
+                (401, json!({ "error": "Missing or invalid API token" }))
+            };
+            write_response(reader.get_mut(), status, &body).expect("respond");
+        });
+
+        let prefs = Preferences {
+            api_enabled: true,
+            api_transport: ApiTransport::Loopback,
+            api_port: u32::from(port),
+            ..Preferences::default()
+        };
+        let client = ApiClient::for_preferences(&prefs, dir.path()).expect("client");
+        let reply = client
+            .send("POST", "/note/append", Some(&json!({ "content": "hi" })))
+            .expect("send")
+            .expect("server reachable");
+        server.join().expect("server thread");
+
+        assert_eq!(reply["html"], "<p>hi</p>");
+    }
+}
@@ -0,0 +1,61 @@
+use crate::services::{
+    preferences::PreferencesService, search::SearchService, storage::StorageService,
+};
+use log::error;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Locks encrypted notes once they have sat unused for the configured number
+/// of minutes, and tells the windows so they can hide the content.
+#[derive(Default)]
+pub struct AutoLock {
+    is_running: Arc<AtomicBool>,
+}
+
+impl AutoLock {
+    pub fn start(&self, app: AppHandle) {
+        if self.is_running.load(Ordering::SeqCst) {
+            return;
+        }
+
+        self.is_running.store(true, Ordering::SeqCst);
+
+        let is_running = Arc::clone(&self.is_running);
+
+        thread::spawn(move || {
+            while is_running.load(Ordering::SeqCst) {
+                thread::sleep(POLL_INTERVAL);
+
+                let minutes = match app.state::<PreferencesService>().get() {
+                    Ok(prefs) if prefs.auto_lock_minutes > 0 => prefs.auto_lock_minutes,
+                    _ => continue,
+                };
+
+                let idle = Duration::from_secs(u64::from(minutes) * 60);
+                match app.state::<StorageService>().lock_if_idle(idle) {
+                    Ok(true) => notify_locked(&app),
+                    Ok(false) => {}
+                    Err(e) => error!("Failed to check notes for auto-lock: {}", e),
+                }
+            }
+        });
+    }
+}
+
+/// Clears in-memory note text and emits `notes-locked`.
+pub fn notify_locked(app: &AppHandle) {
+    if let Err(e) = app.state::<SearchService>().forget_notes() {
+        error!("Failed to clear search index: {}", e);
+    }
+
+    if let Err(e) = app.emit("notes-locked", ()) {
+        error!("Failed to emit notes-locked event: {}", e);
+    }
+}
@@ -0,0 +1,149 @@
+//! Writes to the active note from outside the editor: the capture shortcuts
+//! and the scripting API. Everything happens here rather than in the webview,
+//! so it works while the window is hidden.
+
+use crate::error::{AppError, ErrorCode};
+use crate::services::{
+    html_text::{escape_html, InputFormat, NoteInput},
+    search::SearchService,
+    storage::StorageService,
+    sync::SyncQueue,
+};
+use chrono::{DateTime, Local};
+use log::warn;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// How clipboard text is added to the note.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CaptureStyle {
+    /// One paragraph per line.
+    Paragraphs,
+    /// A list item starting with the time of capture.
+    TimestampedBullet,
+}
+
+impl CaptureStyle {
+    /// Returns `existing` with `text` added. A bullet joins the list the note
+    /// already ends with, so repeated captures build one list.
+    pub fn append(self, existing: &str, text: &str, now: DateTime<Local>) -> String {
+        match self {
+            CaptureStyle::Paragraphs => {
+                let input = NoteInput {
+                    content: text.to_string(),
+                    format: InputFormat::Text,
+                };
+                format!("{}{}", existing, input.to_html())
+            }
+            CaptureStyle::TimestampedBullet => {
+                let body = text
+                    .trim()
+                    .lines()
+                    .map(escape_html)
+                    .collect::<Vec<_>>()
+                    .join("<br>");
+                let item = format!("<li>{} {}</li>", now.format(TIMESTAMP_FORMAT), body);
+
+                match existing.trim_end().strip_suffix("</ul>") {
+                    Some(open_list) => format!("{}{}</ul>", open_list, item),
+                    None => format!("{}<ul>{}</ul>", existing, item),
+                }
+            }
+        }
+    }
+}
+
+/// Appends the text on the clipboard to the active note.
+pub fn capture_clipboard(app: &AppHandle, style: CaptureStyle) -> Result<(), AppError> {
+    let text = app
+        .clipboard()
+        .read_text()
+        .map_err(|e| AppError::internal(format!("Failed to read clipboard: {}", e)))?;
+
+    if text.trim().is_empty() {
+        return Err(AppError::new(
+            ErrorCode::Invalid,
+            "Clipboard has no text to capture",
+        ));
+    }
+
+    update_active(app, |existing| style.append(existing, &text, Local::now()))?;
+    Ok(())
+}
+
+/// Saves the active note the same way the editor does, then tells the
+/// window to reload it. Returns the note id.
+pub fn write_active(app: &AppHandle, content: &str) -> Result<String, AppError> {
+    let storage = app.state::<StorageService>();
+    let id = storage.active_note_id()?;
+
+    storage.write_note_by_id(&id, content)?;
+    saved(app, &id, content)?;
+
+    Ok(id)
+}
+
+/// Like `write_active`, but builds the new content from the current one
+/// under the storage lock, so appends arriving together all land. Returns
+/// the note id and what was saved.
+pub fn update_active(
+    app: &AppHandle,
+    change: impl FnOnce(&str) -> String,
+) -> Result<(String, String), AppError> {
+    let storage = app.state::<StorageService>();
+    let id = storage.active_note_id()?;
+
+    let content = storage.update_note_by_id(&id, change)?;
+    saved(app, &id, &content)?;
+
+    Ok((id, content))
+}
+
+/// Brings search, sync and the window up to date with a saved note.
+fn saved(app: &AppHandle, id: &str, content: &str) -> Result<(), AppError> {
+    let storage = app.state::<StorageService>();
+
+    let indexed = storage
+        .note(id)
+        .map_err(String::from)
+        .and_then(|note| app.state::<SearchService>().index_note(&note, content));
+    if let Err(err) = indexed {
+        warn!("Failed to update search index: {}", err);
+    }
+
+    if let Err(err) = app.state::<SyncQueue>().enqueue(app, id) {
+        warn!("Failed to queue sync: {}", err);
+    }
+
+    app.emit("note-content-changed", id.to_string())
+        .map_err(|e| {
+            AppError::internal(format!("Failed to emit note-content-changed event: {}", e))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn appends_paragraphs_and_extends_bullet_lists() {
+        let now = Local.with_ymd_and_hms(2024, 3, 9, 14, 5, 0).unwrap();
+
+        let note = CaptureStyle::Paragraphs.append("<p>Todo</p>", "a < b\nnext", now);
+        assert_eq!(note, "<p>Todo</p><p>a &lt; b</p><p>next</p>");
+
+        let note = CaptureStyle::TimestampedBullet.append(&note, "first\n", now);
+        assert_eq!(
+            note,
+            "<p>Todo</p><p>a &lt; b</p><p>next</p><ul><li>2024-03-09 14:05 first</li></ul>"
+        );
+
+        let note = CaptureStyle::TimestampedBullet.append(&note, "two\nlines", now);
+        assert!(note.ends_with(
+            "<li>2024-03-09 14:05 first</li><li>2024-03-09 14:05 two<br>lines</li></ul>"
+        ));
+    }
+}
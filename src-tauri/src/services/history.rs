@@ -0,0 +1,452 @@
+use crate::error::AppError;
+use crate::models::note::{DiffKind, DiffLine, Revision};
+use crate::services::persist;
+use crate::services::vault::{self, VaultKey};
+use chrono::{DateTime, Duration, Utc};
+use html2md::parse_html;
+use similar::{ChangeTag, TextDiff};
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+
+const REVISIONS_FILENAME: &str = "revisions.json";
+
+/// How snapshots are bucketed and thinned out as they age.
+#[derive(Debug, Clone, Copy)]
+pub struct RetentionPolicy {
+    /// Saves within one bucket update a single snapshot instead of adding new ones.
+    pub bucket: Duration,
+    /// Every bucket is kept for this long.
+    pub keep_all_for: Duration,
+    /// After `keep_all_for`, one snapshot per hour is kept until this age.
+    pub hourly_for: Duration,
+    /// After `hourly_for`, one snapshot per day is kept until this age.
+    pub daily_for: Duration,
+    pub max_revisions: usize,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            bucket: Duration::minutes(5),
+            keep_all_for: Duration::days(1),
+            hourly_for: Duration::days(7),
+            daily_for: Duration::days(90),
+            max_revisions: 500,
+        }
+    }
+}
+
+/// Snapshot history for a single note, stored in `<note id>.history/` beside
+/// the note file. With a key, snapshots are encrypted like the note itself.
+pub struct HistoryStore {
+    dir: PathBuf,
+    policy: RetentionPolicy,
+    key: Option<VaultKey>,
+}
+
+impl HistoryStore {
+    pub fn new(dir: PathBuf, key: Option<VaultKey>) -> Self {
+        Self {
+            dir,
+            policy: RetentionPolicy::default(),
+            key,
+        }
+    }
+
+    pub fn record(&self, previous: Option<&str>, content: &str) -> Result<(), String> {
+        self.record_at(Utc::now(), previous, content)
+    }
+
+    fn record_at(
+        &self,
+        now: DateTime<Utc>,
+        previous: Option<&str>,
+        content: &str,
+    ) -> Result<(), String> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create history directory: {}", e))?;
+
+        let mut revisions = self.list()?;
+
+        // A sharp shrink (e.g. select-all + delete) pins what was there before,
+        // even if the current bucket has already captured newer edits.
+        if let Some(previous) = previous.filter(|prev| is_large_deletion(prev, content)) {
+            match revisions.last_mut() {
+                Some(latest) if self.latest_matches(latest, previous) => latest.sealed = true,
+                _ => {
+                    let revision = self.new_revision(&revisions, now, previous, true)?;
+                    revisions.push(revision);
+                }
+            }
+        }
+
+        // A missing or unreadable latest snapshot counts as different, so a
+        // damaged file starts a fresh revision instead of blocking every save
+        let latest_readable = revisions
+            .last()
+            .is_some_and(|latest| self.read_snapshot(&latest.id).is_ok());
+
+        match revisions.last_mut() {
+            Some(latest) if self.latest_matches(latest, content) => {}
+            Some(latest)
+                if latest_readable
+                    && !latest.sealed
+                    && same_bucket(latest.saved_at, now, self.policy) =>
+            {
+                self.write_snapshot(&latest.id, content)?;
+                latest.saved_at = now;
+                latest.bytes = content.len();
+            }
+            _ => {
+                let revision = self.new_revision(&revisions, now, content, false)?;
+                revisions.push(revision);
+            }
+        }
+
+        let kept = retain(&revisions, now, self.policy);
+        for revision in revisions.iter().filter(|rev| !kept.contains(&rev.id)) {
+            let _ = fs::remove_file(self.snapshot_path(&revision.id));
+        }
+        revisions.retain(|rev| kept.contains(&rev.id));
+
+        self.save_index(&revisions)
+    }
+
+    /// Revisions in chronological order, oldest first.
+    pub fn list(&self) -> Result<Vec<Revision>, String> {
+        let path = self.dir.join(REVISIONS_FILENAME);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read revision index: {}", e))?;
+
+        serde_json::from_str(&content).map_err(|e| format!("Failed to parse revision index: {}", e))
+    }
+
+    /// Reads a revision by an ID from the frontend. Only IDs in the index are
+    /// accepted, so a crafted one can't reach files outside the history.
+    pub fn read(&self, revision_id: &str) -> Result<String, AppError> {
+        let listed = is_revision_id(revision_id)
+            && self
+                .list()?
+                .iter()
+                .any(|revision| revision.id == revision_id);
+        if !listed || !self.snapshot_path(revision_id).exists() {
+            return Err(AppError::not_found("Revision", revision_id));
+        }
+
+        Ok(self.read_snapshot(revision_id)?)
+    }
+
+    fn latest_matches(&self, latest: &Revision, content: &str) -> bool {
+        self.read_snapshot(&latest.id)
+            .is_ok_and(|snapshot| snapshot == content)
+    }
+
+    fn read_snapshot(&self, revision_id: &str) -> Result<String, String> {
+        let path = self.snapshot_path(revision_id);
+        if !path.exists() {
+            return Err(format!("Revision not found: {}", revision_id));
+        }
+
+        let stored =
+            fs::read_to_string(&path).map_err(|e| format!("Failed to read revision: {}", e))?;
+        vault::decode(self.key.as_ref(), &stored)
+    }
+
+    /// Snapshot files, for re-encoding when encryption is turned on or off.
+    pub fn snapshot_paths(&self) -> Vec<PathBuf> {
+        self.list()
+            .unwrap_or_default()
+            .iter()
+            .map(|revision| self.snapshot_path(&revision.id))
+            .filter(|path| path.exists())
+            .collect()
+    }
+
+    pub fn remove_all(&self) -> Result<(), String> {
+        if !self.dir.exists() {
+            return Ok(());
+        }
+
+        fs::remove_dir_all(&self.dir).map_err(|e| format!("Failed to delete note history: {}", e))
+    }
+
+    fn new_revision(
+        &self,
+        existing: &[Revision],
+        now: DateTime<Utc>,
+        content: &str,
+        sealed: bool,
+    ) -> Result<Revision, String> {
+        // Millisecond IDs sort chronologically; bump on collision so a sealed
+        // snapshot and a fresh one recorded in the same call stay distinct.
+        let mut millis = now.timestamp_millis();
+        while existing.iter().any(|rev| rev.id == millis.to_string()) {
+            millis += 1;
+        }
+
+        let revision = Revision {
+            id: millis.to_string(),
+            saved_at: now,
+            bytes: content.len(),
+            sealed,
+        };
+        self.write_snapshot(&revision.id, content)?;
+
+        Ok(revision)
+    }
+
+    fn write_snapshot(&self, revision_id: &str, content: &str) -> Result<(), String> {
+        let stored = vault::encode(self.key.as_ref(), content)?;
+        persist::write_atomic(&self.snapshot_path(revision_id), stored)
+            .map_err(|e| format!("Failed to write revision: {}", e))
+    }
+
+    fn save_index(&self, revisions: &[Revision]) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(revisions)
+            .map_err(|e| format!("Failed to serialize revision index: {}", e))?;
+
+        persist::write_atomic(&self.dir.join(REVISIONS_FILENAME), json)
+            .map_err(|e| format!("Failed to write revision index: {}", e))
+    }
+
+    fn snapshot_path(&self, revision_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.txt", revision_id))
+    }
+}
+
+/// Line diff of the Markdown renderings of two HTML documents, so changes
+/// read as text rather than as one long line of markup.
+pub fn diff(old_html: &str, new_html: &str) -> Vec<DiffLine> {
+    let old_text = parse_html(old_html);
+    let new_text = parse_html(new_html);
+
+    TextDiff::from_lines(&old_text, &new_text)
+        .iter_all_changes()
+        .map(|change| DiffLine {
+            kind: match change.tag() {
+                ChangeTag::Equal => DiffKind::Equal,
+                ChangeTag::Insert => DiffKind::Insert,
+                ChangeTag::Delete => DiffKind::Delete,
+            },
+            text: change.value().trim_end_matches('\n').to_string(),
+        })
+        .collect()
+}
+
+/// Revision IDs are millisecond timestamps.
+fn is_revision_id(revision_id: &str) -> bool {
+    !revision_id.is_empty() && revision_id.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+fn is_large_deletion(previous: &str, content: &str) -> bool {
+    !previous.is_empty() && content.len() * 2 < previous.len()
+}
+
+fn same_bucket(a: DateTime<Utc>, b: DateTime<Utc>, policy: RetentionPolicy) -> bool {
+    let bucket_ms = policy.bucket.num_milliseconds().max(1);
+    a.timestamp_millis() / bucket_ms == b.timestamp_millis() / bucket_ms
+}
+
+/// IDs of the revisions that survive the retention policy. Within each hourly
+/// or daily window only the newest revision is kept, plus any sealed ones.
+fn retain(revisions: &[Revision], now: DateTime<Utc>, policy: RetentionPolicy) -> HashSet<String> {
+    let mut seen_windows = HashSet::new();
+    let mut kept = HashSet::new();
+
+    for revision in revisions.iter().rev() {
+        if kept.len() >= policy.max_revisions {
+            break;
+        }
+
+        let age = now - revision.saved_at;
+        let window = if age <= policy.keep_all_for {
+            None
+        } else if age <= policy.hourly_for {
+            Some(revision.saved_at.format("h%Y%m%d%H").to_string())
+        } else if age <= policy.daily_for {
+            Some(revision.saved_at.format("d%Y%m%d").to_string())
+        } else {
+            continue;
+        };
+
+        if let Some(window) = window {
+            if !seen_windows.insert(window) && !revision.sealed {
+                continue;
+            }
+        }
+
+        kept.insert(revision.id.clone());
+    }
+
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+    use chrono::TimeZone;
+    use tempfile::tempdir;
+
+    fn at(minutes: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap() + Duration::minutes(minutes)
+    }
+
+    #[test]
+    fn saves_in_one_bucket_share_a_snapshot() {
+        let dir = tempdir().expect("tempdir");
+        let store = HistoryStore::new(dir.path().join("note.history"), None);
+
+        store.record_at(at(0), None, "<p>a</p>").expect("record");
+        store
+            .record_at(at(1), Some("<p>a</p>"), "<p>ab</p>")
+            .expect("record");
+        store
+            .record_at(at(6), Some("<p>ab</p>"), "<p>abc</p>")
+            .expect("record");
+
+        let revisions = store.list().expect("list");
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(store.read(&revisions[0].id).expect("read"), "<p>ab</p>");
+        assert_eq!(store.read(&revisions[1].id).expect("read"), "<p>abc</p>");
+    }
+
+    #[test]
+    fn skips_unchanged_content() {
+        let dir = tempdir().expect("tempdir");
+        let store = HistoryStore::new(dir.path().join("note.history"), None);
+
+        store.record_at(at(0), None, "<p>same</p>").expect("record");
+        store
+            .record_at(at(10), Some("<p>same</p>"), "<p>same</p>")
+            .expect("record");
+
+        assert_eq!(store.list().expect("list").len(), 1);
+    }
+
+    #[test]
+    fn reads_only_listed_revisions() {
+        let dir = tempdir().expect("tempdir");
+        let store = HistoryStore::new(dir.path().join("note.history"), None);
+        store.record_at(at(0), None, "<p>kept</p>").expect("record");
+        fs::write(dir.path().join("other.txt"), "<p>private</p>").expect("other file");
+
+        assert!(store.read("../other").is_err());
+        let missing = store.read("1767225600001").expect_err("unlisted");
+        assert!(missing.is(ErrorCode::NotFound));
+        assert_eq!(missing.target.as_deref(), Some("1767225600001"));
+        let revision = &store.list().expect("list")[0];
+        assert_eq!(store.read(&revision.id).expect("read"), "<p>kept</p>");
+    }
+
+    #[test]
+    fn large_deletion_pins_previous_content() {
+        let dir = tempdir().expect("tempdir");
+        let store = HistoryStore::new(dir.path().join("note.history"), None);
+        let long = "<p>A long paragraph that matters</p>";
+
+        store
+            .record_at(at(0), None, "<p>start</p>")
+            .expect("record");
+        store
+            .record_at(at(1), Some("<p>start</p>"), long)
+            .expect("record");
+        store
+            .record_at(at(2), Some(long), "<p></p>")
+            .expect("record");
+        store
+            .record_at(at(3), Some("<p></p>"), "<p>x</p>")
+            .expect("record");
+
+        let revisions = store.list().expect("list");
+        let contents: Vec<String> = revisions
+            .iter()
+            .map(|rev| store.read(&rev.id).expect("read"))
+            .collect();
+        assert_eq!(contents, vec![long.to_string(), "<p>x</p>".to_string()]);
+        assert!(revisions[0].sealed);
+    }
+
+    #[test]
+    fn missing_latest_snapshot_starts_a_new_revision() {
+        let dir = tempdir().expect("tempdir");
+        let store = HistoryStore::new(dir.path().join("note.history"), None);
+
+        store.record_at(at(0), None, "<p>a</p>").expect("record");
+        let lost = store.list().expect("list")[0].id.clone();
+        fs::remove_file(store.snapshot_path(&lost)).expect("remove snapshot");
+
+        store
+            .record_at(at(1), Some("<p>a</p>"), "<p>ab</p>")
+            .expect("record");
+
+        let revisions = store.list().expect("list");
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(store.read(&revisions[1].id).expect("read"), "<p>ab</p>");
+    }
+
+    #[test]
+    fn retention_keeps_sealed_revisions() {
+        let now = at(60 * 24 * 30);
+        let revision = |id: &str, saved_at, sealed| Revision {
+            id: id.to_string(),
+            saved_at,
+            bytes: 0,
+            sealed,
+        };
+
+        let three_days_ago = now - Duration::days(3);
+        let revisions = vec![
+            revision("pinned", three_days_ago - Duration::minutes(20), true),
+            revision("newer", three_days_ago - Duration::minutes(10), false),
+        ];
+
+        let kept = retain(&revisions, now, RetentionPolicy::default());
+        assert!(kept.contains("pinned"));
+        assert!(kept.contains("newer"));
+    }
+
+    #[test]
+    fn retention_thins_old_revisions() {
+        let policy = RetentionPolicy::default();
+        let now = at(60 * 24 * 30);
+        let revision = |id: &str, saved_at| Revision {
+            id: id.to_string(),
+            saved_at,
+            bytes: 0,
+            sealed: false,
+        };
+
+        let revisions = vec![
+            revision("too-old", now - Duration::days(120)),
+            revision("day-old", now - Duration::days(20) - Duration::hours(2)),
+            revision("day-new", now - Duration::days(20) - Duration::hours(1)),
+            revision("hour-old", now - Duration::days(3) - Duration::minutes(20)),
+            revision("hour-new", now - Duration::days(3) - Duration::minutes(10)),
+            revision("recent-a", now - Duration::hours(2)),
+            revision("recent-b", now - Duration::hours(1)),
+        ];
+
+        let kept = retain(&revisions, now, policy);
+        let mut kept: Vec<&str> = kept.iter().map(String::as_str).collect();
+        kept.sort();
+        assert_eq!(kept, vec!["day-new", "hour-new", "recent-a", "recent-b"]);
+    }
+
+    #[test]
+    fn diff_reports_changed_lines() {
+        let lines = diff("<p>one</p><p>two</p>", "<p>one</p><p>three</p>");
+
+        assert!(lines
+            .iter()
+            .any(|line| line.kind == DiffKind::Delete && line.text == "two"));
+        assert!(lines
+            .iter()
+            .any(|line| line.kind == DiffKind::Insert && line.text == "three"));
+    }
+}
@@ -0,0 +1,164 @@
+//! Hot corner hit testing. Displays and the pointer share one coordinate
+//! space with y growing downwards, and displays may sit at any offset from
+//! each other, including negative ones.
+
+use crate::models::preferences::Corner;
+
+/// A display's area in the shared coordinate space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Bounds {
+    fn right(&self) -> f64 {
+        self.x + self.width
+    }
+
+    fn bottom(&self) -> f64 {
+        self.y + self.height
+    }
+
+    fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.x && x < self.right() && y >= self.y && y < self.bottom()
+    }
+}
+
+/// Whether the pointer at `point` is in the `size`-point square at `corner`
+/// of the display it is on, and that corner is an outer one. A corner where
+/// another display continues past either edge doesn't stop the pointer, so
+/// passing through it doesn't count.
+pub fn is_in_corner(point: (f64, f64), corner: Corner, size: u32, displays: &[Bounds]) -> bool {
+    let (x, y) = point;
+    let Some(display) = displays.iter().find(|display| display.contains(x, y)) else {
+        return false;
+    };
+    let size = size as f64;
+
+    let (at_left, at_top) = match corner {
+        Corner::TopLeft => (true, true),
+        Corner::TopRight => (false, true),
+        Corner::BottomLeft => (true, false),
+        Corner::BottomRight => (false, false),
+    };
+
+    let near_side = if at_left {
+        x < display.x + size
+    } else {
+        x >= display.right() - size
+    };
+    let near_end = if at_top {
+        y < display.y + size
+    } else {
+        y >= display.bottom() - size
+    };
+    if !near_side || !near_end {
+        return false;
+    }
+
+    // Just past the display's edges, level with the pointer
+    let beyond_side = if at_left {
+        display.x - 1.0
+    } else {
+        display.right()
+    };
+    let beyond_end = if at_top {
+        display.y - 1.0
+    } else {
+        display.bottom()
+    };
+
+    !displays
+        .iter()
+        .any(|other| other.contains(beyond_side, y) || other.contains(x, beyond_end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn display(x: f64, y: f64, width: f64, height: f64) -> Bounds {
+        Bounds {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn finds_corners_on_a_single_display() {
+        let displays = [display(0.0, 0.0, 1440.0, 900.0)];
+
+        assert!(is_in_corner((1439.0, 0.0), Corner::TopRight, 10, &displays));
+        assert!(is_in_corner(
+            (0.0, 899.0),
+            Corner::BottomLeft,
+            10,
+            &displays
+        ));
+        assert!(!is_in_corner(
+            (1425.0, 0.0),
+            Corner::TopRight,
+            10,
+            &displays
+        ));
+        assert!(!is_in_corner((1439.0, 0.0), Corner::TopLeft, 10, &displays));
+        assert!(!is_in_corner((1439.0, 0.0), Corner::TopRight, 10, &[]));
+    }
+
+    #[test]
+    fn only_outer_corners_count_across_displays() {
+        // An external display to the left, offset upwards, and one below
+        let main = display(0.0, 0.0, 1440.0, 900.0);
+        let left = display(-1920.0, -300.0, 1920.0, 1080.0);
+        let below = display(0.0, 900.0, 1280.0, 720.0);
+        let displays = [main, left, below];
+
+        // The external display's own corners
+        assert!(is_in_corner(
+            (-1920.0, -300.0),
+            Corner::TopLeft,
+            10,
+            &displays
+        ));
+        assert!(is_in_corner(
+            (-1.0, -300.0),
+            Corner::TopRight,
+            10,
+            &displays
+        ));
+        // The main display's top left runs on into the external display
+        assert!(!is_in_corner((0.0, 0.0), Corner::TopLeft, 10, &displays));
+        assert!(is_in_corner((1439.0, 0.0), Corner::TopRight, 10, &displays));
+        // The lower display continues below the main one's bottom left, but
+        // stops short of its bottom right
+        assert!(!is_in_corner(
+            (0.0, 899.0),
+            Corner::BottomLeft,
+            10,
+            &displays
+        ));
+        assert!(is_in_corner(
+            (1439.0, 899.0),
+            Corner::BottomRight,
+            10,
+            &displays
+        ));
+        assert!(!is_in_corner(
+            (1279.0, 900.0),
+            Corner::TopRight,
+            10,
+            &displays
+        ));
+        assert!(is_in_corner(
+            (1279.0, 1619.0),
+            Corner::BottomRight,
+            10,
+            &displays
+        ));
+    }
+}
@@ -0,0 +1,34 @@
+//! The pointer and displays as Quartz reports them: global display
+//! coordinates in points, with the main display's top left at the origin.
+
+use super::{geometry::Bounds, CursorSource};
+use core_graphics::display::CGDisplay;
+use core_graphics::event::CGEvent;
+use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
+
+pub struct QuartzCursor;
+
+impl CursorSource for QuartzCursor {
+    fn position(&self) -> Option<(f64, f64)> {
+        let event_source = CGEventSource::new(CGEventSourceStateID::CombinedSessionState).ok()?;
+        let event = CGEvent::new(event_source).ok()?;
+        let location = event.location();
+        Some((location.x, location.y))
+    }
+
+    fn displays(&self) -> Vec<Bounds> {
+        CGDisplay::active_displays()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|id| {
+                let frame = CGDisplay::new(id).bounds();
+                Bounds {
+                    x: frame.origin.x,
+                    y: frame.origin.y,
+                    width: frame.size.width,
+                    height: frame.size.height,
+                }
+            })
+            .collect()
+    }
+}
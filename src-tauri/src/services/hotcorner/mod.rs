@@ -0,0 +1,162 @@
+//! Shows the note when the pointer rests in a screen corner. The polling and
+//! the corner test are the same everywhere; each platform only supplies a
+//! `CursorSource`.
+
+mod geometry;
+#[cfg(target_os = "macos")]
+mod macos;
+
+use crate::models::preferences::Corner;
+use log::error;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+pub use geometry::{is_in_corner, Bounds};
+
+/// Where the pointer is and which displays it can move across, both in one
+/// coordinate space with y growing downwards.
+pub trait CursorSource: Send + 'static {
+    fn position(&self) -> Option<(f64, f64)>;
+    fn displays(&self) -> Vec<Bounds>;
+}
+
+/// The pointer on this platform, or `None` where hot corners aren't
+/// supported yet.
+#[cfg(target_os = "macos")]
+pub fn system_cursor() -> Option<Box<dyn CursorSource>> {
+    Some(Box::new(macos::QuartzCursor))
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn system_cursor() -> Option<Box<dyn CursorSource>> {
+    None
+}
+
+pub struct HotCornerService {
+    is_running: Arc<AtomicBool>,
+    corner: Arc<Mutex<Corner>>,
+    size: Arc<Mutex<u32>>,
+    enabled: Arc<Mutex<bool>>,
+}
+
+impl HotCornerService {
+    pub fn new() -> Self {
+        Self {
+            is_running: Arc::new(AtomicBool::new(false)),
+            corner: Arc::new(Mutex::new(Corner::TopRight)),
+            size: Arc::new(Mutex::new(10)),
+            enabled: Arc::new(Mutex::new(true)),
+        }
+    }
+
+    pub fn start(&self, app: AppHandle, cursor: Box<dyn CursorSource>) {
+        if self.is_running.load(Ordering::SeqCst) {
+            return;
+        }
+
+        self.is_running.store(true, Ordering::SeqCst);
+
+        let is_running = Arc::clone(&self.is_running);
+        let corner = Arc::clone(&self.corner);
+        let size = Arc::clone(&self.size);
+        let enabled = Arc::clone(&self.enabled);
+
+        thread::spawn(move || {
+            let mut last_trigger = Instant::now();
+            let debounce_duration = Duration::from_millis(100); // Emit every 100ms while in corner
+
+            while is_running.load(Ordering::SeqCst) {
+                if !*enabled.lock().unwrap() {
+                    thread::sleep(Duration::from_millis(50));
+                    continue;
+                }
+
+                let corner_val = *corner.lock().unwrap();
+                let size_val = *size.lock().unwrap();
+
+                if is_triggered(cursor.as_ref(), corner_val, size_val)
+                    && last_trigger.elapsed() > debounce_duration
+                {
+                    if let Err(e) = app.emit("hotcorner-triggered", ()) {
+                        error!("Failed to emit hotcorner-triggered event: {}", e);
+                    }
+                    last_trigger = Instant::now();
+                }
+
+                thread::sleep(Duration::from_millis(50));
+            }
+        });
+    }
+
+    pub fn update_config(&self, new_corner: Corner, new_size: u32, new_enabled: bool) {
+        *self.corner.lock().unwrap() = new_corner;
+        *self.size.lock().unwrap() = new_size;
+        *self.enabled.lock().unwrap() = new_enabled;
+    }
+}
+
+impl Default for HotCornerService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether the pointer is in the hot corner right now. Displays are read on
+/// every check so plugging one in or rearranging them takes effect at once.
+fn is_triggered(cursor: &dyn CursorSource, corner: Corner, size: u32) -> bool {
+    cursor
+        .position()
+        .is_some_and(|position| is_in_corner(position, corner, size, &cursor.displays()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeCursor {
+        position: Option<(f64, f64)>,
+        displays: Vec<Bounds>,
+    }
+
+    impl CursorSource for FakeCursor {
+        fn position(&self) -> Option<(f64, f64)> {
+            self.position
+        }
+
+        fn displays(&self) -> Vec<Bounds> {
+            self.displays.clone()
+        }
+    }
+
+    #[test]
+    fn checks_the_pointer_against_every_display() {
+        let mut cursor = FakeCursor {
+            position: Some((-1.0, 1439.0)),
+            displays: vec![
+                Bounds {
+                    x: 0.0,
+                    y: 0.0,
+                    width: 1440.0,
+                    height: 900.0,
+                },
+                Bounds {
+                    x: -2560.0,
+                    y: 0.0,
+                    width: 2560.0,
+                    height: 1440.0,
+                },
+            ],
+        };
+        // Bottom right of the left display, below where the main one ends
+        assert!(is_triggered(&cursor, Corner::BottomRight, 10));
+        assert!(!is_triggered(&cursor, Corner::TopRight, 10));
+
+        cursor.position = None;
+        assert!(!is_triggered(&cursor, Corner::BottomRight, 10));
+    }
+}
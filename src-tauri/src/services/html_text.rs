@@ -0,0 +1,72 @@
+//! Turns text from outside the editor into the HTML the note stores. Shared by
+//! the scripting API, quick capture and the file exports.
+
+use crate::services::sync;
+use serde::{Deserialize, Serialize};
+
+/// How incoming text is written.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum InputFormat {
+    #[default]
+    Text,
+    Markdown,
+    Html,
+}
+
+/// Text added to a note, as sent to `PUT /note` and `POST /note/append` or
+/// typed into `wtn append`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NoteInput {
+    pub content: String,
+    #[serde(default)]
+    pub format: InputFormat,
+}
+
+impl NoteInput {
+    pub fn to_html(&self) -> String {
+        match self.format {
+            // Both can carry markup that would run in the editor's webview
+            InputFormat::Html => sync::sanitize_html(&self.content),
+            InputFormat::Markdown => {
+                sync::sanitize_html(&sync::convert_markdown_to_html(&self.content))
+            }
+            InputFormat::Text => self
+                .content
+                .lines()
+                .map(|line| format!("<p>{}</p>", escape_html(line)))
+                .collect(),
+        }
+    }
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_input_formats_to_html() {
+        let text = NoteInput {
+            content: "a < b\nsecond".to_string(),
+            format: InputFormat::Text,
+        };
+        assert_eq!(text.to_html(), "<p>a &lt; b</p><p>second</p>");
+
+        let markdown: NoteInput =
+            serde_json::from_str(r#"{"content":"**bold**","format":"markdown"}"#).expect("parse");
+        assert_eq!(markdown.to_html().trim(), "<p><strong>bold</strong></p>");
+
+        let html: NoteInput = serde_json::from_str(
+            r#"{"content":"<p onclick=\"steal()\">hi</p><script>steal()</script>","format":"html"}"#,
+        )
+        .expect("parse");
+        assert_eq!(html.to_html(), "<p>hi</p>");
+    }
+}
@@ -0,0 +1,85 @@
+//! Tells `wtn` whether the app is running. The app holds a lock on a file in
+//! its data folder for as long as it runs, and the OS drops the lock when the
+//! process exits, even after a crash, so a stale file never counts.
+
+use std::fs::{self, File, TryLockError};
+use std::io;
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+
+const LOCK_FILENAME: &str = "app.lock";
+
+/// Held by the running app. Dropping it releases the lock.
+pub struct InstanceLock {
+    _file: File,
+}
+
+impl InstanceLock {
+    pub fn new(app_handle: &AppHandle) -> Result<Option<Self>, String> {
+        let data_dir = app_handle
+            .path()
+            .app_data_dir()
+            .map_err(|e| format!("Failed to get data directory: {}", e))?;
+
+        Self::acquire(&data_dir)
+    }
+
+    /// Takes the lock, or returns `None` when another process holds it.
+    pub fn acquire(data_dir: &Path) -> Result<Option<Self>, String> {
+        fs::create_dir_all(data_dir)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(data_dir.join(LOCK_FILENAME))
+            .map_err(|e| format!("Failed to open app lock: {}", e))?;
+
+        match file.try_lock() {
+            Ok(()) => Ok(Some(Self { _file: file })),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(e)) => Err(format!("Failed to take app lock: {}", e)),
+        }
+    }
+}
+
+/// Whether an app using `data_dir` is running right now.
+pub fn is_running(data_dir: &Path) -> Result<bool, String> {
+    let file = match File::open(data_dir.join(LOCK_FILENAME)) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(err) => return Err(format!("Failed to open app lock: {}", err)),
+    };
+
+    // Released again when `file` is dropped
+    match file.try_lock_shared() {
+        Ok(()) => Ok(false),
+        Err(TryLockError::WouldBlock) => Ok(true),
+        Err(TryLockError::Error(e)) => Err(format!("Failed to check app lock: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn lock_is_held_until_dropped() {
+        let dir = tempdir().expect("tempdir");
+        assert!(!is_running(dir.path()).expect("check"));
+
+        let lock = InstanceLock::acquire(dir.path())
+            .expect("acquire")
+            .expect("free");
+        assert!(is_running(dir.path()).expect("check"));
+        assert!(InstanceLock::acquire(dir.path())
+            .expect("acquire again")
+            .is_none());
+
+        drop(lock);
+        assert!(!is_running(dir.path()).expect("check"));
+    }
+}
@@ -0,0 +1,340 @@
+//! Log files and the diagnostics bundle. Logs go to the app log folder
+//! (`~/Library/Logs/<identifier>` on macOS) and rotate once a file reaches
+//! `MAX_FILE_SIZE`, keeping the newest `KEPT_FILES`.
+
+use crate::error::AppError;
+use crate::models::preferences::{LogLevel, Preferences};
+use chrono::Local;
+use serde_json::{json, Value};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_log::{RotationStrategy, Target, TargetKind, TimezoneStrategy};
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+const LOG_FILE_NAME: &str = "what-the-note";
+const MAX_FILE_SIZE: u128 = 1024 * 1024;
+const KEPT_FILES: usize = 5;
+const REDACTED: &str = "<redacted>";
+/// Preferences that can hold folder paths or note names. Those ending in
+/// `_path` are the folder paths.
+const PRIVATE_SYNC_KEYS: [&str; 8] = [
+    "markdown_path",
+    "markdown_filename",
+    "html_path",
+    "html_filename",
+    "text_path",
+    "text_filename",
+    "apple_notes_title",
+    "apple_notes_folder",
+];
+
+/// Logs to stdout and the log folder. The plugin lets everything through
+/// and `set_level` decides what is kept, so the level follows the
+/// preference without a restart.
+pub fn plugin_builder() -> tauri_plugin_log::Builder {
+    tauri_plugin_log::Builder::new()
+        .clear_targets()
+        .targets([
+            Target::new(TargetKind::Stdout),
+            Target::new(TargetKind::LogDir {
+                file_name: Some(LOG_FILE_NAME.to_string()),
+            }),
+        ])
+        .level(log::LevelFilter::Trace)
+        .max_file_size(MAX_FILE_SIZE)
+        .rotation_strategy(RotationStrategy::KeepSome(KEPT_FILES))
+        .timezone_strategy(TimezoneStrategy::UseLocal)
+}
+
+pub fn set_level(level: LogLevel) {
+    log::set_max_level(level.filter());
+}
+
+/// Zips the log files, the preferences with paths and note names redacted,
+/// and version details into the Downloads folder, returning the zip's path.
+/// The same paths and names, the note `titles` and the home folder are
+/// removed from the logs too.
+pub fn export_diagnostics(
+    app: &AppHandle,
+    prefs: &Preferences,
+    titles: &[String],
+) -> Result<PathBuf, AppError> {
+    let log_dir = app
+        .path()
+        .app_log_dir()
+        .map_err(|e| format!("Failed to get log directory: {}", e))?;
+    let out_dir = app
+        .path()
+        .download_dir()
+        .map_err(|e| format!("Failed to get Downloads folder: {}", e))?;
+
+    let now = Local::now();
+    let path = out_dir.join(format!(
+        "what-the-note-diagnostics-{}.zip",
+        now.format("%Y%m%d-%H%M%S")
+    ));
+    let info = json!({
+        "app_version": app.package_info().version.to_string(),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "created_at": now.to_rfc3339(),
+    });
+
+    let scrubber = Scrubber::new(prefs, titles, app.path().home_dir().ok().as_deref())?;
+    write_bundle(&path, &log_dir, prefs, &scrubber, &info)?;
+    log::info!("Wrote diagnostics bundle to {}", path.display());
+
+    Ok(path)
+}
+
+fn write_bundle(
+    path: &Path,
+    log_dir: &Path,
+    prefs: &Preferences,
+    scrubber: &Scrubber,
+    info: &Value,
+) -> Result<(), AppError> {
+    let file =
+        File::create(path).map_err(|e| AppError::io("Failed to create diagnostics bundle", e))?;
+    let mut zip = ZipWriter::new(file);
+
+    add_file(&mut zip, "info.json", pretty(info)?.as_bytes())?;
+    add_file(
+        &mut zip,
+        "preferences.json",
+        pretty(&sanitized(prefs)?)?.as_bytes(),
+    )?;
+
+    // No folder yet just means nothing has been logged
+    let mut logs: Vec<PathBuf> = fs::read_dir(log_dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "log"))
+                .collect()
+        })
+        .unwrap_or_default();
+    logs.sort();
+
+    for log in logs {
+        let content = fs::read(&log).map_err(|e| AppError::io("Failed to read log file", e))?;
+        let content = scrubber.scrub(&String::from_utf8_lossy(&content));
+        let name = log.file_name().unwrap_or_default().to_string_lossy();
+        add_file(&mut zip, &format!("logs/{}", name), content.as_bytes())?;
+    }
+
+    zip.finish()
+        .map_err(|e| format!("Failed to write diagnostics bundle: {}", e))?;
+    Ok(())
+}
+
+fn add_file(zip: &mut ZipWriter<File>, name: &str, content: &[u8]) -> Result<(), AppError> {
+    zip.start_file(name, SimpleFileOptions::default())
+        .map_err(|e| format!("Failed to add {} to diagnostics bundle: {}", name, e))?;
+    zip.write_all(content)
+        .map_err(|e| AppError::io(&format!("Failed to add {} to diagnostics bundle", name), e))
+}
+
+fn pretty(value: &Value) -> Result<String, AppError> {
+    Ok(serde_json::to_string_pretty(value)
+        .map_err(|e| format!("Failed to serialize diagnostics: {}", e))?)
+}
+
+/// The preferences as JSON, with anything naming a folder or note replaced.
+fn sanitized(prefs: &Preferences) -> Result<Value, AppError> {
+    let mut value = preferences_json(prefs)?;
+
+    if let Some(sync) = value.get_mut("sync").and_then(Value::as_object_mut) {
+        for key in PRIVATE_SYNC_KEYS {
+            if let Some(field) = sync.get_mut(key).filter(|field| !field.is_null()) {
+                *field = Value::from(REDACTED);
+            }
+        }
+    }
+
+    Ok(value)
+}
+
+fn preferences_json(prefs: &Preferences) -> Result<Value, AppError> {
+    Ok(serde_json::to_value(prefs)
+        .map_err(|e| format!("Failed to serialize preferences: {}", e))?)
+}
+
+/// Takes the home folder and the private preferences and note titles out of
+/// log text. Sync logs full export paths, which hold all three.
+struct Scrubber {
+    home: Option<String>,
+    /// Folder paths, replaced wherever they appear. Longest first, so a value
+    /// containing another is replaced whole.
+    paths: Vec<String>,
+    /// Titles and other names. These can be ordinary words, so they are only
+    /// replaced as a path component or a quoted value.
+    names: Vec<String>,
+}
+
+impl Scrubber {
+    fn new(prefs: &Preferences, titles: &[String], home: Option<&Path>) -> Result<Self, AppError> {
+        let home = home
+            .map(|home| home.to_string_lossy().trim_end_matches('/').to_string())
+            .filter(|home| !home.is_empty());
+
+        let value = preferences_json(prefs)?;
+        let sync_prefs = &value["sync"];
+        let sync = |paths: bool| {
+            PRIVATE_SYNC_KEYS
+                .iter()
+                .filter(move |key| key.ends_with("_path") == paths)
+                .filter_map(move |key| sync_prefs[key].as_str().map(str::to_string))
+        };
+
+        // Told apart by where they came from, since a title can contain `/`
+        let paths = Self::private(sync(true), home.as_deref());
+        let names = Self::private(sync(false).chain(titles.iter().cloned()), home.as_deref());
+
+        Ok(Self { home, paths, names })
+    }
+
+    /// Trimmed, deduplicated and longest first. Paths are matched after the
+    /// home folder has become `~`.
+    fn private(values: impl Iterator<Item = String>, home: Option<&str>) -> Vec<String> {
+        let mut private: Vec<String> = values
+            .map(|text| text.trim().to_string())
+            .filter(|text| !text.is_empty())
+            .map(|text| match home {
+                Some(home) => text.replace(home, "~"),
+                None => text,
+            })
+            .collect();
+        private.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        private.dedup();
+        private
+    }
+
+    fn scrub(&self, text: &str) -> String {
+        let mut text = match &self.home {
+            Some(home) => text.replace(home.as_str(), "~"),
+            None => text.to_string(),
+        };
+        for path in &self.paths {
+            text = text.replace(path.as_str(), REDACTED);
+        }
+        for name in &self.names {
+            text = redact_enclosed(&text, name);
+        }
+        text
+    }
+}
+
+/// Replaces `name` where it sits between a `/` or quote and a `/`, quote,
+/// extension dot or line end.
+fn redact_enclosed(text: &str, name: &str) -> String {
+    let mut redacted = String::with_capacity(text.len());
+    let mut copied = 0;
+
+    for (at, _) in text.match_indices(name) {
+        let end = at + name.len();
+        let before = text[..at].chars().next_back();
+        let after = text[end..].chars().next();
+        let enclosed = matches!(before, Some('/' | '"' | '\''))
+            && matches!(after, None | Some('/' | '.' | '"' | '\'' | '\n' | '\r'));
+
+        if enclosed {
+            redacted.push_str(&text[copied..at]);
+            redacted.push_str(REDACTED);
+            copied = end;
+        }
+    }
+
+    redacted.push_str(&text[copied..]);
+    redacted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use tempfile::tempdir;
+    use zip::ZipArchive;
+
+    #[test]
+    fn bundles_logs_and_redacted_preferences() {
+        let dir = tempdir().expect("tempdir");
+        let log_dir = dir.path().join("logs");
+        fs::create_dir(&log_dir).expect("log dir");
+        fs::write(
+            log_dir.join("what-the-note.log"),
+            "[INFO] started\n[DEBUG] Markdown sync moved /Users/alex/Secret Notes/Old Plans.md to /Users/alex/Secret Notes/Shopping.md\n[DEBUG] HTML sync wrote 10 bytes to /Users/alex/Exports/note.html",
+        )
+        .expect("log");
+        fs::write(log_dir.join("notes.txt"), "not a log").expect("other file");
+
+        let mut prefs = Preferences::default();
+        prefs.sync.markdown_path = Some("~/Secret Notes".to_string());
+
+        let path = dir.path().join("bundle.zip");
+        let titles = ["Old Plans".to_string(), "Shopping".to_string()];
+        let scrubber =
+            Scrubber::new(&prefs, &titles, Some(Path::new("/Users/alex"))).expect("scrubber");
+        write_bundle(
+            &path,
+            &log_dir,
+            &prefs,
+            &scrubber,
+            &json!({ "app_version": "1.2.3" }),
+        )
+        .expect("bundle");
+
+        let mut archive = ZipArchive::new(File::open(&path).expect("open")).expect("zip");
+        let mut names: Vec<_> = archive.file_names().map(str::to_string).collect();
+        names.sort();
+        assert_eq!(
+            names,
+            ["info.json", "logs/what-the-note.log", "preferences.json"]
+        );
+
+        let mut saved = String::new();
+        archive
+            .by_name("preferences.json")
+            .expect("preferences")
+            .read_to_string(&mut saved)
+            .expect("read");
+        assert!(!saved.contains("Secret Notes"));
+        assert!(saved.contains(REDACTED));
+        assert!(saved.contains("\"text_size\": 14"));
+
+        let mut logged = String::new();
+        archive
+            .by_name("logs/what-the-note.log")
+            .expect("log")
+            .read_to_string(&mut logged)
+            .expect("read");
+        assert_eq!(
+            logged,
+            "[INFO] started\n[DEBUG] Markdown sync moved <redacted>/<redacted>.md to <redacted>/<redacted>.md\n[DEBUG] HTML sync wrote 10 bytes to ~/Exports/note.html"
+        );
+    }
+
+    #[test]
+    fn short_titles_only_redact_paths_and_quotes() {
+        let titles = ["I".to_string(), "Note".to_string()];
+        let scrubber = Scrubber::new(&Preferences::default(), &titles, None).expect("scrubber");
+
+        assert_eq!(
+            scrubber.scrub("[INFO] Note saved, I think\n[DEBUG] Text sync wrote to ~/Exports/Note.txt\n[WARN] Failed to rename \"I\""),
+            "[INFO] Note saved, I think\n[DEBUG] Text sync wrote to ~/Exports/<redacted>.txt\n[WARN] Failed to rename \"<redacted>\""
+        );
+    }
+
+    #[test]
+    fn titles_with_slashes_are_still_names() {
+        let titles = ["a/b".to_string()];
+        let scrubber = Scrubber::new(&Preferences::default(), &titles, None).expect("scrubber");
+
+        assert_eq!(
+            scrubber.scrub("[DEBUG] Ratio a/b is 2\n[DEBUG] Markdown sync wrote to ~/Notes/a/b.md"),
+            "[DEBUG] Ratio a/b is 2\n[DEBUG] Markdown sync wrote to ~/Notes/<redacted>.md"
+        );
+    }
+}
@@ -0,0 +1,356 @@
+use crate::models::preferences::{Preferences, CURRENT_SCHEMA_VERSION};
+use log::warn;
+use serde_json::{json, Map, Value};
+
+/// `MIGRATIONS[n]` upgrades a preferences document from schema version `n`
+/// to `n + 1`. Files written before versioning existed are version 0.
+const MIGRATIONS: &[fn(&mut Map<String, Value>)] = &[
+    migrate_v0_to_v1,
+    migrate_v1_to_v2,
+    migrate_v2_to_v3,
+    migrate_v3_to_v4,
+    migrate_v4_to_v5,
+];
+
+/// Result of turning a raw preferences document into usable `Preferences`.
+pub struct LoadedPreferences {
+    pub preferences: Preferences,
+    /// True when the document was upgraded or repaired and should be saved back.
+    pub changed: bool,
+}
+
+/// Runs the migrations `object` hasn't had yet, returning the schema version
+/// it was written with. A document from a newer build is left as it is.
+pub fn upgrade(object: &mut Map<String, Value>) -> u32 {
+    let version = object
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .map(|v| v as u32)
+        .unwrap_or(0);
+
+    if version > CURRENT_SCHEMA_VERSION {
+        // Written by a newer build; read what we understand but leave the file alone
+        warn!(
+            "Preferences schema version {} is newer than supported version {}",
+            version, CURRENT_SCHEMA_VERSION
+        );
+        return version;
+    }
+
+    for (from, migrate) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        migrate(object);
+        object.insert("schema_version".to_string(), Value::from(from as u32 + 1));
+    }
+
+    version
+}
+
+/// Upgrades `document` to the current schema and deserializes it, resetting
+/// any field that cannot be read and clamping any value that fails
+/// validation. Never fails: the worst case is the default preferences.
+pub fn load(document: Value) -> LoadedPreferences {
+    let mut object = match document {
+        Value::Object(object) => object,
+        other => {
+            warn!(
+                "Preferences file is not a JSON object ({}), using defaults",
+                other
+            );
+            return LoadedPreferences {
+                preferences: Preferences::default(),
+                changed: true,
+            };
+        }
+    };
+
+    let version = upgrade(&mut object);
+    let mut changed = version < CURRENT_SCHEMA_VERSION;
+
+    let (mut preferences, dropped) = deserialize_lenient(object);
+    changed |= !dropped.is_empty();
+
+    changed |= !preferences.sanitize().is_empty();
+
+    if let Err(err) = preferences.validate() {
+        warn!(
+            "Preferences still invalid after repair ({}), using defaults",
+            err
+        );
+        preferences = Preferences::default();
+        changed = true;
+    }
+
+    // In memory the document is always in this build's layout
+    preferences.schema_version = CURRENT_SCHEMA_VERSION;
+    if version > CURRENT_SCHEMA_VERSION {
+        changed = false;
+    }
+
+    LoadedPreferences {
+        preferences,
+        changed,
+    }
+}
+
+/// Deserializes `object` over the defaults, keeping every top-level or nested
+/// field that deserializes on its own and dropping the rest.
+fn deserialize_lenient(object: Map<String, Value>) -> (Preferences, Vec<String>) {
+    let document = Value::Object(object);
+    if let Ok(preferences) = serde_json::from_value::<Preferences>(document.clone()) {
+        return (preferences, Vec::new());
+    }
+
+    let mut merged = serde_json::to_value(Preferences::default()).expect("defaults serialize");
+    let mut dropped = Vec::new();
+
+    for (key, value) in document.as_object().into_iter().flatten() {
+        if accepts(&merged, &[key], value) {
+            merged[key] = value.clone();
+            continue;
+        }
+
+        match (value, merged.get(key)) {
+            (Value::Object(fields), Some(Value::Object(_))) => {
+                for (field, field_value) in fields {
+                    if accepts(&merged, &[key, field], field_value) {
+                        merged[key][field] = field_value.clone();
+                    } else {
+                        dropped.push(format!("{}.{}", key, field));
+                    }
+                }
+            }
+            _ => dropped.push(key.clone()),
+        }
+    }
+
+    for field in &dropped {
+        warn!("Preferences: could not read {}, reset to default", field);
+    }
+
+    let preferences = serde_json::from_value(merged).unwrap_or_default();
+    (preferences, dropped)
+}
+
+fn accepts(base: &Value, path: &[&String], value: &Value) -> bool {
+    let mut candidate = base.clone();
+    let mut slot = &mut candidate;
+    for key in path {
+        slot = &mut slot[key.as_str()];
+    }
+    *slot = value.clone();
+
+    serde_json::from_value::<Preferences>(candidate).is_ok()
+}
+
+/// Version 0 is every file written before `schema_version` existed. Its
+/// layout is identical to version 1, so stamping the version is all it needs.
+fn migrate_v0_to_v1(_object: &mut Map<String, Value>) {}
+
+/// Version 2 gives the Markdown export its own metadata style. Files that had
+/// metadata switched off keep it off for Markdown.
+fn migrate_v1_to_v2(object: &mut Map<String, Value>) {
+    let Some(Value::Object(sync)) = object.get_mut("sync") else {
+        return;
+    };
+
+    if sync.get("include_metadata") == Some(&Value::Bool(false)) {
+        sync.insert("markdown_metadata".to_string(), Value::from("none"));
+    }
+}
+
+/// Version 3 replaces the single `keyboard_shortcut` and the two capture
+/// shortcuts with one list of bindings.
+fn migrate_v2_to_v3(object: &mut Map<String, Value>) {
+    let shortcuts: Vec<Value> = [
+        ("keyboard_shortcut", "toggle-window"),
+        ("capture_shortcut", "capture-clipboard"),
+        ("capture_bullet_shortcut", "capture-bullet"),
+    ]
+    .into_iter()
+    .filter_map(|(key, action)| match object.remove(key) {
+        Some(Value::String(shortcut)) if !shortcut.trim().is_empty() => {
+            Some(json!({ "shortcut": shortcut, "action": action }))
+        }
+        _ => None,
+    })
+    .collect();
+
+    if !shortcuts.is_empty() {
+        object.insert("shortcuts".to_string(), Value::Array(shortcuts));
+    }
+}
+
+/// Version 4 gives the HTML and plain text exports their own metadata
+/// style, carried over from the shared switch like Markdown's was.
+fn migrate_v3_to_v4(object: &mut Map<String, Value>) {
+    let Some(Value::Object(sync)) = object.get_mut("sync") else {
+        return;
+    };
+
+    if sync.get("include_metadata") == Some(&Value::Bool(false)) {
+        for key in ["html_metadata", "text_metadata"] {
+            sync.insert(key.to_string(), Value::from("none"));
+        }
+    }
+}
+
+/// Version 5 renames the shared `include_metadata` switch, which by now only
+/// affects Apple Notes, to `apple_notes_metadata`.
+fn migrate_v4_to_v5(object: &mut Map<String, Value>) {
+    let Some(Value::Object(sync)) = object.get_mut("sync") else {
+        return;
+    };
+
+    if let Some(value) = sync.remove("include_metadata") {
+        sync.insert("apple_notes_metadata".to_string(), value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::preferences::{MetadataStyle, ShortcutAction, Theme};
+
+    #[test]
+    fn stamps_unversioned_files() {
+        let loaded = load(json!({ "text_size": 18 }));
+
+        assert!(loaded.changed);
+        assert_eq!(loaded.preferences.schema_version, CURRENT_SCHEMA_VERSION);
+        assert_eq!(loaded.preferences.text_size, 18);
+    }
+
+    #[test]
+    fn current_valid_file_is_untouched() {
+        let document = serde_json::to_value(Preferences::default()).expect("serialize");
+        let loaded = load(document);
+
+        assert!(!loaded.changed);
+    }
+
+    #[test]
+    fn clamps_out_of_range_values() {
+        let loaded = load(json!({
+            "schema_version": CURRENT_SCHEMA_VERSION,
+            "text_size": 99,
+            "hotcorner_size": 0,
+            "shortcuts": [{ "shortcut": " ", "action": "show-window" }]
+        }));
+
+        assert!(loaded.changed);
+        assert_eq!(loaded.preferences.text_size, 32);
+        assert_eq!(loaded.preferences.hotcorner_size, 1);
+        assert!(loaded.preferences.shortcuts.is_empty());
+    }
+
+    #[test]
+    fn resets_unreadable_fields_only() {
+        let loaded = load(json!({
+            "schema_version": CURRENT_SCHEMA_VERSION,
+            "theme": "neon",
+            "text_size": 20,
+            "sync": { "markdown_enabled": "yes", "markdown_path": "~/notes" }
+        }));
+
+        assert!(loaded.changed);
+        assert_eq!(loaded.preferences.theme, Theme::default());
+        assert_eq!(loaded.preferences.text_size, 20);
+        assert!(!loaded.preferences.sync.markdown_enabled);
+        assert_eq!(
+            loaded.preferences.sync.markdown_path.as_deref(),
+            Some("~/notes")
+        );
+    }
+
+    #[test]
+    fn carries_disabled_metadata_over_to_markdown() {
+        let loaded = load(json!({
+            "schema_version": 1,
+            "sync": { "include_metadata": false }
+        }));
+
+        assert!(loaded.changed);
+        assert_eq!(
+            loaded.preferences.sync.markdown_metadata,
+            MetadataStyle::None
+        );
+    }
+
+    #[test]
+    fn carries_disabled_metadata_over_to_file_exports() {
+        let loaded = load(json!({
+            "schema_version": 3,
+            "sync": { "include_metadata": false, "markdown_metadata": "yaml" }
+        }));
+
+        let sync = &loaded.preferences.sync;
+        assert_eq!(sync.html_metadata, MetadataStyle::None);
+        assert_eq!(sync.text_metadata, MetadataStyle::None);
+        assert_eq!(sync.markdown_metadata, MetadataStyle::Yaml);
+
+        let kept = load(json!({ "schema_version": 3, "sync": { "include_metadata": true } }));
+        assert_eq!(
+            kept.preferences.sync.html_metadata,
+            MetadataStyle::HtmlComment
+        );
+    }
+
+    #[test]
+    fn moves_metadata_switch_to_apple_notes() {
+        let loaded = load(json!({
+            "schema_version": 4,
+            "sync": { "include_metadata": false, "markdown_filename": "What The Note" }
+        }));
+
+        let sync = &loaded.preferences.sync;
+        assert!(!sync.apple_notes_metadata);
+        assert_eq!(sync.markdown_filename, "What The Note");
+    }
+
+    #[test]
+    fn moves_single_shortcuts_into_bindings() {
+        let loaded = load(json!({
+            "schema_version": 2,
+            "keyboard_shortcut": "Ctrl+Shift+N",
+            "capture_shortcut": "",
+            "capture_bullet_shortcut": "Ctrl+Shift+B"
+        }));
+
+        let actions: Vec<_> = loaded
+            .preferences
+            .shortcuts
+            .iter()
+            .map(|binding| (binding.shortcut.as_str(), binding.action))
+            .collect();
+        assert_eq!(
+            actions,
+            [
+                ("Ctrl+Shift+N", ShortcutAction::ToggleWindow),
+                ("Ctrl+Shift+B", ShortcutAction::CaptureBullet)
+            ]
+        );
+    }
+
+    #[test]
+    fn drops_shortcuts_that_cannot_register() {
+        let loaded = load(json!({
+            "schema_version": CURRENT_SCHEMA_VERSION,
+            "shortcuts": [
+                { "shortcut": "cmd+alt+n", "action": "toggle-window" },
+                { "shortcut": "Alt+Command+N", "action": "show-window" },
+                { "shortcut": "Command+Nope", "action": "hide-window" }
+            ]
+        }));
+
+        assert!(loaded.changed);
+        assert_eq!(loaded.preferences.shortcuts.len(), 1);
+        assert_eq!(loaded.preferences.shortcuts[0].shortcut, "Alt+Command+N");
+    }
+
+    #[test]
+    fn leaves_newer_files_alone() {
+        let loaded = load(json!({ "schema_version": CURRENT_SCHEMA_VERSION + 1 }));
+
+        assert!(!loaded.changed);
+    }
+}
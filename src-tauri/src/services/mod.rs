@@ -1,11 +1,23 @@
+pub mod api;
+pub mod auto_lock;
+pub mod capture;
+pub mod history;
+pub mod hotcorner;
+pub mod html_text;
+pub mod instance;
+pub mod logging;
+pub mod migrations;
+pub mod persist;
+pub mod placement;
+pub mod preference_effects;
 pub mod preferences;
+pub mod preferences_watcher;
+pub mod search;
 pub mod shortcuts;
 pub mod storage;
 pub mod sync;
 pub mod tray;
-
-#[cfg(target_os = "macos")]
-pub mod hotcorner;
+pub mod vault;
 
 #[cfg(test)]
 pub(crate) mod sample;
@@ -0,0 +1,315 @@
+use log::warn;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use uuid::Uuid;
+
+const TEMP_EXTENSION: &str = "tmp";
+/// Temp files this old belong to writes that will never finish; younger ones
+/// may be a save in progress from another process, such as the CLI's.
+const STALE_TEMP_AGE: Duration = Duration::from_secs(60);
+
+/// Whether opening a store may repair and tidy its files. `ReadOnly` is for
+/// `wtn` reading files the running app may be writing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    ReadWrite,
+    ReadOnly,
+}
+
+/// Writes `contents` to a sibling temp file, fsyncs it and renames it over
+/// `path`, so readers only ever see the old or the new file in full. Each
+/// write gets its own temp file, so writers to the same path don't collide.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    write_via_temp(path, contents.as_ref(), File::options())
+}
+
+/// Like [`write_atomic`], for secrets: only the current user can read the
+/// file, from the moment its temp file is created.
+pub fn write_atomic_private(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let mut options = File::options();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    write_via_temp(path, contents.as_ref(), options)
+}
+
+fn write_via_temp(path: &Path, contents: &[u8], mut options: OpenOptions) -> io::Result<()> {
+    let tmp_path = sibling(
+        path,
+        &format!("{}.{}", Uuid::new_v4().simple(), TEMP_EXTENSION),
+    );
+
+    let written = options
+        .write(true)
+        .create_new(true)
+        .open(&tmp_path)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        });
+    if let Err(err) = written.and_then(|_| fs::rename(&tmp_path, path)) {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(err);
+    }
+
+    sync_parent_dir(path)
+}
+
+/// Deletes temp files left in `dir` and its subfolders by writes that were
+/// interrupted before their rename. Called when storage or preferences are
+/// opened rather than on every read, which could race a write.
+pub fn remove_temp_files(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+        if path.is_dir() {
+            remove_temp_files(&path);
+        } else if path.extension().is_some_and(|ext| ext == TEMP_EXTENSION) && is_stale(&path) {
+            if let Err(err) = fs::remove_file(&path) {
+                warn!("Failed to remove {}: {}", path.display(), err);
+            }
+        }
+    }
+}
+
+/// Like [`write_atomic`], but first copies the current file to `<name>.bak`
+/// so [`read_recovering`] has a last good copy to fall back on.
+pub fn write_with_backup(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    if fs::metadata(path)
+        .map(|meta| meta.len() > 0)
+        .unwrap_or(false)
+    {
+        fs::copy(path, sibling(path, "bak"))?;
+    }
+
+    write_atomic(path, contents)
+}
+
+/// Reads and parses `path`, falling back to the `.bak` copy when the file is
+/// zero-length or fails to parse. A recovered copy is written back in place
+/// unless `access` is [`Access::ReadOnly`].
+///
+/// Returns `Ok(None)` when neither file holds usable data and the original
+/// is missing or empty; a non-empty file that fails to parse without a usable
+/// backup returns the parse error.
+pub fn read_recovering<T>(
+    path: &Path,
+    parse: impl Fn(&str) -> Result<T, String>,
+    access: Access,
+) -> Result<Option<T>, String> {
+    read_with_fallback(path, parse, false, access)
+}
+
+/// Like [`read_recovering`], for files that can legitimately be emptied,
+/// such as a cleared note: an empty file is parsed like any other, and only
+/// a parse error falls back to the `.bak` copy.
+pub fn read_recovering_allow_empty<T>(
+    path: &Path,
+    parse: impl Fn(&str) -> Result<T, String>,
+    access: Access,
+) -> Result<Option<T>, String> {
+    read_with_fallback(path, parse, true, access)
+}
+
+fn read_with_fallback<T>(
+    path: &Path,
+    parse: impl Fn(&str) -> Result<T, String>,
+    allow_empty: bool,
+    access: Access,
+) -> Result<Option<T>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    let error = if content.is_empty() && !allow_empty {
+        None
+    } else {
+        match parse(&content) {
+            Ok(value) => return Ok(Some(value)),
+            Err(err) => Some(err),
+        }
+    };
+
+    let backup_path = sibling(path, "bak");
+    if let Ok(backup) = fs::read_to_string(&backup_path) {
+        if !backup.is_empty() {
+            if let Ok(value) = parse(&backup) {
+                warn!(
+                    "Recovered {} from last good copy ({})",
+                    path.display(),
+                    error.as_deref().unwrap_or("file was empty")
+                );
+                if access == Access::ReadWrite {
+                    write_atomic(path, &backup)
+                        .map_err(|e| format!("Failed to restore {}: {}", path.display(), e))?;
+                }
+                return Ok(Some(value));
+            }
+        }
+    }
+
+    match error {
+        Some(err) => Err(err),
+        None => Ok(None),
+    }
+}
+
+pub fn remove_with_backup(path: &Path) -> io::Result<()> {
+    for candidate in [path.to_path_buf(), sibling(path, "bak")] {
+        match fs::remove_file(&candidate) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn is_stale(path: &Path) -> bool {
+    fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age >= STALE_TEMP_AGE)
+}
+
+fn sibling(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(".");
+    name.push(extension);
+    path.with_file_name(name)
+}
+
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => File::open(parent)?.sync_all(),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn parse_json(content: &str) -> Result<serde_json::Value, String> {
+        serde_json::from_str(content).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn recovers_truncated_file_from_backup() {
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("preferences.json");
+
+        write_with_backup(&path, r#"{"theme":"minimal"}"#).expect("first write");
+        write_with_backup(&path, r#"{"theme":"sticky-note"}"#).expect("second write");
+        fs::write(&path, "").expect("truncate");
+
+        let value = read_recovering(&path, parse_json, Access::ReadWrite)
+            .expect("recover")
+            .expect("value");
+        assert_eq!(value["theme"], "minimal");
+        assert_eq!(
+            fs::read_to_string(&path).expect("read"),
+            r#"{"theme":"minimal"}"#
+        );
+    }
+
+    #[test]
+    fn recovers_half_written_json_from_backup() {
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("notes.json");
+
+        write_with_backup(&path, r#"{"a":1}"#).expect("first write");
+        write_with_backup(&path, r#"{"a":2}"#).expect("second write");
+        fs::write(&path, r#"{"a":"#).expect("corrupt");
+
+        let value = read_recovering(&path, parse_json, Access::ReadWrite)
+            .expect("recover")
+            .expect("value");
+        assert_eq!(value["a"], 1);
+    }
+
+    #[test]
+    fn read_only_recovery_leaves_the_file_alone() {
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("notes.json");
+
+        write_with_backup(&path, r#"{"a":1}"#).expect("first write");
+        write_with_backup(&path, r#"{"a":2}"#).expect("second write");
+        fs::write(&path, r#"{"a":"#).expect("corrupt");
+
+        let value = read_recovering(&path, parse_json, Access::ReadOnly)
+            .expect("recover")
+            .expect("value");
+        assert_eq!(value["a"], 1);
+        assert_eq!(fs::read_to_string(&path).expect("read"), r#"{"a":"#);
+    }
+
+    #[test]
+    fn reports_corruption_without_backup() {
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("notes.json");
+        fs::write(&path, "{").expect("corrupt");
+
+        assert!(read_recovering(&path, parse_json, Access::ReadWrite).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn private_files_are_never_readable_by_others() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("api-token");
+        write_atomic_private(&path, "secret").expect("write");
+
+        let mode = fs::metadata(&path).expect("metadata").permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(fs::read_to_string(&path).expect("read"), "secret");
+    }
+
+    #[test]
+    fn discards_interrupted_temp_files_only_when_asked() {
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("notes").join("note.txt");
+        fs::create_dir(dir.path().join("notes")).expect("notes dir");
+        write_atomic(&path, "kept").expect("write");
+        let stale = sibling(&path, "0123.tmp");
+        fs::write(&stale, "partial").expect("stale tmp");
+        File::options()
+            .write(true)
+            .open(&stale)
+            .and_then(|file| file.set_modified(SystemTime::now() - STALE_TEMP_AGE))
+            .expect("age tmp");
+        let in_progress = sibling(&path, "4567.tmp");
+        fs::write(&in_progress, "partial").expect("fresh tmp");
+
+        // A read may overlap a write, so it leaves temp files alone
+        let value = read_recovering(&path, |s| Ok(s.to_string()), Access::ReadWrite).expect("read");
+        assert_eq!(value.as_deref(), Some("kept"));
+        assert!(stale.exists());
+
+        remove_temp_files(dir.path());
+        assert!(!stale.exists());
+        assert!(in_progress.exists());
+        assert_eq!(fs::read_to_string(&path).expect("read"), "kept");
+    }
+}
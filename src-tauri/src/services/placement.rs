@@ -0,0 +1,388 @@
+//! Where the note window goes on screen. Plain geometry in physical pixels,
+//! kept free of Tauri types so it can be tested without a display.
+
+use crate::models::preferences::{Corner, PlacementPolicy, WindowPlacement};
+
+/// Monitor setups remembered before the least recently used is forgotten.
+const MAX_PLACEMENTS: usize = 8;
+/// Gap kept between the window and the monitor edge or the pointer, in
+/// logical pixels so it looks the same at any scale.
+const PADDING: f64 = 20.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    fn right(&self) -> i64 {
+        self.x as i64 + self.width as i64
+    }
+
+    fn bottom(&self) -> i64 {
+        self.y as i64 + self.height as i64
+    }
+
+    fn overlap(&self, other: &Rect) -> i64 {
+        let width = self.right().min(other.right()) - (self.x.max(other.x) as i64);
+        let height = self.bottom().min(other.bottom()) - (self.y.max(other.y) as i64);
+        width.max(0) * height.max(0)
+    }
+
+    fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && (x as i64) < self.right() && y >= self.y && (y as i64) < self.bottom()
+    }
+
+    fn centre(&self) -> (i32, i32) {
+        (
+            (self.x as i64 + self.width as i64 / 2) as i32,
+            (self.y as i64 + self.height as i64 / 2) as i32,
+        )
+    }
+
+    /// Squared distance between the two centres.
+    fn distance(&self, other: &Rect) -> i64 {
+        let (x, y) = self.centre();
+        let (other_x, other_y) = other.centre();
+        let (dx, dy) = (x as i64 - other_x as i64, y as i64 - other_y as i64);
+        dx * dx + dy * dy
+    }
+}
+
+/// A connected monitor. `frame` is in physical pixels and `scale` is how
+/// many of them make one logical pixel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Screen {
+    pub name: Option<String>,
+    pub frame: Rect,
+    pub scale: f64,
+}
+
+impl Screen {
+    fn physical(&self, logical: f64) -> i32 {
+        (logical * self.scale).round() as i32
+    }
+}
+
+/// The note window as it is before being shown.
+pub struct Window {
+    /// Bounds remembered for the connected monitors, if any.
+    pub saved: Option<Rect>,
+    /// Where it is now, and the scale of the monitor that was measured on.
+    pub current: Rect,
+    pub scale: f64,
+}
+
+/// Identifies a monitor setup by the name and resolution of each monitor,
+/// in any order, so the same monitors plugged in again match.
+pub fn layout_key(screens: &[Screen]) -> String {
+    let mut monitors: Vec<String> = screens
+        .iter()
+        .map(|screen| {
+            format!(
+                "{}@{}x{}",
+                screen.name.as_deref().unwrap_or("unnamed"),
+                screen.frame.width,
+                screen.frame.height
+            )
+        })
+        .collect();
+    monitors.sort();
+    monitors.join(",")
+}
+
+/// Moves `bounds`, shrinking it if it has to, so all of it is on the monitor
+/// it mostly covers, or the nearest one if it's on none. `None` when there
+/// are no monitors.
+pub fn clamp(bounds: Rect, screens: &[Screen]) -> Option<Rect> {
+    let frame = screens
+        .iter()
+        .map(|screen| screen.frame)
+        .max_by_key(|frame| (bounds.overlap(frame), -bounds.distance(frame)))?;
+
+    Some(fit(bounds, frame))
+}
+
+fn fit(bounds: Rect, frame: Rect) -> Rect {
+    let width = bounds.width.min(frame.width);
+    let height = bounds.height.min(frame.height);
+    let x = bounds
+        .x
+        .clamp(frame.x, frame.x + (frame.width - width) as i32);
+    let y = bounds
+        .y
+        .clamp(frame.y, frame.y + (frame.height - height) as i32);
+
+    Rect::new(x, y, width, height)
+}
+
+/// The monitor under the point, or the nearest one when it falls in a gap
+/// between monitors.
+pub fn screen_at(screens: &[Screen], x: i32, y: i32) -> Option<&Screen> {
+    let point = Rect::new(x, y, 0, 0);
+    screens
+        .iter()
+        .find(|screen| screen.frame.contains(x, y))
+        .or_else(|| {
+            screens
+                .iter()
+                .min_by_key(|screen| point.distance(&screen.frame))
+        })
+}
+
+/// Where to show the window under `policy`. Sizes carry over between
+/// monitors in logical pixels, so the window looks the same size on a
+/// Retina display as on the external monitor beside it. `None` when there
+/// are no monitors.
+pub fn place(
+    policy: PlacementPolicy,
+    corner: Corner,
+    window: &Window,
+    screens: &[Screen],
+    cursor: Option<(i32, i32)>,
+) -> Option<Rect> {
+    let saved = window.saved.and_then(|bounds| clamp(bounds, screens));
+    let origin = saved.and_then(|bounds| {
+        let (x, y) = bounds.centre();
+        screen_at(screens, x, y)
+    });
+    let target = cursor
+        .and_then(|(x, y)| screen_at(screens, x, y))
+        .or(origin)
+        .or(screens.first())?;
+
+    let size = match (saved, origin) {
+        (Some(bounds), Some(from)) => rescale(bounds, from.scale, target.scale),
+        _ => rescale(window.current, window.scale, target.scale),
+    };
+
+    let placed = match (policy, saved, origin, cursor) {
+        (PlacementPolicy::LastPosition, Some(bounds), _, _) => bounds,
+        (PlacementPolicy::MonitorUnderCursor, Some(bounds), Some(from), _) => {
+            let ratio = target.scale / from.scale;
+            let offset = |value: i32, start: i32| (((value - start) as f64) * ratio).round() as i32;
+            let moved = Rect::new(
+                target.frame.x + offset(bounds.x, from.frame.x),
+                target.frame.y + offset(bounds.y, from.frame.y),
+                size.width,
+                size.height,
+            );
+            fit(moved, target.frame)
+        }
+        (PlacementPolicy::NearCursor, _, _, Some((x, y))) => beside(size, x, y, target),
+        (PlacementPolicy::PinnedCorner, _, _, _) => in_corner(size, corner, target),
+        // Nothing saved, or no pointer to go by
+        _ => in_corner(size, Corner::TopRight, target),
+    };
+
+    Some(placed)
+}
+
+/// `bounds` resized from one monitor scale to another.
+fn rescale(bounds: Rect, from: f64, to: f64) -> Rect {
+    let ratio = to / from;
+    let scaled = |value: u32| (value as f64 * ratio).round() as u32;
+    Rect::new(
+        bounds.x,
+        bounds.y,
+        scaled(bounds.width),
+        scaled(bounds.height),
+    )
+}
+
+/// Below and to the right of the pointer, flipping to the other side when
+/// there is no room.
+fn beside(size: Rect, x: i32, y: i32, screen: &Screen) -> Rect {
+    let gap = screen.physical(PADDING);
+    let frame = screen.frame;
+    let left = if x as i64 + gap as i64 + size.width as i64 > frame.right() {
+        x - gap - size.width as i32
+    } else {
+        x + gap
+    };
+    let top = if y as i64 + gap as i64 + size.height as i64 > frame.bottom() {
+        y - gap - size.height as i32
+    } else {
+        y + gap
+    };
+
+    fit(Rect::new(left, top, size.width, size.height), frame)
+}
+
+fn in_corner(size: Rect, corner: Corner, screen: &Screen) -> Rect {
+    let padding = screen.physical(PADDING);
+    let frame = screen.frame;
+    let left = frame.x + padding;
+    let right = frame.x + frame.width as i32 - size.width as i32 - padding;
+    let top = frame.y + padding;
+    let bottom = frame.y + frame.height as i32 - size.height as i32 - padding;
+
+    let (x, y) = match corner {
+        Corner::TopLeft => (left, top),
+        Corner::TopRight => (right, top),
+        Corner::BottomLeft => (left, bottom),
+        Corner::BottomRight => (right, bottom),
+    };
+    fit(Rect::new(x, y, size.width, size.height), frame)
+}
+
+/// The bounds saved for `layout`, if it has been seen before.
+pub fn recall(placements: &[WindowPlacement], layout: &str) -> Option<Rect> {
+    placements
+        .iter()
+        .find(|placement| placement.layout == layout)
+        .map(|placement| Rect::new(placement.x, placement.y, placement.width, placement.height))
+}
+
+/// Saves `bounds` for `layout`, moving it to the front and dropping the
+/// oldest setup once there are too many.
+pub fn remember(placements: &mut Vec<WindowPlacement>, layout: &str, bounds: Rect) {
+    placements.retain(|placement| placement.layout != layout);
+    placements.insert(
+        0,
+        WindowPlacement {
+            layout: layout.to_string(),
+            x: bounds.x,
+            y: bounds.y,
+            width: bounds.width,
+            height: bounds.height,
+        },
+    );
+    placements.truncate(MAX_PLACEMENTS);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn screen(name: &str, x: i32, y: i32, width: u32, height: u32) -> Screen {
+        Screen {
+            name: Some(name.to_string()),
+            frame: Rect::new(x, y, width, height),
+            scale: 1.0,
+        }
+    }
+
+    #[test]
+    fn keeps_windows_whole_and_on_screen() {
+        let laptop = screen("Built-in Retina Display", 0, 0, 2880, 1800);
+        let external = screen("DELL U2720Q", -3840, -360, 3840, 2160);
+
+        // Mostly on the external monitor, hanging off its left edge
+        assert_eq!(
+            clamp(
+                Rect::new(-3900, 100, 800, 600),
+                &[laptop.clone(), external.clone()]
+            ),
+            Some(Rect::new(-3840, 100, 800, 600))
+        );
+        // Saved on a monitor that is gone: moved to the nearest and shrunk
+        assert_eq!(
+            clamp(Rect::new(-2000, 0, 3000, 2000), &[laptop.clone()]),
+            Some(Rect::new(0, 0, 2880, 1800))
+        );
+        // Already visible: untouched
+        let visible = Rect::new(100, 100, 400, 300);
+        assert_eq!(clamp(visible, &[laptop.clone()]), Some(visible));
+        assert_eq!(clamp(visible, &[]), None);
+
+        assert_eq!(
+            layout_key(&[laptop.clone(), external.clone()]),
+            layout_key(&[external, laptop])
+        );
+    }
+
+    #[test]
+    fn remembers_each_setup_separately() {
+        let mut placements = Vec::new();
+        remember(&mut placements, "docked", Rect::new(-3000, 40, 600, 500));
+        remember(&mut placements, "laptop", Rect::new(20, 20, 400, 300));
+        remember(&mut placements, "docked", Rect::new(-2000, 40, 600, 500));
+
+        assert_eq!(placements.len(), 2);
+        assert_eq!(placements[0].layout, "docked");
+        assert_eq!(
+            recall(&placements, "docked"),
+            Some(Rect::new(-2000, 40, 600, 500))
+        );
+        assert_eq!(
+            recall(&placements, "laptop"),
+            Some(Rect::new(20, 20, 400, 300))
+        );
+        assert_eq!(recall(&placements, "projector"), None);
+
+        for index in 0..MAX_PLACEMENTS {
+            remember(&mut placements, &index.to_string(), Rect::new(0, 0, 1, 1));
+        }
+        assert_eq!(placements.len(), MAX_PLACEMENTS);
+        assert_eq!(recall(&placements, "laptop"), None);
+    }
+
+    #[test]
+    fn follows_the_pointer_across_mixed_scales() {
+        let laptop = Screen {
+            scale: 2.0,
+            ..screen("Built-in Retina Display", 0, 0, 2880, 1800)
+        };
+        let external = screen("DELL U2720Q", 2880, 0, 1920, 1080);
+        let screens = [laptop, external];
+        let window = Window {
+            saved: Some(Rect::new(200, 100, 800, 600)),
+            current: Rect::new(0, 0, 800, 600),
+            scale: 2.0,
+        };
+        let on_external = Some((3000, 500));
+        let place = |policy, corner| place(policy, corner, &window, &screens, on_external);
+
+        assert_eq!(
+            place(PlacementPolicy::LastPosition, Corner::TopRight),
+            Some(Rect::new(200, 100, 800, 600))
+        );
+        // Same spot and logical size, now on the 1x monitor
+        assert_eq!(
+            place(PlacementPolicy::MonitorUnderCursor, Corner::TopRight),
+            Some(Rect::new(2980, 50, 400, 300))
+        );
+        assert_eq!(
+            place(PlacementPolicy::NearCursor, Corner::TopRight),
+            Some(Rect::new(3020, 520, 400, 300))
+        );
+        assert_eq!(
+            place(PlacementPolicy::PinnedCorner, Corner::BottomRight),
+            Some(Rect::new(4380, 760, 400, 300))
+        );
+        // Too close to the bottom right to fit beside the pointer
+        assert_eq!(
+            super::place(
+                PlacementPolicy::NearCursor,
+                Corner::TopRight,
+                &window,
+                &screens,
+                Some((4700, 1000))
+            ),
+            Some(Rect::new(4280, 680, 400, 300))
+        );
+        assert_eq!(
+            super::place(
+                PlacementPolicy::NearCursor,
+                Corner::TopRight,
+                &window,
+                &[],
+                None
+            ),
+            None
+        );
+    }
+}
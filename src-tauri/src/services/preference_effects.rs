@@ -0,0 +1,313 @@
+//! The live side of preferences: registered shortcuts, the hot corner, launch
+//! at login, the log level and the scripting API. Each effect runs only when
+//! its fields change, and is undone by running it again with the previous
+//! preferences.
+
+use crate::models::preferences::{FieldError, Preferences};
+use crate::services::{
+    api::ApiServer, hotcorner::HotCornerService, logging, shortcuts::ShortcutsService,
+};
+use log::error;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_autostart::ManagerExt;
+
+/// One side effect of a preferences change. `C` is the app handle outside of
+/// tests.
+pub struct Effect<C> {
+    pub field: &'static str,
+    pub changed: fn(&Preferences, &Preferences) -> bool,
+    pub apply: fn(&C, &Preferences) -> Result<(), Vec<FieldError>>,
+    /// Set for effects whose failure shouldn't hold up the rest of the
+    /// update. Copies the effect's fields from the first preferences into the
+    /// second, so the previous values are kept and everything else is saved.
+    pub keep: Option<fn(&Preferences, &mut Preferences)>,
+}
+
+/// What `apply` did: the effects that ran, and the problems of any that
+/// kept their previous values instead.
+#[derive(Debug, Default, PartialEq)]
+pub struct Outcome {
+    pub applied: Vec<usize>,
+    pub warnings: Vec<FieldError>,
+}
+
+/// The effects `PreferencesService::apply` runs, in order.
+pub fn live_effects() -> Vec<Effect<AppHandle>> {
+    vec![
+        Effect {
+            field: "shortcuts",
+            changed: |old, new| {
+                old.shortcuts != new.shortcuts || old.shortcut_enabled != new.shortcut_enabled
+            },
+            apply: apply_shortcuts,
+            keep: Some(|old, new| {
+                new.shortcuts = old.shortcuts.clone();
+                new.shortcut_enabled = old.shortcut_enabled;
+            }),
+        },
+        hot_corner("hotcorner_corner", |old, new| {
+            old.hotcorner_corner != new.hotcorner_corner
+        }),
+        hot_corner("hotcorner_size", |old, new| {
+            old.hotcorner_size != new.hotcorner_size
+        }),
+        hot_corner("hotcorner_enabled", |old, new| {
+            old.hotcorner_enabled != new.hotcorner_enabled
+        }),
+        Effect {
+            field: "launch_on_startup",
+            changed: |old, new| old.launch_on_startup != new.launch_on_startup,
+            apply: |app, prefs| {
+                let autostart_manager = app.autolaunch();
+                let result = if prefs.launch_on_startup {
+                    autostart_manager.enable()
+                } else {
+                    autostart_manager.disable()
+                };
+                result.map_err(|e| {
+                    vec![FieldError::new(
+                        "launch_on_startup",
+                        format!("Failed to update launch at login: {}", e),
+                    )]
+                })
+            },
+            keep: None,
+        },
+        Effect {
+            field: "log_level",
+            changed: |old, new| old.log_level != new.log_level,
+            apply: |_, prefs| {
+                logging::set_level(prefs.log_level);
+                Ok(())
+            },
+            keep: None,
+        },
+        Effect {
+            field: "api_enabled",
+            changed: |old, new| {
+                old.api_enabled != new.api_enabled
+                    || old.api_transport != new.api_transport
+                    || old.api_port != new.api_port
+            },
+            apply: |app, prefs| {
+                app.state::<ApiServer>()
+                    .apply(app, prefs)
+                    .map_err(|e| vec![FieldError::new("api_enabled", e)])
+            },
+            keep: None,
+        },
+    ]
+}
+
+/// One effect per hot corner field, so a failure names the field that
+/// changed. Each sends the whole configuration, so running more than one is
+/// harmless.
+fn hot_corner(
+    field: &'static str,
+    changed: fn(&Preferences, &Preferences) -> bool,
+) -> Effect<AppHandle> {
+    Effect {
+        field,
+        changed,
+        apply: |app, prefs| {
+            app.state::<HotCornerService>().update_config(
+                prefs.hotcorner_corner,
+                prefs.hotcorner_size,
+                prefs.hotcorner_enabled,
+            );
+            Ok(())
+        },
+        keep: None,
+    }
+}
+
+/// Runs every effect whose fields differ between `old` and `new`. An effect
+/// with `keep` that fails is run again with `old`, its fields in `new` are
+/// set back to `old`'s and its errors become warnings. If any other effect
+/// fails, it and every effect before it are reverted to `old` and its errors
+/// returned.
+pub fn apply<C>(
+    effects: &[Effect<C>],
+    context: &C,
+    old: &Preferences,
+    new: &mut Preferences,
+) -> Result<Outcome, Vec<FieldError>> {
+    let mut outcome = Outcome::default();
+
+    for (index, effect) in effects.iter().enumerate() {
+        if !(effect.changed)(old, new) {
+            continue;
+        }
+
+        let Err(errors) = (effect.apply)(context, new) else {
+            outcome.applied.push(index);
+            continue;
+        };
+
+        match effect.keep {
+            Some(keep) => {
+                revert(effects, &[index], context, old);
+                keep(old, new);
+                outcome.warnings.extend(errors);
+            }
+            None => {
+                // Includes the failed effect, which may be half applied
+                outcome.applied.push(index);
+                revert(effects, &outcome.applied, context, old);
+                return Err(errors);
+            }
+        }
+    }
+
+    Ok(outcome)
+}
+
+/// Runs the `applied` effects again with `old`, last first.
+pub fn revert<C>(effects: &[Effect<C>], applied: &[usize], context: &C, old: &Preferences) {
+    for &index in applied.iter().rev() {
+        let effect = &effects[index];
+        if let Err(errors) = (effect.apply)(context, old) {
+            for error in errors {
+                error!("Failed to roll back {}: {}", effect.field, error.message);
+            }
+        }
+    }
+}
+
+fn apply_shortcuts(app: &AppHandle, prefs: &Preferences) -> Result<(), Vec<FieldError>> {
+    let issues =
+        ShortcutsService::apply(app, prefs).map_err(|e| vec![FieldError::new("shortcuts", e)])?;
+
+    if issues.is_empty() {
+        return Ok(());
+    }
+
+    Err(issues
+        .into_iter()
+        .map(|issue| {
+            FieldError::new(
+                format!("shortcuts.{}", issue.index),
+                format!("{}: {}", issue.shortcut, issue.message),
+            )
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    type Log = RefCell<Vec<String>>;
+
+    fn record(field: &'static str) -> Effect<Log> {
+        Effect {
+            field,
+            changed: |_, _| true,
+            apply: |log, prefs| {
+                log.borrow_mut()
+                    .push(format!("text_size={}", prefs.text_size));
+                Ok(())
+            },
+            keep: None,
+        }
+    }
+
+    #[test]
+    fn reverts_applied_effects_when_one_fails() {
+        let old = Preferences::default();
+        let new = Preferences {
+            text_size: 20,
+            ..Preferences::default()
+        };
+
+        let effects = [
+            record("first"),
+            Effect {
+                field: "unchanged",
+                changed: |_, _| false,
+                apply: |_, _| panic!("unchanged effects are skipped"),
+                keep: None,
+            },
+            record("second"),
+            Effect {
+                field: "failing",
+                changed: |_, _| true,
+                apply: |log, prefs| {
+                    log.borrow_mut()
+                        .push(format!("failing={}", prefs.text_size));
+                    if prefs.text_size == 20 {
+                        Err(vec![FieldError::new("failing", "refused")])
+                    } else {
+                        Ok(())
+                    }
+                },
+                keep: None,
+            },
+        ];
+
+        let log = Log::default();
+        let outcome = apply(&effects[..3], &log, &old, &mut new.clone()).expect("apply");
+        assert_eq!(outcome.applied, [0, 2]);
+
+        log.borrow_mut().clear();
+        let errors = apply(&effects, &log, &old, &mut new.clone()).unwrap_err();
+        assert_eq!(errors, [FieldError::new("failing", "refused")]);
+        assert_eq!(
+            *log.borrow(),
+            [
+                "text_size=20",
+                "text_size=20",
+                "failing=20",
+                "failing=14",
+                "text_size=14",
+                "text_size=14"
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_previous_values_of_effects_that_decline() {
+        let old = Preferences::default();
+        let mut new = Preferences {
+            text_size: 20,
+            hotcorner_size: 30,
+            ..Preferences::default()
+        };
+
+        let effects = [
+            Effect {
+                field: "declining",
+                changed: |old, new| old.hotcorner_size != new.hotcorner_size,
+                apply: |log: &Log, prefs| {
+                    log.borrow_mut()
+                        .push(format!("hotcorner_size={}", prefs.hotcorner_size));
+                    if prefs.hotcorner_size == 30 {
+                        Err(vec![FieldError::new("hotcorner_size", "refused")])
+                    } else {
+                        Ok(())
+                    }
+                },
+                keep: Some(|old, new| new.hotcorner_size = old.hotcorner_size),
+            },
+            record("after"),
+        ];
+
+        let log = Log::default();
+        let outcome = apply(&effects, &log, &old, &mut new).expect("apply");
+
+        assert_eq!(
+            outcome,
+            Outcome {
+                applied: vec![1],
+                warnings: vec![FieldError::new("hotcorner_size", "refused")],
+            }
+        );
+        assert_eq!(new.hotcorner_size, old.hotcorner_size);
+        assert_eq!(new.text_size, 20);
+        assert_eq!(
+            *log.borrow(),
+            ["hotcorner_size=30", "hotcorner_size=10", "text_size=20"]
+        );
+    }
+}
@@ -1,8 +1,18 @@
-use crate::models::preferences::Preferences;
+use crate::error::AppError;
+use crate::models::preferences::{
+    FieldError, Preferences, SavedPreferences, CURRENT_SCHEMA_VERSION,
+};
+use crate::services::{
+    migrations,
+    persist::{self, Access},
+    preference_effects,
+};
+use log::warn;
+use serde_json::Value;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use tauri::Manager;
+use tauri::{AppHandle, Manager};
 
 pub struct PreferencesService {
     preferences: Arc<Mutex<Preferences>>,
@@ -10,17 +20,33 @@ pub struct PreferencesService {
 }
 
 impl PreferencesService {
-    pub fn new(app_handle: &tauri::AppHandle) -> Result<Self, String> {
+    pub fn new(app_handle: &tauri::AppHandle) -> Result<Self, AppError> {
         let config_dir = app_handle
             .path()
             .app_config_dir()
-            .map_err(|e| format!("Failed to get config directory: {}", e))?;
+            .map_err(|e| AppError::internal(format!("Failed to get config directory: {}", e)))?;
 
-        fs::create_dir_all(&config_dir)
-            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+        Self::open(&config_dir)
+    }
+
+    /// Opens the preferences in `config_dir` directly, for use outside the app.
+    pub fn open(config_dir: &Path) -> Result<Self, AppError> {
+        fs::create_dir_all(config_dir)
+            .map_err(|e| AppError::io("Failed to create config directory", e))?;
+        persist::remove_temp_files(config_dir);
 
+        Self::open_with(config_dir, Access::ReadWrite)
+    }
+
+    /// Like `open`, but opening writes and removes nothing: an old file is
+    /// migrated in memory only. For `wtn`, which may run alongside the app.
+    pub fn open_read_only(config_dir: &Path) -> Result<Self, AppError> {
+        Self::open_with(config_dir, Access::ReadOnly)
+    }
+
+    fn open_with(config_dir: &Path, access: Access) -> Result<Self, AppError> {
         let config_path = config_dir.join("preferences.json");
-        let preferences = Self::load_from_file(&config_path)?;
+        let preferences = Self::load_from_file(&config_path, access)?;
 
         Ok(Self {
             preferences: Arc::new(Mutex::new(preferences)),
@@ -28,46 +54,344 @@ impl PreferencesService {
         })
     }
 
-    fn load_from_file(path: &PathBuf) -> Result<Preferences, String> {
-        if !path.exists() {
-            return Ok(Preferences::default());
-        }
+    /// Loads, migrates and repairs the preferences file. Only I/O failures are
+    /// errors; an unreadable or invalid file falls back to defaults so the app
+    /// still starts.
+    fn load_from_file(path: &Path, access: Access) -> Result<Preferences, AppError> {
+        let parse = |content: &str| {
+            serde_json::from_str::<serde_json::Value>(content)
+                .map_err(|e| format!("Failed to parse preferences: {}", e))
+        };
+        let parsed = persist::read_recovering(path, parse, access);
 
-        let content = fs::read_to_string(path)
-            .map_err(|e| format!("Failed to read preferences file: {}", e))?;
+        let document = match parsed {
+            Ok(Some(document)) => document,
+            Ok(None) => return Ok(Preferences::default()),
+            Err(err) => {
+                // Keep the unreadable file around so hand edits aren't lost
+                warn!("{}; using default preferences", err);
+                if access == Access::ReadWrite {
+                    let _ = fs::copy(path, path.with_extension("json.invalid"));
+                }
+                return Ok(Preferences::default());
+            }
+        };
 
-        let preferences: Preferences = serde_json::from_str(&content)
-            .map_err(|e| format!("Failed to parse preferences: {}", e))?;
+        let loaded = migrations::load(document);
 
-        preferences.validate()?;
+        if loaded.changed && access == Access::ReadWrite {
+            let json = serde_json::to_string_pretty(&loaded.preferences).map_err(|e| {
+                AppError::internal(format!("Failed to serialize preferences: {}", e))
+            })?;
+            persist::write_with_backup(path, json)
+                .map_err(|e| AppError::io("Failed to write preferences file", e))?;
+        }
 
-        Ok(preferences)
+        Ok(loaded.preferences)
     }
 
-    pub fn get(&self) -> Result<Preferences, String> {
+    pub fn config_path(&self) -> &Path {
+        &self.config_path
+    }
+
+    pub fn get(&self) -> Result<Preferences, AppError> {
         self.preferences
             .lock()
             .map(|prefs| prefs.clone())
-            .map_err(|e| format!("Failed to lock preferences: {}", e))
+            .map_err(|e| AppError::internal(format!("Failed to lock preferences: {}", e)))
     }
 
-    pub fn update(&self, new_prefs: Preferences) -> Result<(), String> {
-        new_prefs.validate()?;
+    pub fn update(&self, mut new_prefs: Preferences) -> Result<(), AppError> {
+        // A shortcut that fails to normalize is reported by `field_errors`
+        let _ = new_prefs.normalize_shortcuts();
+        let errors = new_prefs.field_errors();
+        if !errors.is_empty() {
+            return Err(errors.into());
+        }
 
         {
             let mut prefs = self
                 .preferences
                 .lock()
-                .map_err(|e| format!("Failed to lock preferences: {}", e))?;
+                .map_err(|e| AppError::internal(format!("Failed to lock preferences: {}", e)))?;
             *prefs = new_prefs.clone();
         }
 
-        let json = serde_json::to_string_pretty(&new_prefs)
-            .map_err(|e| format!("Failed to serialize preferences: {}", e))?;
+        self.write(&new_prefs)
+    }
+
+    /// Validates `new_prefs`, brings the live state in line with it and saves
+    /// it, returning what was saved. Nothing is saved unless every side
+    /// effect succeeds, and a failure undoes the effects already applied.
+    /// Shortcuts that fail to register are the exception: the previous ones
+    /// stay in use and are saved, and the failures come back as warnings.
+    pub fn apply(
+        &self,
+        app: &AppHandle,
+        new_prefs: Preferences,
+    ) -> Result<SavedPreferences, Vec<FieldError>> {
+        self.transition(app, true, |_| Ok(new_prefs))
+    }
+
+    /// Like `apply`, but merges a JSON merge patch into the current
+    /// preferences while holding the lock, so writers changing different
+    /// fields at the same time keep each other's changes.
+    pub fn patch(
+        &self,
+        app: &AppHandle,
+        patch: &Value,
+    ) -> Result<SavedPreferences, Vec<FieldError>> {
+        self.transition(app, true, |current| patched(current, patch))
+    }
+
+    /// Like `patch`, for changes worked out from the current preferences,
+    /// such as updating one entry in a list.
+    pub fn modify(
+        &self,
+        app: &AppHandle,
+        change: impl FnOnce(&mut Preferences),
+    ) -> Result<SavedPreferences, Vec<FieldError>> {
+        self.transition(app, true, |current| {
+            let mut new_prefs = current.clone();
+            change(&mut new_prefs);
+            Ok(new_prefs)
+        })
+    }
+
+    /// Picks up an edit made to the preferences file outside the app, applying
+    /// it like `apply` but without writing the file back. Returns `None` when
+    /// the file matches what is already in use, such as after our own save.
+    /// Errors leave the running preferences untouched.
+    pub fn reload(&self, app: &AppHandle) -> Result<Option<SavedPreferences>, Vec<FieldError>> {
+        let mut unchanged = false;
+
+        // Read under the lock so a save in progress can't be undone by an
+        // older copy of the file
+        let reloaded = self.transition(app, false, |current| {
+            let edited = read_edited(&self.config_path)?;
+            unchanged = edited == *current;
+            Ok(edited)
+        })?;
+
+        Ok((!unchanged).then_some(reloaded))
+    }
+
+    fn transition(
+        &self,
+        app: &AppHandle,
+        save: bool,
+        change: impl FnOnce(&Preferences) -> Result<Preferences, Vec<FieldError>>,
+    ) -> Result<SavedPreferences, Vec<FieldError>> {
+        let mut prefs = self.preferences.lock().map_err(|e| {
+            vec![FieldError::general(format!(
+                "Failed to lock preferences: {}",
+                e
+            ))]
+        })?;
+
+        let mut new_prefs = change(&prefs)?;
+
+        // A shortcut that fails to normalize is reported by `field_errors`
+        let _ = new_prefs.normalize_shortcuts();
+        let errors = new_prefs.field_errors();
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let effects = preference_effects::live_effects();
+        let outcome = preference_effects::apply(&effects, app, &prefs, &mut new_prefs)?;
+
+        if save {
+            if let Err(err) = self.write(&new_prefs) {
+                preference_effects::revert(&effects, &outcome.applied, app, &prefs);
+                return Err(vec![FieldError::general(err.message)]);
+            }
+        }
+
+        *prefs = new_prefs.clone();
+        Ok(SavedPreferences {
+            preferences: new_prefs,
+            warnings: outcome.warnings,
+        })
+    }
+
+    fn write(&self, prefs: &Preferences) -> Result<(), AppError> {
+        let json = serde_json::to_string_pretty(prefs)
+            .map_err(|e| AppError::internal(format!("Failed to serialize preferences: {}", e)))?;
+
+        persist::write_with_backup(&self.config_path, json)
+            .map_err(|e| AppError::io("Failed to write preferences file", e))
+    }
+}
+
+/// Reads preferences edited by hand. Unlike `load_from_file`, nothing is
+/// reset or clamped: a value that can't be used is an error to show the user.
+fn read_edited(path: &Path) -> Result<Preferences, Vec<FieldError>> {
+    let invalid = |message: String| vec![FieldError::general(message)];
+
+    let content = fs::read_to_string(path)
+        .map_err(|e| invalid(format!("Failed to read preferences file: {}", e)))?;
+    let document = serde_json::from_str::<Value>(&content)
+        .map_err(|e| invalid(format!("Failed to parse preferences file: {}", e)))?;
+    let Value::Object(mut object) = document else {
+        return Err(invalid(
+            "Preferences file must contain a JSON object".to_string(),
+        ));
+    };
+
+    migrations::upgrade(&mut object);
+    let mut preferences = serde_json::from_value::<Preferences>(Value::Object(object))
+        .map_err(|e| invalid(format!("Invalid preferences file: {}", e)))?;
+    preferences.schema_version = CURRENT_SCHEMA_VERSION;
+
+    Ok(preferences)
+}
+
+/// Returns `current` with an RFC 7386 merge patch applied. Keys the
+/// preferences don't have are rejected rather than ignored, and a value of
+/// the wrong type is reported against its top-level key.
+fn patched(current: &Preferences, patch: &Value) -> Result<Preferences, Vec<FieldError>> {
+    let original = serde_json::to_value(current).map_err(|e| {
+        vec![FieldError::general(format!(
+            "Failed to serialize preferences: {}",
+            e
+        ))]
+    })?;
+
+    let mut document = original.clone();
+    merge_patch(&mut document, patch, "")?;
+
+    serde_json::from_value(document).map_err(|err| {
+        let fields: Vec<FieldError> = patch
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter_map(|(key, value)| {
+                let mut single = original.clone();
+                merge_patch(&mut single[key], value, key).ok()?;
+                let result = serde_json::from_value::<Preferences>(single);
+                result
+                    .err()
+                    .map(|e| FieldError::new(key, format!("Invalid value for {}: {}", key, e)))
+            })
+            .collect();
+
+        if fields.is_empty() {
+            vec![FieldError::general(format!("Invalid preferences: {}", err))]
+        } else {
+            fields
+        }
+    })
+}
+
+/// Objects merge key by key, `null` resets a key to its default and any other
+/// value replaces the target.
+fn merge_patch(target: &mut Value, patch: &Value, path: &str) -> Result<(), Vec<FieldError>> {
+    let (Value::Object(fields), Value::Object(object)) = (patch, &mut *target) else {
+        *target = patch.clone();
+        return Ok(());
+    };
+
+    for (key, value) in fields {
+        let field = if path.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", path, key)
+        };
+
+        let Some(slot) = object.get_mut(key) else {
+            return Err(vec![FieldError::new(
+                &field,
+                format!("Unknown preference: {}", field),
+            )]);
+        };
+
+        if value.is_null() {
+            object.remove(key);
+        } else {
+            merge_patch(slot, value, &field)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::preferences::Theme;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    #[test]
+    fn read_only_open_leaves_an_old_file_as_it_is() {
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("preferences.json");
+        fs::write(&path, r#"{"text_size":18}"#).expect("write");
+
+        let service = PreferencesService::open_read_only(dir.path()).expect("open");
+        assert_eq!(service.get().expect("get").text_size, 18);
+        assert_eq!(
+            fs::read_to_string(&path).expect("read"),
+            r#"{"text_size":18}"#
+        );
+    }
+
+    #[test]
+    fn merge_patch_changes_only_the_named_fields() {
+        let mut current = Preferences::default();
+        current.text_size = 18;
+        current.sync.markdown_enabled = true;
+        current.window_x = Some(40);
+
+        let updated = patched(
+            &current,
+            &json!({
+                "theme": "minimal-dark",
+                "window_x": null,
+                "sync": { "markdown_path": "~/Notes" }
+            }),
+        )
+        .expect("patch");
+
+        assert_eq!(updated.theme, Theme::MinimalDark);
+        assert_eq!(updated.text_size, 18);
+        assert_eq!(updated.window_x, None);
+        assert!(updated.sync.markdown_enabled);
+        assert_eq!(updated.sync.markdown_path.as_deref(), Some("~/Notes"));
+
+        let errors = patched(&current, &json!({ "sync": { "colour": "red" } })).unwrap_err();
+        assert_eq!(errors[0].field.as_deref(), Some("sync.colour"));
+
+        let errors =
+            patched(&current, &json!({ "text_size": "big", "theme": "minimal" })).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field.as_deref(), Some("text_size"));
+    }
+
+    #[test]
+    fn reads_hand_edits_strictly() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("preferences.json");
+
+        fs::write(&path, r#"{ "text_size": 20, "theme": "minimal" "#).unwrap();
+        let errors = read_edited(&path).unwrap_err();
+        assert!(errors[0]
+            .message
+            .starts_with("Failed to parse preferences file"));
 
-        fs::write(&self.config_path, json)
-            .map_err(|e| format!("Failed to write preferences file: {}", e))?;
+        fs::write(&path, r#"{ "text_size": "big" }"#).unwrap();
+        assert!(read_edited(&path).is_err());
 
-        Ok(())
+        // Older layouts are still upgraded
+        fs::write(
+            &path,
+            r#"{ "schema_version": 2, "text_size": 20, "keyboard_shortcut": "Alt+Command+K" }"#,
+        )
+        .unwrap();
+        let edited = read_edited(&path).expect("edited preferences");
+        assert_eq!(edited.text_size, 20);
+        assert_eq!(edited.schema_version, CURRENT_SCHEMA_VERSION);
+        assert_eq!(edited.shortcuts[0].shortcut, "Alt+Command+K");
     }
 }
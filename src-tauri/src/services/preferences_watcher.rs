@@ -0,0 +1,83 @@
+use crate::models::preferences::FieldError;
+use crate::services::preferences::PreferencesService;
+use log::{error, warn};
+use std::fs;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Emitter, Manager};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Polls `preferences.json` and applies edits made by hand or by config
+/// management while the app is running.
+#[derive(Default)]
+pub struct PreferencesWatcher {
+    is_running: Arc<AtomicBool>,
+}
+
+impl PreferencesWatcher {
+    pub fn start(&self, app: AppHandle) {
+        if self.is_running.load(Ordering::SeqCst) {
+            return;
+        }
+
+        self.is_running.store(true, Ordering::SeqCst);
+
+        let is_running = Arc::clone(&self.is_running);
+
+        thread::spawn(move || {
+            let path = app
+                .state::<PreferencesService>()
+                .config_path()
+                .to_path_buf();
+            let stamp = || {
+                fs::metadata(&path)
+                    .and_then(|meta| Ok((meta.modified()?, meta.len())))
+                    .ok()
+            };
+            let mut last_seen: Option<(SystemTime, u64)> = stamp();
+
+            while is_running.load(Ordering::SeqCst) {
+                thread::sleep(POLL_INTERVAL);
+
+                // A missing file is left alone until something writes it again
+                let current = stamp();
+                if current.is_none() || current == last_seen {
+                    continue;
+                }
+                last_seen = current;
+
+                Self::reload(&app);
+            }
+        });
+    }
+
+    fn reload(app: &AppHandle) {
+        match app.state::<PreferencesService>().reload(app) {
+            Ok(Some(saved)) => {
+                if let Err(e) = app.emit("preferences-updated", saved.preferences) {
+                    error!("Failed to emit preferences-updated event: {}", e);
+                }
+                // The rest of the edit is in use; only these were left out
+                if !saved.warnings.is_empty() {
+                    Self::report_ignored(app, saved.warnings);
+                }
+            }
+            Ok(None) => {}
+            Err(errors) => Self::report_ignored(app, errors),
+        }
+    }
+
+    fn report_ignored(app: &AppHandle, errors: Vec<FieldError>) {
+        for error in &errors {
+            warn!("Ignored edit to preferences file: {}", error.message);
+        }
+        if let Err(e) = app.emit("preferences-reload-failed", errors) {
+            error!("Failed to emit preferences-reload-failed event: {}", e);
+        }
+    }
+}
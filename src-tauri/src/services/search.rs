@@ -0,0 +1,526 @@
+use crate::error::ErrorCode;
+use crate::models::note::NoteMeta;
+use crate::models::search::{SearchHit, SearchSource, SnippetPart};
+use crate::services::storage::StorageService;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+
+pub const DEFAULT_LIMIT: usize = 20;
+
+/// Characters of context kept on each side of the first match in a snippet.
+const SNIPPET_CONTEXT: usize = 60;
+/// Title words count this many times over body words when ranking.
+const TITLE_WEIGHT: u32 = 3;
+/// A query word that only matches as a prefix scores this fraction of a whole-word match.
+const PREFIX_WEIGHT: f32 = 0.7;
+const BM25_K1: f32 = 1.2;
+const BM25_B: f32 = 0.75;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum DocKey {
+    Note(String),
+    File(PathBuf),
+}
+
+/// What the indexed copy was built from, to tell whether it is stale.
+#[derive(Debug, Clone, PartialEq)]
+enum Version {
+    Note(DateTime<Utc>, String),
+    File(SystemTime),
+}
+
+struct Document {
+    title: String,
+    text: String,
+    version: Version,
+    terms: HashMap<String, u32>,
+    length: u32,
+}
+
+#[derive(Default)]
+struct SearchIndex {
+    documents: HashMap<DocKey, Document>,
+    postings: HashMap<String, HashMap<DocKey, u32>>,
+    total_length: u64,
+}
+
+/// In-memory full-text index over every note and the Markdown files in the
+/// export folder, ranked with BM25.
+#[derive(Default)]
+pub struct SearchService {
+    index: Arc<Mutex<SearchIndex>>,
+}
+
+impl SearchService {
+    /// Indexes or re-indexes one note from its stored HTML.
+    pub fn index_note(&self, note: &NoteMeta, html: &str) -> Result<(), String> {
+        let mut index = self.lock_index()?;
+        index.upsert(
+            DocKey::Note(note.id.clone()),
+            note.title.clone(),
+            strip_html(html),
+            Version::Note(note.updated_at, note.title.clone()),
+        );
+        Ok(())
+    }
+
+    /// Drops every note from the index, so nothing readable stays in memory
+    /// once encrypted notes lock.
+    pub fn forget_notes(&self) -> Result<(), String> {
+        self.lock_index()?
+            .retain(|key| !matches!(key, DocKey::Note(_)));
+        Ok(())
+    }
+
+    /// Brings the note entries in line with storage, re-reading only notes
+    /// whose title or modification time changed.
+    pub fn refresh_notes(&self, storage: &StorageService) -> Result<(), String> {
+        let notes = storage.list_notes()?;
+
+        let stale: Vec<NoteMeta> = {
+            let mut index = self.lock_index()?;
+
+            let live: HashSet<DocKey> = notes
+                .iter()
+                .map(|note| DocKey::Note(note.id.clone()))
+                .collect();
+            index.retain(|key| !matches!(key, DocKey::Note(_)) || live.contains(key));
+
+            notes
+                .into_iter()
+                .filter(|note| {
+                    let current = Version::Note(note.updated_at, note.title.clone());
+                    index.version(&DocKey::Note(note.id.clone())) != Some(&current)
+                })
+                .collect()
+        };
+
+        for note in stale {
+            // Locked notes can't be read; they are indexed once unlocked
+            let html = match storage.read_note_by_id(&note.id) {
+                Err(err) if err.is(ErrorCode::Locked) => continue,
+                result => result?,
+            };
+            self.index_note(&note, &html)?;
+        }
+
+        Ok(())
+    }
+
+    /// Brings the file entries in line with the `.md` files in `dir`, or drops
+    /// them all when there is no export folder.
+    pub fn refresh_files(&self, dir: Option<&Path>) -> Result<(), String> {
+        let mut files: Vec<(PathBuf, SystemTime)> = Vec::new();
+
+        if let Some(dir) = dir.filter(|dir| dir.is_dir()) {
+            let entries =
+                fs::read_dir(dir).map_err(|e| format!("Failed to read export folder: {}", e))?;
+
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let is_markdown = path
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("md"));
+                if !is_markdown {
+                    continue;
+                }
+                if let Ok(modified) = entry.metadata().and_then(|meta| meta.modified()) {
+                    files.push((path, modified));
+                }
+            }
+        }
+
+        let mut index = self.lock_index()?;
+
+        let live: HashSet<DocKey> = files
+            .iter()
+            .map(|(path, _)| DocKey::File(path.clone()))
+            .collect();
+        index.retain(|key| !matches!(key, DocKey::File(_)) || live.contains(key));
+
+        for (path, modified) in files {
+            let key = DocKey::File(path.clone());
+            if index.version(&key) == Some(&Version::File(modified)) {
+                continue;
+            }
+
+            // Unreadable files (e.g. mid-write) are picked up on the next search
+            let Ok(text) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let title = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            index.upsert(
+                key,
+                title,
+                collapse_whitespace(&text),
+                Version::File(modified),
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>, String> {
+        Ok(self.lock_index()?.search(query, limit))
+    }
+
+    fn lock_index(&self) -> Result<std::sync::MutexGuard<'_, SearchIndex>, String> {
+        self.index
+            .lock()
+            .map_err(|e| format!("Failed to lock search index: {}", e))
+    }
+}
+
+impl SearchIndex {
+    fn version(&self, key: &DocKey) -> Option<&Version> {
+        self.documents.get(key).map(|doc| &doc.version)
+    }
+
+    fn upsert(&mut self, key: DocKey, title: String, text: String, version: Version) {
+        self.remove(&key);
+
+        let mut terms: HashMap<String, u32> = HashMap::new();
+        for term in tokenize(&text) {
+            *terms.entry(term).or_default() += 1;
+        }
+        for term in tokenize(&title) {
+            *terms.entry(term).or_default() += TITLE_WEIGHT;
+        }
+
+        let length = terms.values().sum::<u32>();
+        for (term, count) in &terms {
+            self.postings
+                .entry(term.clone())
+                .or_default()
+                .insert(key.clone(), *count);
+        }
+        self.total_length += u64::from(length);
+
+        self.documents.insert(
+            key,
+            Document {
+                title,
+                text,
+                version,
+                terms,
+                length,
+            },
+        );
+    }
+
+    fn remove(&mut self, key: &DocKey) {
+        let Some(doc) = self.documents.remove(key) else {
+            return;
+        };
+
+        for term in doc.terms.keys() {
+            if let Some(docs) = self.postings.get_mut(term) {
+                docs.remove(key);
+                if docs.is_empty() {
+                    self.postings.remove(term);
+                }
+            }
+        }
+        self.total_length -= u64::from(doc.length);
+    }
+
+    fn retain(&mut self, keep: impl Fn(&DocKey) -> bool) {
+        let gone: Vec<DocKey> = self
+            .documents
+            .keys()
+            .filter(|key| !keep(key))
+            .cloned()
+            .collect();
+        for key in gone {
+            self.remove(&key);
+        }
+    }
+
+    /// Every query word must match, either as a whole word or as the start of
+    /// one, so results narrow while the user is still typing.
+    fn search(&self, query: &str, limit: usize) -> Vec<SearchHit> {
+        let mut seen = HashSet::new();
+        let mut words = tokenize(query);
+        words.retain(|word| seen.insert(word.clone()));
+        if words.is_empty() || self.documents.is_empty() {
+            return Vec::new();
+        }
+
+        let doc_count = self.documents.len() as f32;
+        let average_length = (self.total_length as f32 / doc_count).max(1.0);
+        let mut scores: HashMap<&DocKey, f32> = HashMap::new();
+
+        for (position, word) in words.iter().enumerate() {
+            let mut word_scores: HashMap<&DocKey, f32> = HashMap::new();
+
+            for (term, docs) in &self.postings {
+                if !term.starts_with(word.as_str()) {
+                    continue;
+                }
+                let weight = if term == word { 1.0 } else { PREFIX_WEIGHT };
+                let df = docs.len() as f32;
+                let idf = (1.0 + (doc_count - df + 0.5) / (df + 0.5)).ln();
+
+                for (key, count) in docs {
+                    let length = self.documents[key].length as f32;
+                    let tf = *count as f32;
+                    let score = weight * idf * tf * (BM25_K1 + 1.0)
+                        / (tf + BM25_K1 * (1.0 - BM25_B + BM25_B * length / average_length));
+
+                    let best = word_scores.entry(key).or_default();
+                    *best = best.max(score);
+                }
+            }
+
+            if position == 0 {
+                scores = word_scores;
+            } else {
+                scores.retain(|key, _| word_scores.contains_key(key));
+                for (key, score) in scores.iter_mut() {
+                    *score += word_scores[key];
+                }
+            }
+        }
+
+        let mut ranked: Vec<(&DocKey, f32)> = scores.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranked.truncate(limit);
+
+        ranked
+            .into_iter()
+            .map(|(key, score)| {
+                let doc = &self.documents[key];
+                let (source, note_id, path) = match key {
+                    DocKey::Note(id) => (SearchSource::Note, Some(id.clone()), None),
+                    DocKey::File(path) => (
+                        SearchSource::File,
+                        None,
+                        Some(path.to_string_lossy().to_string()),
+                    ),
+                };
+
+                SearchHit {
+                    source,
+                    note_id,
+                    path,
+                    title: doc.title.clone(),
+                    score,
+                    snippet: snippet(&doc.text, &words),
+                }
+            })
+            .collect()
+    }
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Cuts a window of text around the first match and marks every word in it
+/// that starts with one of the query words.
+fn snippet(text: &str, words: &[String]) -> Vec<SnippetPart> {
+    let mut matches: Vec<(usize, usize)> = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        match (start, c.is_alphanumeric()) {
+            (None, true) => start = Some(i),
+            (Some(s), false) => {
+                let word = text[s..i].to_lowercase();
+                if words.iter().any(|query| word.starts_with(query.as_str())) {
+                    matches.push((s, i));
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+
+    let first = matches.first().map(|(s, _)| *s).unwrap_or(0);
+    let window_start = text[..first]
+        .char_indices()
+        .rev()
+        .nth(SNIPPET_CONTEXT.saturating_sub(1))
+        .map(|(i, _)| i)
+        .unwrap_or(0);
+    let window_end = text[first..]
+        .char_indices()
+        .nth(SNIPPET_CONTEXT * 2)
+        .map(|(i, _)| first + i)
+        .unwrap_or(text.len());
+
+    let mut parts: Vec<SnippetPart> = Vec::new();
+    let mut push = |text: &str, highlight: bool| {
+        if !text.is_empty() {
+            parts.push(SnippetPart {
+                text: text.to_string(),
+                highlight,
+            });
+        }
+    };
+
+    if window_start > 0 {
+        push("…", false);
+    }
+    let mut cursor = window_start;
+    for (s, e) in matches {
+        if s < window_start || e > window_end {
+            continue;
+        }
+        push(&text[cursor..s], false);
+        push(&text[s..e], true);
+        cursor = e;
+    }
+    push(&text[cursor..window_end], false);
+    if window_end < text.len() {
+        push("…", false);
+    }
+
+    parts
+}
+
+/// Reduces stored note HTML to the words a person would read.
+fn strip_html(html: &str) -> String {
+    static TAG_REGEX: OnceLock<Regex> = OnceLock::new();
+    let tag_regex = TAG_REGEX.get_or_init(|| Regex::new(r"<[^>]*>").expect("valid regex"));
+    let without_tags = tag_regex.replace_all(html, " ");
+
+    let decoded = without_tags
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+
+    collapse_whitespace(&decoded)
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn note(id: &str, title: &str) -> NoteMeta {
+        NoteMeta::new(id.to_string(), title.to_string())
+    }
+
+    #[test]
+    fn ranks_and_highlights_matches() {
+        let search = SearchService::default();
+        search
+            .index_note(
+                &note("a", "Groceries"),
+                "<p>Buy milk &amp; eggs</p><p>Call the plumber</p>",
+            )
+            .expect("index");
+        search
+            .index_note(
+                &note("b", "Milk tasting"),
+                "<p>Milk, milk and more milk</p>",
+            )
+            .expect("index");
+
+        let hits = search.search("milk", DEFAULT_LIMIT).expect("search");
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].note_id.as_deref(), Some("b"));
+
+        let hits = search.search("plum eg", DEFAULT_LIMIT).expect("search");
+        assert_eq!(hits.len(), 1);
+        let highlighted: Vec<&str> = hits[0]
+            .snippet
+            .iter()
+            .filter(|part| part.highlight)
+            .map(|part| part.text.as_str())
+            .collect();
+        assert_eq!(highlighted, vec!["eggs", "plumber"]);
+    }
+
+    #[test]
+    fn reindexing_replaces_old_terms() {
+        let search = SearchService::default();
+        let mut meta = note("a", "Note");
+        search.index_note(&meta, "<p>apples</p>").expect("index");
+
+        meta.updated_at = Utc::now();
+        search.index_note(&meta, "<p>oranges</p>").expect("index");
+
+        assert!(search
+            .search("apples", DEFAULT_LIMIT)
+            .expect("search")
+            .is_empty());
+        assert_eq!(
+            search
+                .search("oranges", DEFAULT_LIMIT)
+                .expect("search")
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn skips_locked_notes() {
+        let dir = tempdir().expect("tempdir");
+        let storage = StorageService::open(dir.path()).expect("open storage");
+        let id = storage.active_note_id().expect("active note");
+        storage
+            .write_note_by_id(&id, "<p>secret hammock</p>")
+            .expect("write");
+        storage.enable_encryption("correct horse").expect("encrypt");
+        storage.lock().expect("lock");
+
+        let search = SearchService::default();
+        search.refresh_notes(&storage).expect("refresh");
+        assert!(search
+            .search("hammock", DEFAULT_LIMIT)
+            .expect("search")
+            .is_empty());
+
+        storage.unlock("correct horse").expect("unlock");
+        search.refresh_notes(&storage).expect("refresh");
+        assert_eq!(
+            search
+                .search("hammock", DEFAULT_LIMIT)
+                .expect("search")
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn indexes_markdown_files_in_export_folder() {
+        let dir = tempdir().expect("tempdir");
+        fs::write(dir.path().join("Ideas.md"), "# Ideas\n\nA hammock for cats").expect("write");
+        fs::write(dir.path().join("ignored.txt"), "hammock").expect("write");
+
+        let search = SearchService::default();
+        search.refresh_files(Some(dir.path())).expect("refresh");
+
+        let hits = search.search("hammock", DEFAULT_LIMIT).expect("search");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].source, SearchSource::File);
+        assert_eq!(hits[0].title, "Ideas");
+
+        fs::remove_file(dir.path().join("Ideas.md")).expect("remove");
+        search.refresh_files(Some(dir.path())).expect("refresh");
+        assert!(search
+            .search("hammock", DEFAULT_LIMIT)
+            .expect("search")
+            .is_empty());
+    }
+}
@@ -1,33 +1,78 @@
-use tauri::{AppHandle, Emitter};
-use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut};
+use crate::commands::window;
+use crate::error::AppError;
+use crate::models::preferences::{Preferences, ShortcutAction, ShortcutBinding};
+use crate::services::capture::{self, CaptureStyle};
+use crate::services::{preferences::PreferencesService, storage::StorageService, sync::SyncQueue};
+use log::{error, warn};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
 
 pub struct ShortcutsService;
 
+/// A binding that could not be registered. The others still are.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ShortcutIssue {
+    /// Position of the binding in `Preferences::shortcuts`.
+    pub index: usize,
+    pub shortcut: String,
+    pub action: ShortcutAction,
+    pub message: String,
+}
+
+impl ShortcutIssue {
+    fn new(index: usize, binding: &ShortcutBinding, message: String) -> Self {
+        Self {
+            index,
+            shortcut: binding.shortcut.clone(),
+            action: binding.action,
+            message,
+        }
+    }
+}
+
 impl ShortcutsService {
-    pub fn register(app: &AppHandle, shortcut_str: &str) -> Result<(), String> {
+    /// Replaces every registered shortcut with the bindings in `prefs`,
+    /// returning the ones that were skipped and why.
+    pub fn apply(app: &AppHandle, prefs: &Preferences) -> Result<Vec<ShortcutIssue>, String> {
         // Unregister any existing shortcuts first
         Self::unregister_all(app)?;
 
-        let shortcut: Shortcut = shortcut_str
-            .parse()
-            .map_err(|e| format!("Invalid shortcut format: {:?}", e))?;
+        if !prefs.shortcut_enabled {
+            return Ok(Vec::new());
+        }
 
-        app.global_shortcut()
-            .on_shortcut(shortcut, move |app, _shortcut, event| {
-                // Only respond to key press, not release, to avoid double-triggering
-                if event.state == tauri_plugin_global_shortcut::ShortcutState::Pressed {
-                    if let Err(e) = app.emit("toggle-window", ()) {
-                        eprintln!("Failed to emit toggle-window event: {}", e);
-                    }
-                    // Notify frontend that window was triggered via shortcut (implicit focus)
-                    if let Err(e) = app.emit("shortcut-triggered", ()) {
-                        eprintln!("Failed to emit shortcut-triggered event: {}", e);
-                    }
-                }
-            })
-            .map_err(|e| format!("Failed to register shortcut: {}", e))?;
+        let (parsed, mut issues) = Self::parse_bindings(&prefs.shortcuts);
+        for (index, shortcut, binding) in parsed {
+            let action = binding.action;
+            let registered =
+                app.global_shortcut()
+                    .on_shortcut(shortcut, move |app, _shortcut, event| {
+                        // Only respond to key press, not release, to avoid double-triggering
+                        if event.state == ShortcutState::Pressed {
+                            Self::run(app, action);
+                        }
+                    });
+
+            if let Err(e) = registered {
+                issues.push(ShortcutIssue::new(
+                    index,
+                    binding,
+                    format!("Failed to register shortcut: {}", e),
+                ));
+            }
+        }
+
+        for issue in &issues {
+            warn!(
+                "Skipped shortcut {} ({}): {}",
+                issue.shortcut,
+                issue.action.label(),
+                issue.message
+            );
+        }
 
-        Ok(())
+        Ok(issues)
     }
 
     pub fn unregister_all(app: &AppHandle) -> Result<(), String> {
@@ -35,4 +80,130 @@ impl ShortcutsService {
             .unregister_all()
             .map_err(|e| format!("Failed to unregister shortcuts: {}", e))
     }
+
+    /// Parses each binding, keeping the first of any that share a key
+    /// combination and reporting the rest.
+    fn parse_bindings(
+        bindings: &[ShortcutBinding],
+    ) -> (Vec<(usize, Shortcut, &ShortcutBinding)>, Vec<ShortcutIssue>) {
+        let mut parsed: Vec<(usize, Shortcut, &ShortcutBinding)> = Vec::new();
+        let mut issues = Vec::new();
+
+        for (index, binding) in bindings.iter().enumerate() {
+            let shortcut: Shortcut = match binding.shortcut.parse() {
+                Ok(shortcut) => shortcut,
+                Err(e) => {
+                    issues.push(ShortcutIssue::new(
+                        index,
+                        binding,
+                        format!("Invalid shortcut format: {:?}", e),
+                    ));
+                    continue;
+                }
+            };
+
+            match parsed.iter().find(|(_, existing, _)| *existing == shortcut) {
+                Some((_, _, first)) => issues.push(ShortcutIssue::new(
+                    index,
+                    binding,
+                    format!("Already used for {}", first.action.label()),
+                )),
+                None => parsed.push((index, shortcut, binding)),
+            }
+        }
+
+        (parsed, issues)
+    }
+
+    fn run(app: &AppHandle, action: ShortcutAction) {
+        let result = match action {
+            ShortcutAction::ToggleWindow => {
+                Self::emit(app, "toggle-window");
+                // Notify frontend that window was triggered via shortcut (implicit focus)
+                Self::emit(app, "shortcut-triggered");
+                Ok(())
+            }
+            ShortcutAction::ShowWindow => window::show_window_command(app.clone())
+                .map(|_| Self::emit(app, "shortcut-triggered"))
+                .map_err(String::from),
+            ShortcutAction::HideWindow => {
+                window::hide_window_command(app.clone()).map_err(String::from)
+            }
+            ShortcutAction::OpenPreferences => {
+                window::open_preferences_window(app.clone()).map_err(String::from)
+            }
+            ShortcutAction::SyncNow => Self::sync_now(app),
+            ShortcutAction::SwitchTheme => Self::switch_theme(app),
+            ShortcutAction::CaptureClipboard => {
+                capture::capture_clipboard(app, CaptureStyle::Paragraphs).map_err(String::from)
+            }
+            ShortcutAction::CaptureBullet => {
+                capture::capture_clipboard(app, CaptureStyle::TimestampedBullet)
+                    .map_err(String::from)
+            }
+        };
+
+        if let Err(e) = result {
+            error!("Shortcut action {} failed: {}", action.label(), e);
+        }
+    }
+
+    fn emit(app: &AppHandle, event: &str) {
+        if let Err(e) = app.emit(event, ()) {
+            error!("Failed to emit {} event: {}", event, e);
+        }
+    }
+
+    fn sync_now(app: &AppHandle) -> Result<(), String> {
+        let id = app.state::<StorageService>().active_note_id()?;
+        app.state::<SyncQueue>().enqueue(app, &id)
+    }
+
+    fn switch_theme(app: &AppHandle) -> Result<(), String> {
+        let prefs_service = app.state::<PreferencesService>();
+        let theme = prefs_service.get()?.theme.next();
+        let prefs = prefs_service
+            .patch(app, &serde_json::json!({ "theme": theme }))
+            .map_err(AppError::from)?;
+
+        app.emit("preferences-updated", prefs)
+            .map_err(|e| format!("Failed to emit preferences-updated event: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binding(shortcut: &str, action: ShortcutAction) -> ShortcutBinding {
+        ShortcutBinding {
+            shortcut: shortcut.to_string(),
+            action,
+        }
+    }
+
+    #[test]
+    fn reports_conflicts_and_bad_formats_per_binding() {
+        let bindings = [
+            binding("Alt+Command+N", ShortcutAction::ToggleWindow),
+            binding("Command+Nope", ShortcutAction::SyncNow),
+            binding("Cmd+Alt+N", ShortcutAction::CaptureClipboard),
+            binding("Alt+Command+B", ShortcutAction::CaptureBullet),
+        ];
+
+        let (parsed, issues) = ShortcutsService::parse_bindings(&bindings);
+
+        let kept: Vec<_> = parsed
+            .iter()
+            .map(|(_, _, binding)| binding.action)
+            .collect();
+        assert_eq!(
+            kept,
+            [ShortcutAction::ToggleWindow, ShortcutAction::CaptureBullet]
+        );
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].index, 1);
+        assert_eq!(issues[0].action, ShortcutAction::SyncNow);
+        assert_eq!(issues[1].message, "Already used for Show/hide note");
+    }
 }
@@ -1,39 +1,715 @@
+use crate::error::{AppError, ErrorCode};
+use crate::models::note::{
+    normalize_title, EncryptionStatus, NoteMeta, NotesIndex, Revision, RevisionDiff,
+};
+use crate::services::history::{self, HistoryStore};
+use crate::services::persist::{self, Access};
+use crate::services::vault::{self, KdfParams, Vault, VaultKey};
+use chrono::Utc;
+use log::warn;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
 use tauri::Manager;
+use uuid::Uuid;
 
+const LEGACY_NOTE_FILENAME: &str = "note.txt";
+const INDEX_FILENAME: &str = "notes.json";
+const NOTES_DIRNAME: &str = "notes";
+const FIRST_NOTE_TITLE: &str = "My Note";
+
+/// Note content can be encrypted at rest; titles and timestamps in the
+/// index stay readable so the note list works while locked.
+///
+/// Lock order is index, then vault, wherever both are held.
 pub struct StorageService {
-    note_path: PathBuf,
+    notes_dir: PathBuf,
+    index_path: PathBuf,
+    index: Arc<Mutex<NotesIndex>>,
+    vault: Arc<Mutex<Vault>>,
+    access: Access,
 }
 
 impl StorageService {
-    pub fn new(app_handle: &tauri::AppHandle) -> Result<Self, String> {
+    pub fn new(app_handle: &tauri::AppHandle) -> Result<Self, AppError> {
         let data_dir = app_handle
             .path()
             .app_data_dir()
             .map_err(|e| format!("Failed to get data directory: {}", e))?;
 
-        fs::create_dir_all(&data_dir)
-            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+        Self::open(&data_dir)
+    }
+
+    pub fn open(data_dir: &Path) -> Result<Self, AppError> {
+        Self::open_with(data_dir, Access::ReadWrite)
+    }
+
+    /// Opens the notes for reading without repairing, migrating or tidying
+    /// any file, for `wtn` to use while the app may be running.
+    pub fn open_read_only(data_dir: &Path) -> Result<Self, AppError> {
+        Self::open_with(data_dir, Access::ReadOnly)
+    }
+
+    fn open_with(data_dir: &Path, access: Access) -> Result<Self, AppError> {
+        let notes_dir = data_dir.join(NOTES_DIRNAME);
+
+        if access == Access::ReadWrite {
+            fs::create_dir_all(&notes_dir)
+                .map_err(|e| AppError::io("Failed to create data directory", e))?;
+            persist::remove_temp_files(data_dir);
+        }
+
+        let index_path = data_dir.join(INDEX_FILENAME);
+        let loaded = persist::read_recovering(&index_path, Self::parse_index, access)
+            .unwrap_or_else(|err| {
+                warn!(
+                    "Notes index is unreadable, rebuilding from note files: {}",
+                    err
+                );
+                None
+            });
+
+        let index = match loaded {
+            Some(index) => index,
+            None => Self::rebuild_index(data_dir, &notes_dir, &index_path, access)?,
+        };
 
-        let note_path = data_dir.join("note.txt");
+        let vault = Vault::open(data_dir, access)?;
 
-        Ok(Self { note_path })
+        Ok(Self {
+            notes_dir,
+            index_path,
+            index: Arc::new(Mutex::new(index)),
+            vault: Arc::new(Mutex::new(vault)),
+            access,
+        })
     }
 
-    pub fn read_note(&self) -> Result<String, String> {
-        if !self.note_path.exists() {
-            return Ok(Self::default_note());
+    pub fn read_note(&self) -> Result<String, AppError> {
+        let id = self.active_note_id()?;
+        self.read_note_by_id(&id)
+    }
+
+    pub fn read_note_by_id(&self, id: &str) -> Result<String, AppError> {
+        self.ensure_exists(id)?;
+        self.read_content(id)
+    }
+
+    pub fn write_note_by_id(&self, id: &str, content: &str) -> Result<(), AppError> {
+        let mut index = self.lock_index()?;
+        self.write_content(&mut index, id, content)
+    }
+
+    /// Replaces a note with `change` applied to its current content, holding
+    /// the index lock throughout so concurrent appends can't drop each other.
+    /// Returns what was saved.
+    pub fn update_note_by_id(
+        &self,
+        id: &str,
+        change: impl FnOnce(&str) -> String,
+    ) -> Result<String, AppError> {
+        let mut index = self.lock_index()?;
+        if index.find(id).is_none() {
+            return Err(AppError::not_found("Note", id));
         }
 
-        fs::read_to_string(&self.note_path).map_err(|e| format!("Failed to read note: {}", e))
+        let content = change(&self.read_content(id)?);
+        self.write_content(&mut index, id, &content)?;
+        Ok(content)
+    }
+
+    pub fn list_revisions(&self, id: &str) -> Result<Vec<Revision>, AppError> {
+        self.ensure_exists(id)?;
+        let mut revisions = self.history(id, None).list()?;
+        revisions.reverse();
+        Ok(revisions)
+    }
+
+    pub fn diff_revision(&self, id: &str, revision_id: &str) -> Result<RevisionDiff, AppError> {
+        self.ensure_exists(id)?;
+        let revision = self.history(id, self.key()?).read(revision_id)?;
+        let current = self.read_note_by_id(id)?;
+
+        Ok(RevisionDiff {
+            revision_id: revision_id.to_string(),
+            lines: history::diff(&revision, &current),
+        })
     }
 
-    pub fn write_note(&self, content: &str) -> Result<(), String> {
-        fs::write(&self.note_path, content).map_err(|e| format!("Failed to write note: {}", e))
+    /// Writes a revision's content back as the current note. The restore is
+    /// itself recorded, so it can be undone from the history too.
+    pub fn restore_revision(&self, id: &str, revision_id: &str) -> Result<String, AppError> {
+        self.ensure_exists(id)?;
+        let content = self.history(id, self.key()?).read(revision_id)?;
+        self.write_note_by_id(id, &content)?;
+        Ok(content)
+    }
+
+    pub fn active_note_id(&self) -> Result<String, AppError> {
+        self.with_index(|index| Ok(index.active_note_id.clone()))
+    }
+
+    pub fn active_note(&self) -> Result<NoteMeta, AppError> {
+        self.with_index(|index| {
+            index
+                .active()
+                .cloned()
+                .ok_or_else(|| AppError::internal("Active note is missing from the index"))
+        })
+    }
+
+    pub fn note(&self, id: &str) -> Result<NoteMeta, AppError> {
+        self.with_index(|index| {
+            index
+                .find(id)
+                .cloned()
+                .ok_or_else(|| AppError::not_found("Note", id))
+        })
+    }
+
+    pub fn list_notes(&self) -> Result<Vec<NoteMeta>, AppError> {
+        self.with_index(|index| Ok(index.notes.clone()))
+    }
+
+    pub fn create_note(&self, title: &str) -> Result<NoteMeta, AppError> {
+        let title = normalize_title(title)?;
+        let mut index = self.lock_index()?;
+
+        let note = NoteMeta::new(Uuid::new_v4().to_string(), title);
+        persist::write_atomic(&self.note_path(&note.id), "")
+            .map_err(|e| AppError::io("Failed to create note", e))?;
+
+        index.notes.push(note.clone());
+        self.persist_index(&index)?;
+
+        Ok(note)
+    }
+
+    pub fn rename_note(&self, id: &str, title: &str) -> Result<NoteMeta, AppError> {
+        let title = normalize_title(title)?;
+        let mut index = self.lock_index()?;
+
+        let note = index
+            .find_mut(id)
+            .ok_or_else(|| AppError::not_found("Note", id))?;
+        note.title = title;
+        note.updated_at = Utc::now();
+        let renamed = note.clone();
+
+        self.persist_index(&index)?;
+
+        Ok(renamed)
+    }
+
+    /// Removes a note and its content. The last remaining note cannot be
+    /// deleted; deleting the active note makes the first remaining one active.
+    pub fn delete_note(&self, id: &str) -> Result<(), AppError> {
+        let mut index = self.lock_index()?;
+
+        let position = index
+            .notes
+            .iter()
+            .position(|note| note.id == id)
+            .ok_or_else(|| AppError::not_found("Note", id))?;
+
+        if index.notes.len() == 1 {
+            return Err(
+                AppError::new(ErrorCode::Conflict, "Cannot delete the only note").with_target(id),
+            );
+        }
+
+        index.notes.remove(position);
+        if index.active_note_id == id {
+            index.active_note_id = index.notes[0].id.clone();
+        }
+
+        self.persist_index(&index)?;
+
+        persist::remove_with_backup(&self.note_path(id))
+            .map_err(|e| AppError::io("Failed to delete note", e))?;
+
+        Ok(self.history(id, None).remove_all()?)
+    }
+
+    pub fn switch_note(&self, id: &str) -> Result<NoteMeta, AppError> {
+        let mut index = self.lock_index()?;
+
+        let note = index
+            .find(id)
+            .cloned()
+            .ok_or_else(|| AppError::not_found("Note", id))?;
+        index.active_note_id = note.id.clone();
+
+        self.persist_index(&index)?;
+
+        Ok(note)
+    }
+
+    pub fn encryption_status(&self) -> Result<EncryptionStatus, AppError> {
+        let vault = self.lock_vault()?;
+        Ok(EncryptionStatus {
+            enabled: vault.is_enabled(),
+            locked: vault.is_locked(),
+        })
+    }
+
+    /// Encrypts every note and its history with a key derived from
+    /// `passphrase`. The vault is written first, so an interrupted migration
+    /// leaves plaintext files that the next unlock finishes encrypting.
+    pub fn enable_encryption(&self, passphrase: &str) -> Result<(), AppError> {
+        let index = self.lock_index()?;
+        let mut vault = self.lock_vault()?;
+
+        if vault.is_enabled() {
+            return Err(AppError::new(
+                ErrorCode::Conflict,
+                "Encryption is already enabled",
+            ));
+        }
+
+        let key = vault.create(passphrase, KdfParams::default())?;
+        self.reencode_all(&index, &key, true)
+    }
+
+    /// Decrypts everything back to plaintext and forgets the vault.
+    pub fn disable_encryption(&self, passphrase: &str) -> Result<(), AppError> {
+        let index = self.lock_index()?;
+        let mut vault = self.lock_vault()?;
+
+        if !vault.is_enabled() {
+            return Err(AppError::new(
+                ErrorCode::Conflict,
+                "Encryption is not enabled",
+            ));
+        }
+
+        let key = vault.unlock(passphrase)?;
+        self.reencode_all(&index, &key, false)?;
+        Ok(vault.remove()?)
+    }
+
+    pub fn unlock(&self, passphrase: &str) -> Result<(), AppError> {
+        let index = self.lock_index()?;
+        let mut vault = self.lock_vault()?;
+
+        let key = vault.unlock(passphrase)?;
+        self.reencode_all(&index, &key, true)
+    }
+
+    pub fn lock(&self) -> Result<(), AppError> {
+        self.lock_vault()?.lock();
+        Ok(())
+    }
+
+    /// Locks when notes have not been read or written for `idle`. Returns
+    /// whether this call locked them.
+    pub fn lock_if_idle(&self, idle: Duration) -> Result<bool, AppError> {
+        let mut vault = self.lock_vault()?;
+
+        if vault.is_enabled() && !vault.is_locked() && vault.idle_for() >= idle {
+            vault.lock();
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    /// Rewrites note and snapshot files so they are all encrypted (or all
+    /// plaintext), skipping ones already in that form. Backups of the old
+    /// form are removed so no plaintext copy is left behind.
+    fn reencode_all(
+        &self,
+        index: &NotesIndex,
+        key: &VaultKey,
+        encrypt: bool,
+    ) -> Result<(), AppError> {
+        for note in &index.notes {
+            let mut paths = vec![self.note_path(&note.id)];
+            paths.extend(self.history(&note.id, None).snapshot_paths());
+
+            for path in paths {
+                let stored = match fs::read_to_string(&path) {
+                    Ok(stored) => stored,
+                    Err(_) => continue,
+                };
+                if stored.is_empty() || vault::is_encrypted(&stored) == encrypt {
+                    continue;
+                }
+
+                let content = vault::decode(Some(key), &stored)?;
+                let target = if encrypt { Some(key) } else { None };
+                persist::write_atomic(&path, vault::encode(target, &content)?).map_err(|e| {
+                    AppError::io(&format!("Failed to re-encode {}", path.display()), e)
+                })?;
+            }
+
+            let mut backup = self.note_path(&note.id).into_os_string();
+            backup.push(".bak");
+            let stale = fs::read_to_string(&backup)
+                .is_ok_and(|stored| !stored.is_empty() && vault::is_encrypted(&stored) != encrypt);
+            if stale {
+                fs::remove_file(&backup)
+                    .map_err(|e| AppError::io("Failed to remove note backup", e))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_content(&self, id: &str) -> Result<String, AppError> {
+        let path = self.note_path(id);
+        let key = self.key()?;
+
+        let parse = |content: &str| vault::decode(key.as_ref(), content);
+        match persist::read_recovering_allow_empty(&path, parse, self.access)? {
+            Some(content) => Ok(content),
+            None if path.exists() => Ok(String::new()),
+            None => Ok(Self::default_note()),
+        }
+    }
+
+    fn write_content(
+        &self,
+        index: &mut NotesIndex,
+        id: &str,
+        content: &str,
+    ) -> Result<(), AppError> {
+        let key = self.key()?;
+        let note = index
+            .find_mut(id)
+            .ok_or_else(|| AppError::not_found("Note", id))?;
+        note.updated_at = Utc::now();
+
+        let path = self.note_path(id);
+        let previous = fs::read_to_string(&path)
+            .ok()
+            .and_then(|stored| vault::decode(key.as_ref(), &stored).ok());
+
+        persist::write_with_backup(&path, vault::encode(key.as_ref(), content)?)
+            .map_err(|e| AppError::io("Failed to write note", e))?;
+
+        // History is best-effort; a failed snapshot must not lose the save itself
+        if let Err(err) = self.history(id, key).record(previous.as_deref(), content) {
+            warn!("Failed to record note history: {}", err);
+        }
+
+        self.persist_index(index)
+    }
+
+    fn note_path(&self, id: &str) -> PathBuf {
+        self.notes_dir.join(format!("{}.txt", id))
+    }
+
+    fn history(&self, id: &str, key: Option<VaultKey>) -> HistoryStore {
+        HistoryStore::new(self.notes_dir.join(format!("{}.history", id)), key)
+    }
+
+    /// The key for note content, or an error while notes are locked.
+    fn key(&self) -> Result<Option<VaultKey>, AppError> {
+        self.lock_vault()?.key()
+    }
+
+    fn lock_vault(&self) -> Result<MutexGuard<'_, Vault>, AppError> {
+        self.vault
+            .lock()
+            .map_err(|e| AppError::internal(format!("Failed to lock note vault: {}", e)))
+    }
+
+    fn ensure_exists(&self, id: &str) -> Result<(), AppError> {
+        self.with_index(|index| {
+            index
+                .find(id)
+                .map(|_| ())
+                .ok_or_else(|| AppError::not_found("Note", id))
+        })
+    }
+
+    fn lock_index(&self) -> Result<MutexGuard<'_, NotesIndex>, AppError> {
+        self.index
+            .lock()
+            .map_err(|e| AppError::internal(format!("Failed to lock notes index: {}", e)))
+    }
+
+    fn with_index<T>(
+        &self,
+        f: impl FnOnce(&NotesIndex) -> Result<T, AppError>,
+    ) -> Result<T, AppError> {
+        let index = self.lock_index()?;
+        f(&index)
+    }
+
+    fn persist_index(&self, index: &NotesIndex) -> Result<(), AppError> {
+        Self::write_index(&self.index_path, index)
+    }
+
+    fn write_index(path: &Path, index: &NotesIndex) -> Result<(), AppError> {
+        let json = serde_json::to_string_pretty(index)
+            .map_err(|e| format!("Failed to serialize notes index: {}", e))?;
+
+        persist::write_with_backup(path, json)
+            .map_err(|e| AppError::io("Failed to write notes index", e))
+    }
+
+    fn parse_index(content: &str) -> Result<NotesIndex, String> {
+        let index: NotesIndex = serde_json::from_str(content)
+            .map_err(|e| format!("Failed to parse notes index: {}", e))?;
+
+        if index.notes.is_empty() || index.active().is_none() {
+            return Err("Notes index has no valid active note".to_string());
+        }
+
+        Ok(index)
+    }
+
+    /// Builds a fresh index from the note files on disk, used when the index
+    /// is missing or unrecoverable. On first run there are no note files, so
+    /// the pre-collection `note.txt` is moved in as the first note instead.
+    /// A read-only rebuild stays in memory and leaves a legacy note in place.
+    fn rebuild_index(
+        data_dir: &Path,
+        notes_dir: &Path,
+        index_path: &Path,
+        access: Access,
+    ) -> Result<NotesIndex, AppError> {
+        let mut notes = if notes_dir.exists() {
+            Self::scan_note_files(notes_dir)?
+        } else {
+            Vec::new()
+        };
+
+        if notes.is_empty() {
+            let first = NoteMeta::new(Uuid::new_v4().to_string(), FIRST_NOTE_TITLE.to_string());
+            let legacy_path = data_dir.join(LEGACY_NOTE_FILENAME);
+
+            if access == Access::ReadWrite && legacy_path.exists() {
+                fs::rename(&legacy_path, notes_dir.join(format!("{}.txt", first.id)))
+                    .map_err(|e| AppError::io("Failed to migrate existing note", e))?;
+            }
+
+            notes.push(first);
+        }
+
+        let index = NotesIndex {
+            active_note_id: notes[0].id.clone(),
+            notes,
+        };
+        if access == Access::ReadWrite {
+            Self::write_index(index_path, &index)?;
+        }
+
+        Ok(index)
+    }
+
+    fn scan_note_files(notes_dir: &Path) -> Result<Vec<NoteMeta>, AppError> {
+        let entries = fs::read_dir(notes_dir)
+            .map_err(|e| AppError::io("Failed to read notes directory", e))?;
+
+        let mut ids: Vec<String> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "txt"))
+            .filter_map(|path| path.file_stem()?.to_str().map(str::to_string))
+            .collect();
+        ids.sort();
+
+        Ok(ids
+            .into_iter()
+            .enumerate()
+            .map(|(position, id)| NoteMeta::new(id, format!("Recovered Note {}", position + 1)))
+            .collect())
     }
 
     fn default_note() -> String {
         r#"<h1>Welcome to What The Note!</h1><p>A minimal, always-accessible sticky note for macOS.</p><h2>Quick Start</h2><ul><li><p><strong>Show/Hide:</strong> Use keyboard shortcut (⌥⌘N) or hover your mouse in the top-right corner</p></li><li><p><strong>Formatting:</strong> Click the text icon in the top-left to reveal styling options</p></li><li><p><strong>Settings:</strong> Click the gear icon to customize behavior and shortcuts</p></li></ul><h2>Features</h2><ul><li><p>Auto-save - your notes are saved instantly</p></li><li><p>Rich formatting - bold, italic, lists, headings, and more</p></li><li><p>Drag to reposition, resize from edges</p></li><li><p>Click away to hide (customizable in settings)</p></li><li><p>Adjustable text size in preferences</p></li></ul><p><em>Delete this text and start writing your notes!</em></p>"#.to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn cleared_note_stays_cleared() {
+        let dir = tempdir().expect("tempdir");
+        let storage = StorageService::open(dir.path()).expect("open storage");
+        let id = storage.active_note_id().expect("active note");
+
+        storage
+            .write_note_by_id(&id, "<p>Old text</p>")
+            .expect("write");
+        storage.write_note_by_id(&id, "").expect("clear");
+
+        assert_eq!(storage.read_note_by_id(&id).expect("read"), "");
+    }
+
+    #[test]
+    fn migrates_legacy_note_as_first_note() {
+        let dir = tempdir().expect("tempdir");
+        fs::write(dir.path().join(LEGACY_NOTE_FILENAME), "<p>Old note</p>").expect("write");
+
+        let storage = StorageService::open(dir.path()).expect("open storage");
+        let notes = storage.list_notes().expect("list");
+
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].title, FIRST_NOTE_TITLE);
+        assert_eq!(storage.read_note().expect("read"), "<p>Old note</p>");
+        assert!(!dir.path().join(LEGACY_NOTE_FILENAME).exists());
+    }
+
+    #[test]
+    fn read_only_open_writes_nothing() {
+        let dir = tempdir().expect("tempdir");
+        fs::write(dir.path().join(LEGACY_NOTE_FILENAME), "<p>Old note</p>").expect("write");
+
+        let storage = StorageService::open_read_only(dir.path()).expect("open storage");
+        assert_eq!(storage.list_notes().expect("list").len(), 1);
+
+        let mut files: Vec<_> = fs::read_dir(dir.path())
+            .expect("read dir")
+            .map(|entry| entry.expect("entry").file_name())
+            .collect();
+        files.sort();
+        assert_eq!(files, [LEGACY_NOTE_FILENAME]);
+    }
+
+    #[test]
+    fn active_note_persists_across_reopen() {
+        let dir = tempdir().expect("tempdir");
+        let storage = StorageService::open(dir.path()).expect("open storage");
+
+        let second = storage.create_note("Project B").expect("create");
+        storage.switch_note(&second.id).expect("switch");
+        storage
+            .write_note_by_id(&second.id, "<p>B</p>")
+            .expect("write");
+
+        let reopened = StorageService::open(dir.path()).expect("reopen storage");
+        assert_eq!(reopened.active_note_id().expect("active"), second.id);
+        assert_eq!(reopened.read_note().expect("read"), "<p>B</p>");
+    }
+
+    #[test]
+    fn deleting_active_note_switches_to_remaining() {
+        let dir = tempdir().expect("tempdir");
+        let storage = StorageService::open(dir.path()).expect("open storage");
+        let first_id = storage.active_note_id().expect("active");
+
+        let second = storage.create_note("Scratch").expect("create");
+        storage.switch_note(&second.id).expect("switch");
+        storage.delete_note(&second.id).expect("delete");
+
+        assert_eq!(storage.active_note_id().expect("active"), first_id);
+        assert!(storage.delete_note(&first_id).is_err());
+    }
+
+    #[test]
+    fn restores_revision_after_accidental_clear() {
+        let dir = tempdir().expect("tempdir");
+        let storage = StorageService::open(dir.path()).expect("open storage");
+        let id = storage.active_note_id().expect("active");
+
+        storage
+            .write_note_by_id(&id, "<p>Important meeting notes</p>")
+            .expect("write");
+        storage.write_note_by_id(&id, "").expect("clear");
+
+        let revisions = storage.list_revisions(&id).expect("revisions");
+        let lost = revisions
+            .iter()
+            .find(|rev| rev.sealed)
+            .expect("pinned revision");
+
+        let restored = storage.restore_revision(&id, &lost.id).expect("restore");
+        assert_eq!(restored, "<p>Important meeting notes</p>");
+        assert_eq!(storage.read_note().expect("read"), restored);
+    }
+
+    #[test]
+    fn rebuilds_lost_index_from_note_files() {
+        let dir = tempdir().expect("tempdir");
+        let storage = StorageService::open(dir.path()).expect("open storage");
+        let first_id = storage.active_note_id().expect("active");
+        storage
+            .write_note_by_id(&first_id, "<p>Keep me</p>")
+            .expect("write");
+        drop(storage);
+
+        fs::write(dir.path().join(INDEX_FILENAME), "{\"active_note_id\":").expect("corrupt");
+        fs::remove_file(dir.path().join("notes.json.bak")).ok();
+
+        let reopened = StorageService::open(dir.path()).expect("reopen storage");
+        assert_eq!(reopened.active_note_id().expect("active"), first_id);
+        assert_eq!(reopened.read_note().expect("read"), "<p>Keep me</p>");
+    }
+
+    #[test]
+    fn rejects_blank_titles() {
+        let dir = tempdir().expect("tempdir");
+        let storage = StorageService::open(dir.path()).expect("open storage");
+        let id = storage.active_note_id().expect("active");
+
+        assert!(storage.create_note("   ").is_err());
+        assert!(storage.rename_note(&id, "").is_err());
+        assert_eq!(
+            storage
+                .rename_note(&id, "  Renamed ")
+                .expect("rename")
+                .title,
+            "Renamed"
+        );
+    }
+
+    #[test]
+    fn encrypts_existing_notes_and_locks() {
+        let dir = tempdir().expect("tempdir");
+        let storage = StorageService::open(dir.path()).expect("open storage");
+        let id = storage.active_note_id().expect("active");
+        storage
+            .write_note_by_id(&id, "<p>Secret plans</p>")
+            .expect("write");
+        storage
+            .write_note_by_id(&id, "<p>Secret</p>")
+            .expect("write");
+
+        storage
+            .enable_encryption("correct horse")
+            .expect("enable encryption");
+
+        let leaked = fs::read_dir(dir.path().join(NOTES_DIRNAME))
+            .expect("read dir")
+            .flat_map(|entry| {
+                let path = entry.expect("entry").path();
+                if path.is_dir() {
+                    fs::read_dir(path)
+                        .expect("read history")
+                        .map(|entry| entry.expect("entry").path())
+                        .collect()
+                } else {
+                    vec![path]
+                }
+            })
+            .any(|path| {
+                fs::read_to_string(path)
+                    .unwrap_or_default()
+                    .contains("Secret")
+            });
+        assert!(!leaked);
+        drop(storage);
+
+        let reopened = StorageService::open(dir.path()).expect("reopen storage");
+        assert!(reopened.encryption_status().expect("status").locked);
+        assert!(reopened.read_note().is_err());
+        assert!(reopened.unlock("wrong horse").is_err());
+
+        reopened.unlock("correct horse").expect("unlock");
+        assert_eq!(reopened.read_note().expect("read"), "<p>Secret</p>");
+        assert!(reopened.lock_if_idle(Duration::ZERO).expect("idle lock"));
+
+        reopened
+            .disable_encryption("correct horse")
+            .expect("disable encryption");
+        let path = dir.path().join(NOTES_DIRNAME).join(format!("{}.txt", id));
+        assert_eq!(fs::read_to_string(path).expect("read"), "<p>Secret</p>");
+    }
+}
@@ -1,5 +1,6 @@
-use super::{SyncError, SyncResult};
-use crate::models::preferences::SyncPreferences;
+use super::{SyncCapabilities, SyncError, SyncResult, SyncTarget};
+use crate::models::note::NoteMeta;
+use crate::models::preferences::{Preferences, SyncPreferences};
 use chrono::Utc;
 use log::debug;
 #[cfg(not(target_os = "macos"))]
@@ -7,6 +8,41 @@ use log::warn;
 use regex::Regex;
 use std::process::{Command, Stdio};
 
+pub struct AppleNotesTarget;
+
+impl SyncTarget for AppleNotesTarget {
+    fn id(&self) -> &'static str {
+        "apple-notes"
+    }
+
+    fn label(&self) -> &'static str {
+        "Apple Notes"
+    }
+
+    fn capabilities(&self) -> SyncCapabilities {
+        SyncCapabilities {
+            two_way: false,
+            requires_permission: true,
+            supported: cfg!(target_os = "macos"),
+        }
+    }
+
+    fn is_enabled(&self, prefs: &SyncPreferences) -> bool {
+        prefs.apple_notes_enabled
+    }
+
+    fn export(&self, _note: &NoteMeta, content: &str, prefs: &Preferences) -> SyncResult<()> {
+        export(content, &prefs.sync)
+    }
+
+    /// Probes Automation permission first so a denial is reported as such
+    /// rather than as a failed script.
+    fn test(&self, _note: &NoteMeta, content: &str, prefs: &Preferences) -> SyncResult<()> {
+        check_permission()?;
+        export(content, &prefs.sync)
+    }
+}
+
 pub fn export(content: &str, prefs: &SyncPreferences) -> SyncResult<()> {
     #[cfg(target_os = "macos")]
     {
@@ -32,7 +68,7 @@ pub fn export(content: &str, prefs: &SyncPreferences) -> SyncResult<()> {
             without_heading.trim()
         };
 
-        let metadata_html = if prefs.include_metadata {
+        let metadata_html = if prefs.apple_notes_metadata {
             let timestamp = Utc::now().to_rfc3339();
             format!(
                 "<p style=\"font-size:11px;color:#6e6e73;margin:8px 0;\"><em>Synced from What The Note • {}</em></p>",
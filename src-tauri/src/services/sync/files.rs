@@ -0,0 +1,96 @@
+use super::{SyncError, SyncResult};
+use crate::models::note::NoteMeta;
+use crate::models::preferences::DEFAULT_EXPORT_FILENAME;
+use log::{debug, warn};
+use shellexpand::tilde;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Resolves a user-entered destination folder, expanding `~`. The messages
+/// name the target so the settings UI can point at the right field.
+pub(super) fn resolve_directory(
+    raw_path: Option<&str>,
+    missing: &'static str,
+    not_directory: &'static str,
+) -> SyncResult<PathBuf> {
+    let raw_path = raw_path
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .ok_or(SyncError::NotConfigured(missing))?;
+
+    let candidate = PathBuf::from(tilde(raw_path).into_owned());
+
+    if candidate.is_file() {
+        return Err(SyncError::NotConfigured(not_directory));
+    }
+
+    Ok(candidate)
+}
+
+/// Expands a filename template and strips characters that aren't allowed in
+/// file names, so a note title can never point outside the folder. `{title}`,
+/// `{id}` and `{date}` become the note's title, ID and creation date.
+pub(super) fn render_filename(template: &str, note: &NoteMeta, extension: &str) -> String {
+    let expanded = template
+        .replace("{title}", &note.title)
+        .replace("{id}", &note.id)
+        .replace("{date}", &note.created_at.format("%Y-%m-%d").to_string());
+
+    let cleaned: String = expanded
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '-',
+            c if c.is_control() => '-',
+            c => c,
+        })
+        .collect();
+
+    let stem = cleaned.trim();
+    let stem = stem
+        .strip_suffix(&format!(".{}", extension))
+        .unwrap_or(stem)
+        .trim_matches('.')
+        .trim();
+    let stem = if stem.is_empty() {
+        DEFAULT_EXPORT_FILENAME
+    } else {
+        stem
+    };
+
+    format!("{}.{}", stem, extension)
+}
+
+/// Moves an export from the path it had before a rename to its new one. When
+/// another file already has the new name, both are left alone: it may belong
+/// to a note with the same title, and the old one may hold edits not yet
+/// imported.
+pub(super) fn move_export(label: &str, old_path: &Path, new_path: &Path) -> SyncResult<()> {
+    if old_path == new_path || !old_path.exists() {
+        return Ok(());
+    }
+
+    if new_path.exists() {
+        warn!(
+            "{} sync kept {} because {} already exists",
+            label,
+            old_path.display(),
+            new_path.display()
+        );
+        return Ok(());
+    }
+
+    fs::rename(old_path, new_path)?;
+    debug!(
+        "{} sync moved {} to {}",
+        label,
+        old_path.display(),
+        new_path.display()
+    );
+
+    Ok(())
+}
+
+/// JSON string syntax is valid double-quoted YAML.
+pub(super) fn yaml_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "\"\"".to_string())
+}
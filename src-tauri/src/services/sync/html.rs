@@ -0,0 +1,239 @@
+use super::{files, SyncCapabilities, SyncResult, SyncTarget};
+use crate::models::note::NoteMeta;
+use crate::models::preferences::{MetadataStyle, Preferences, SyncPreferences, Theme};
+use crate::services::{html_text::escape_html, persist};
+use chrono::{DateTime, Utc};
+use log::debug;
+use std::fs;
+use std::path::PathBuf;
+
+const SOURCE_LABEL: &str = "What The Note";
+
+pub struct HtmlTarget;
+
+impl SyncTarget for HtmlTarget {
+    fn id(&self) -> &'static str {
+        "html"
+    }
+
+    fn label(&self) -> &'static str {
+        "HTML"
+    }
+
+    fn capabilities(&self) -> SyncCapabilities {
+        SyncCapabilities {
+            two_way: false,
+            requires_permission: false,
+            supported: true,
+        }
+    }
+
+    fn is_enabled(&self, prefs: &SyncPreferences) -> bool {
+        prefs.html_enabled
+    }
+
+    fn export(&self, note: &NoteMeta, content: &str, prefs: &Preferences) -> SyncResult<()> {
+        export(note, content, &prefs.sync, prefs.theme)
+    }
+
+    fn rename(&self, before: &NoteMeta, after: &NoteMeta, prefs: &Preferences) -> SyncResult<()> {
+        files::move_export(
+            "HTML",
+            &output_path(before, &prefs.sync)?,
+            &output_path(after, &prefs.sync)?,
+        )
+    }
+}
+
+/// Writes the note as a standalone HTML page styled like the given theme.
+pub fn export(
+    note: &NoteMeta,
+    content: &str,
+    prefs: &SyncPreferences,
+    theme: Theme,
+) -> SyncResult<()> {
+    fs::create_dir_all(resolve_base_path(prefs)?)?;
+
+    let output_path = output_path(note, prefs)?;
+    let rendered = render(note, content, prefs, theme);
+
+    persist::write_atomic(&output_path, &rendered)?;
+    debug!(
+        "HTML sync wrote {} bytes to {}",
+        rendered.len(),
+        output_path.display()
+    );
+
+    Ok(())
+}
+
+pub fn output_path(note: &NoteMeta, prefs: &SyncPreferences) -> SyncResult<PathBuf> {
+    Ok(resolve_base_path(prefs)?.join(files::render_filename(&prefs.html_filename, note, "html")))
+}
+
+/// The page `export` would write, without touching the destination folder.
+pub fn render(note: &NoteMeta, content: &str, prefs: &SyncPreferences, theme: Theme) -> String {
+    render_document(note, content, prefs, theme, Utc::now())
+}
+
+fn render_document(
+    note: &NoteMeta,
+    content: &str,
+    prefs: &SyncPreferences,
+    theme: Theme,
+    synced_at: DateTime<Utc>,
+) -> String {
+    let body = sanitize_html(content);
+    let date = synced_at.to_rfc3339();
+
+    let mut tags = vec![("generator", SOURCE_LABEL.to_string())];
+    match prefs.html_metadata {
+        MetadataStyle::Yaml => tags.extend([
+            ("title", note.title.clone()),
+            ("id", note.id.clone()),
+            ("created", note.created_at.to_rfc3339()),
+            ("updated", note.updated_at.to_rfc3339()),
+            ("sync-date", date.clone()),
+        ]),
+        MetadataStyle::HtmlComment => tags.push(("sync-date", date.clone())),
+        MetadataStyle::None => tags.clear(),
+    }
+    let meta: String = tags
+        .iter()
+        .map(|(name, content)| {
+            format!(
+                "\n<meta name=\"{}\" content=\"{}\">",
+                name,
+                escape_html(content)
+            )
+        })
+        .collect();
+    let banner = if tags.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "<p class=\"sync-banner\">Synced from {} • {}</p>\n",
+            SOURCE_LABEL, date
+        )
+    };
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>{}\n<style>\n{}</style>\n</head>\n<body>\n<main>\n{}{}\n</main>\n</body>\n</html>\n",
+        escape_html(&note.title),
+        meta,
+        theme_css(theme),
+        banner,
+        body.trim()
+    )
+}
+
+/// Strips scripts, event handlers and anything else a browser would run,
+/// keeping inline styles so text colors survive.
+pub fn sanitize_html(content: &str) -> String {
+    ammonia::Builder::default()
+        .add_generic_attributes(&["style"])
+        .clean(content)
+        .to_string()
+}
+
+/// Mirrors the theme variables in `styles.css`.
+fn theme_css(theme: Theme) -> String {
+    let (background, text, secondary, accent) = match theme {
+        Theme::LiquidGlass => (
+            "linear-gradient(135deg, #a7f3e8, #f0f9ff, #e9d5ff)",
+            "#1a1a1a",
+            "#525252",
+            "#14b8a6",
+        ),
+        Theme::GradientCosmic => (
+            "linear-gradient(135deg, #d946ef, #8b5cf6, #06b6d4)",
+            "#ffffff",
+            "rgba(255, 255, 255, 0.9)",
+            "#06b6d4",
+        ),
+        Theme::Minimal => ("#fafafa", "#18181b", "#71717a", "#3b82f6"),
+        Theme::MinimalDark => ("#18181b", "#fafafa", "#a1a1aa", "#3b82f6"),
+        Theme::StickyNote => ("#fffdd0", "#3d3520", "#6b5d3f", "#d97706"),
+    };
+
+    format!(
+        "body {{ margin: 0; min-height: 100vh; background: {background}; color: {text}; \
+         font-family: -apple-system, BlinkMacSystemFont, \"Segoe UI\", sans-serif; line-height: 1.6; }}\n\
+         main {{ max-width: 720px; margin: 0 auto; padding: 48px 24px; }}\n\
+         a {{ color: {accent}; }}\n\
+         blockquote {{ margin: 0; padding-left: 16px; border-left: 3px solid {accent}; color: {secondary}; }}\n\
+         .sync-banner {{ font-size: 11px; color: {secondary}; font-style: italic; }}\n"
+    )
+}
+
+fn resolve_base_path(prefs: &SyncPreferences) -> SyncResult<PathBuf> {
+    files::resolve_directory(
+        prefs.html_path.as_deref(),
+        "HTML destination folder is not set",
+        "HTML destination must be a directory",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn prefs(dir: &std::path::Path) -> SyncPreferences {
+        SyncPreferences {
+            html_enabled: true,
+            html_path: Some(dir.to_string_lossy().to_string()),
+            ..SyncPreferences::default()
+        }
+    }
+
+    #[test]
+    fn writes_sanitized_themed_document() {
+        let dir = tempdir().expect("tempdir");
+        let note = NoteMeta::new("note-1".to_string(), "Groceries".to_string());
+
+        export(
+            &note,
+            "<h1 onclick=\"alert(1)\">Hello</h1><script>alert(1)</script><p style=\"color: red\">World</p>",
+            &prefs(dir.path()),
+            Theme::StickyNote,
+        )
+        .expect("export success");
+
+        let content = fs::read_to_string(dir.path().join("What The Note.html")).expect("read file");
+        assert!(content.starts_with("<!DOCTYPE html>"));
+        assert!(content.contains("#fffdd0"));
+        assert!(content.contains("<h1>Hello</h1>"));
+        assert!(content.contains("style=\"color: red\""));
+        assert!(!content.contains("alert"));
+        assert!(content.contains("name=\"sync-date\""));
+    }
+
+    #[test]
+    fn escapes_note_title_in_metadata() {
+        let prefs = SyncPreferences {
+            html_metadata: MetadataStyle::Yaml,
+            ..SyncPreferences::default()
+        };
+        let note = NoteMeta::new("note-1".to_string(), "Groceries & more".to_string());
+
+        let content = render(&note, "<p>Milk</p>", &prefs, Theme::Minimal);
+        assert!(content.contains("<title>Groceries &amp; more</title>"));
+        assert!(content.contains("<meta name=\"id\" content=\"note-1\">"));
+        assert!(content.contains("Milk"));
+    }
+
+    #[test]
+    fn leaves_metadata_out_when_switched_off() {
+        let dir = tempdir().expect("tempdir");
+        let prefs = SyncPreferences {
+            html_metadata: MetadataStyle::None,
+            ..prefs(dir.path())
+        };
+        let note = NoteMeta::new("note-1".to_string(), "Groceries".to_string());
+
+        let content = render(&note, "<p>Milk</p>", &prefs, Theme::Minimal);
+        assert!(!content.contains("<meta name="));
+        assert!(!content.contains("sync-banner\">"));
+    }
+}
@@ -1,44 +1,171 @@
-use super::{SyncError, SyncResult};
-use crate::models::preferences::SyncPreferences;
-use chrono::Utc;
+use super::{files, sanitize_html, SyncCapabilities, SyncResult, SyncTarget};
+use crate::models::note::NoteMeta;
+use crate::models::preferences::{
+    MarkdownMode, MetadataStyle, Preferences, SyncPreferences, DEFAULT_EXPORT_FILENAME,
+};
+use crate::services::persist;
+use chrono::{DateTime, Local, Utc};
 use html2md::parse_html;
 use log::{debug, warn};
-use shellexpand::tilde;
+use pulldown_cmark::{html::push_html, Options, Parser};
+use regex::Regex;
 use std::fs;
+use std::io;
 use std::path::PathBuf;
+use std::sync::OnceLock;
 
-const OUTPUT_FILENAME: &str = "What The Note.md";
 const SOURCE_LABEL: &str = "What The Note";
+const JOURNAL_MARKER: &str = "what-the-note";
 const SYNC_WARNING: &str =
     "<!-- ⚠️ AUTO-SYNCED FROM WHAT THE NOTE — Do not edit this file directly -->";
 
-pub fn export(content: &str, prefs: &SyncPreferences) -> SyncResult<()> {
+/// A Markdown file whose body no longer matches the hash the app stamped on
+/// it, i.e. it was edited outside the app since the last export.
+#[derive(Debug)]
+pub struct ExternalEdit {
+    pub path: PathBuf,
+    /// The body as edited, without the metadata header.
+    pub body: String,
+    /// `sync-date` of the export the edit was made on top of, if present.
+    pub synced_at: Option<DateTime<Utc>>,
+    /// Hash of the body the edit was made on top of.
+    base_hash: String,
+}
+
+impl ExternalEdit {
+    /// Whether the edit was made on top of what the note holds now, i.e. the
+    /// note hasn't changed since the file was last written.
+    pub fn is_based_on(&self, content: &str) -> bool {
+        content_hash(&export_body(&convert_html_to_markdown(content))) == self.base_hash
+    }
+
+    /// The edit as note content. Anyone who can write to the folder can
+    /// edit the file, so markup that would run in the editor is removed.
+    pub fn to_html(&self) -> String {
+        sanitize_html(&convert_markdown_to_html(&self.body))
+    }
+}
+
+pub struct MarkdownTarget;
+
+impl SyncTarget for MarkdownTarget {
+    fn id(&self) -> &'static str {
+        "markdown"
+    }
+
+    fn label(&self) -> &'static str {
+        "Markdown"
+    }
+
+    fn capabilities(&self) -> SyncCapabilities {
+        SyncCapabilities {
+            two_way: true,
+            requires_permission: false,
+            supported: true,
+        }
+    }
+
+    fn is_enabled(&self, prefs: &SyncPreferences) -> bool {
+        prefs.markdown_enabled
+    }
+
+    fn export(&self, note: &NoteMeta, content: &str, prefs: &Preferences) -> SyncResult<()> {
+        export(note, content, &prefs.sync)
+    }
+
+    fn rename(&self, before: &NoteMeta, after: &NoteMeta, prefs: &Preferences) -> SyncResult<()> {
+        rename_export(before, after, &prefs.sync)
+    }
+}
+
+pub fn export(note: &NoteMeta, content: &str, prefs: &SyncPreferences) -> SyncResult<()> {
+    if prefs.markdown_mode == MarkdownMode::Journal {
+        return export_journal(note, content, prefs, Local::now());
+    }
+
     let base_path = resolve_base_path(prefs)?;
 
     fs::create_dir_all(&base_path)?;
 
-    let markdown_body = convert_html_to_markdown(content);
-    let timestamp = Utc::now().to_rfc3339();
+    let output_path = output_path(note, prefs)?;
 
-    let mut sections: Vec<String> = Vec::new();
+    // Never overwrite an edit that the watcher hasn't picked up yet
+    if prefs.is_markdown_two_way() {
+        if let Some(edit) = read_external_edit(note, prefs)? {
+            let copy = write_conflict_copy(&edit)?;
+            warn!(
+                "Markdown file changed outside the app; kept it as {}",
+                copy.display()
+            );
+        }
+    }
 
-    if prefs.include_metadata {
-        let metadata_banner = format!(
-            "<!-- source: {} | sync-date: {} -->",
-            SOURCE_LABEL, timestamp
-        );
-        sections.push(metadata_banner);
-        sections.push(SYNC_WARNING.to_string());
+    let rendered = render(note, content, prefs);
+
+    persist::write_atomic(&output_path, &rendered)?;
+    debug!(
+        "Markdown sync wrote {} bytes to {}",
+        rendered.len(),
+        output_path.display()
+    );
+
+    Ok(())
+}
+
+/// Moves the exported file when the filename template puts the note's title
+/// in it, so a renamed note doesn't leave its old file behind. Journal files
+/// are named by date and stay where they are.
+pub fn rename_export(
+    before: &NoteMeta,
+    after: &NoteMeta,
+    prefs: &SyncPreferences,
+) -> SyncResult<()> {
+    if prefs.markdown_mode == MarkdownMode::Journal {
+        return Ok(());
     }
 
-    sections.push(markdown_body.trim_end().to_string());
+    files::move_export(
+        "Markdown",
+        &output_path(before, prefs)?,
+        &output_path(after, prefs)?,
+    )
+}
 
-    let rendered = sections.join("\n\n");
+/// The Markdown document `export` would write for the note, metadata included.
+pub fn render(note: &NoteMeta, content: &str, prefs: &SyncPreferences) -> String {
+    render_document(&convert_html_to_markdown(content), note, prefs, Utc::now())
+}
 
-    let output_path = base_path.join(OUTPUT_FILENAME);
-    fs::write(&output_path, &rendered)?;
+/// Writes the note to the file for `now`'s date. In append mode the rest of
+/// that file is kept and only this note's entry is added or refreshed.
+fn export_journal(
+    note: &NoteMeta,
+    content: &str,
+    prefs: &SyncPreferences,
+    now: DateTime<Local>,
+) -> SyncResult<()> {
+    let base_path = resolve_base_path(prefs)?;
+
+    fs::create_dir_all(&base_path)?;
+
+    let output_path = base_path.join(format!("{}.md", now.format("%Y-%m-%d")));
+    let markdown_body = convert_html_to_markdown(content);
+    let synced_at = now.with_timezone(&Utc);
+
+    let rendered = if prefs.markdown_journal_append {
+        let existing = match fs::read_to_string(&output_path) {
+            Ok(existing) => existing,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err.into()),
+        };
+        upsert_journal_entry(&existing, note, &markdown_body, synced_at)
+    } else {
+        render_document(&markdown_body, note, prefs, synced_at)
+    };
+
+    persist::write_atomic(&output_path, &rendered)?;
     debug!(
-        "Markdown sync wrote {} bytes to {}",
+        "Markdown journal wrote {} bytes to {}",
         rendered.len(),
         output_path.display()
     );
@@ -46,32 +173,289 @@ pub fn export(content: &str, prefs: &SyncPreferences) -> SyncResult<()> {
     Ok(())
 }
 
-fn resolve_base_path(prefs: &SyncPreferences) -> SyncResult<PathBuf> {
-    let raw_path = prefs
-        .markdown_path
-        .as_ref()
-        .and_then(|p| {
-            let trimmed = p.trim();
-            if trimmed.is_empty() {
-                None
-            } else {
-                Some(trimmed)
+/// Replaces the note's marked entry in a journal file, or appends one if the
+/// note hasn't been written there yet. Text outside the markers is untouched.
+fn upsert_journal_entry(
+    existing: &str,
+    note: &NoteMeta,
+    markdown_body: &str,
+    synced_at: DateTime<Utc>,
+) -> String {
+    let begin = format!("<!-- {}:begin {} ", JOURNAL_MARKER, note.id);
+    let end = format!("<!-- {}:end {} -->", JOURNAL_MARKER, note.id);
+    let entry = format!(
+        "{}sync-date: {} -->\n## {}\n\n{}\n{}",
+        begin,
+        synced_at.to_rfc3339(),
+        note.title,
+        markdown_body.trim(),
+        end
+    );
+
+    let existing = existing.replace("\r\n", "\n");
+    if let Some(start) = existing.find(&begin) {
+        if let Some(offset) = existing[start..].find(&end) {
+            let stop = start + offset + end.len();
+            return format!("{}{}{}", &existing[..start], entry, &existing[stop..]);
+        }
+    }
+
+    let head = existing.trim_end();
+    if head.is_empty() {
+        format!("{}\n", entry)
+    } else {
+        format!("{}\n\n{}\n", head, entry)
+    }
+}
+
+/// Returns the pending external edit to the exported file, if any. Files
+/// without a content hash in their header can't be checked and are ignored.
+pub fn read_external_edit(
+    note: &NoteMeta,
+    prefs: &SyncPreferences,
+) -> SyncResult<Option<ExternalEdit>> {
+    let path = output_path(note, prefs)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let document = fs::read_to_string(&path)?;
+    let (header, body) = split_document(&document);
+
+    let Some(header) = header else {
+        return Ok(None);
+    };
+    let Some(hash) = header.hash else {
+        return Ok(None);
+    };
+
+    if content_hash(&body) == hash {
+        return Ok(None);
+    }
+
+    Ok(Some(ExternalEdit {
+        path,
+        body,
+        synced_at: header.synced_at,
+        base_hash: hash,
+    }))
+}
+
+/// Re-stamps the header of an imported file so its hash and sync date match
+/// the edited body, leaving the body exactly as the user wrote it.
+pub fn accept_external_edit(
+    edit: &ExternalEdit,
+    note: &NoteMeta,
+    prefs: &SyncPreferences,
+) -> SyncResult<()> {
+    let rendered = render_document(&edit.body, note, prefs, Utc::now());
+    persist::write_atomic(&edit.path, rendered)?;
+    Ok(())
+}
+
+/// Saves a conflicting external edit beside the exported file. The name is
+/// derived from the edited content, so re-detecting the same edit reuses it.
+pub fn write_conflict_copy(edit: &ExternalEdit) -> SyncResult<PathBuf> {
+    let stem = edit
+        .path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| DEFAULT_EXPORT_FILENAME.to_string());
+    let copy_path = edit.path.with_file_name(format!(
+        "{} (conflict {}).md",
+        stem,
+        &content_hash(&edit.body)[..8]
+    ));
+
+    persist::write_atomic(&copy_path, &edit.body)?;
+    Ok(copy_path)
+}
+
+pub fn output_path(note: &NoteMeta, prefs: &SyncPreferences) -> SyncResult<PathBuf> {
+    Ok(
+        resolve_base_path(prefs)?.join(files::render_filename(
+            &prefs.markdown_filename,
+            note,
+            "md",
+        )),
+    )
+}
+
+fn render_document(
+    markdown_body: &str,
+    note: &NoteMeta,
+    prefs: &SyncPreferences,
+    synced_at: DateTime<Utc>,
+) -> String {
+    let body = export_body(markdown_body);
+    let hash = content_hash(body);
+    let mut sections: Vec<String> = Vec::new();
+
+    match metadata_style(prefs) {
+        MetadataStyle::Yaml => {
+            let mut lines = vec![
+                "---".to_string(),
+                format!("title: {}", files::yaml_string(&note.title)),
+                format!("id: {}", note.id),
+                format!("created: {}", note.created_at.to_rfc3339()),
+                format!("updated: {}", note.updated_at.to_rfc3339()),
+                format!("synced: {}", synced_at.to_rfc3339()),
+                format!("source: {}", files::yaml_string(SOURCE_LABEL)),
+            ];
+
+            let tags = extract_tags(body);
+            if !tags.is_empty() {
+                let tags: Vec<String> = tags.iter().map(|tag| files::yaml_string(tag)).collect();
+                lines.push(format!("tags: [{}]", tags.join(", ")));
             }
-        })
-        .ok_or(SyncError::NotConfigured(
-            "Markdown destination folder is not set",
-        ))?;
 
-    let expanded = tilde(raw_path).into_owned();
-    let candidate = PathBuf::from(expanded);
+            lines.push(format!("hash: {}", hash));
+            lines.push("---".to_string());
+            sections.push(lines.join("\n"));
+        }
+        MetadataStyle::HtmlComment => {
+            let metadata_banner = format!(
+                "<!-- source: {} | sync-date: {} | hash: {} -->",
+                SOURCE_LABEL,
+                synced_at.to_rfc3339(),
+                hash
+            );
+            sections.push(metadata_banner);
+
+            if !prefs.is_markdown_two_way() {
+                sections.push(SYNC_WARNING.to_string());
+            }
+        }
+        MetadataStyle::None => {}
+    }
+
+    sections.push(body.to_string());
+
+    sections.join("\n\n")
+}
+
+/// The body as written below the header.
+fn export_body(markdown_body: &str) -> &str {
+    markdown_body.trim_start_matches('\n').trim_end()
+}
+
+/// Two-way sync relies on the header to detect external edits, so it falls
+/// back to the comment banner when metadata is switched off.
+fn metadata_style(prefs: &SyncPreferences) -> MetadataStyle {
+    match prefs.markdown_metadata {
+        MetadataStyle::None if prefs.is_markdown_two_way() => MetadataStyle::HtmlComment,
+        style => style,
+    }
+}
+
+/// Collects inline `#tags` in order of first appearance.
+fn extract_tags(body: &str) -> Vec<String> {
+    static TAG_REGEX: OnceLock<Regex> = OnceLock::new();
+    let tag_regex =
+        TAG_REGEX.get_or_init(|| Regex::new(r"(?:^|\s)#([A-Za-z][\w/-]*)").expect("valid regex"));
+
+    let mut tags: Vec<String> = Vec::new();
+    for caps in tag_regex.captures_iter(body) {
+        let tag = caps[1].to_string();
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+struct Header {
+    synced_at: Option<DateTime<Utc>>,
+    hash: Option<String>,
+}
+
+/// Splits an exported file into its metadata header and the Markdown body.
+fn split_document(document: &str) -> (Option<Header>, String) {
+    let normalized = document.replace("\r\n", "\n");
+
+    if let Some((header, rest)) = split_front_matter(&normalized) {
+        return (
+            Some(header),
+            rest.trim_start_matches('\n').trim_end().to_string(),
+        );
+    }
+
+    static BANNER_REGEX: OnceLock<Regex> = OnceLock::new();
+    let banner_regex = BANNER_REGEX.get_or_init(|| {
+        Regex::new(r"^<!-- source: [^|]*\| sync-date: (\S+)(?: \| hash: ([0-9a-f]+))? -->\n*")
+            .expect("valid regex")
+    });
+
+    let Some(caps) = banner_regex.captures(&normalized) else {
+        return (None, normalized.trim_end().to_string());
+    };
+
+    let header = Header {
+        synced_at: parse_date(&caps[1]),
+        hash: caps.get(2).map(|m| m.as_str().to_string()),
+    };
+
+    let rest = &normalized[caps.get(0).map(|m| m.end()).unwrap_or(0)..];
+    let body = rest
+        .strip_prefix(SYNC_WARNING)
+        .unwrap_or(rest)
+        .trim_start_matches('\n')
+        .trim_end()
+        .to_string();
+
+    (Some(header), body)
+}
+
+/// Reads the `synced` and `hash` keys from YAML front matter. Other keys are
+/// left to whatever tool the user edits the file with.
+fn split_front_matter(document: &str) -> Option<(Header, &str)> {
+    let inner = document.strip_prefix("---\n")?;
+    let end = inner.find("\n---")?;
+    let after = &inner[end + 4..];
+    let rest = match after.strip_prefix('\n') {
+        Some(rest) => rest,
+        None if after.is_empty() => after,
+        None => return None,
+    };
 
-    if candidate.is_file() {
-        return Err(SyncError::NotConfigured(
-            "Markdown destination must be a directory",
-        ));
+    let mut header = Header {
+        synced_at: None,
+        hash: None,
+    };
+    for line in inner[..end].lines() {
+        if let Some(value) = line.strip_prefix("synced:") {
+            header.synced_at = parse_date(value.trim());
+        } else if let Some(value) = line.strip_prefix("hash:") {
+            header.hash = Some(value.trim().to_string());
+        }
     }
 
-    Ok(candidate)
+    Some((header, rest))
+}
+
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|date| date.with_timezone(&Utc))
+}
+
+/// FNV-1a over the trimmed body. Stable across builds, unlike `DefaultHasher`,
+/// since the value is persisted in the exported file.
+fn content_hash(body: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in body.trim_end().bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+fn resolve_base_path(prefs: &SyncPreferences) -> SyncResult<PathBuf> {
+    files::resolve_directory(
+        prefs.markdown_path.as_deref(),
+        "Markdown destination folder is not set",
+        "Markdown destination must be a directory",
+    )
 }
 
 fn convert_html_to_markdown(content: &str) -> String {
@@ -83,9 +467,20 @@ fn convert_html_to_markdown(content: &str) -> String {
     parse_html(content)
 }
 
+pub fn convert_markdown_to_html(markdown: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TABLES);
+
+    let mut html = String::new();
+    push_html(&mut html, Parser::new_ext(markdown, options));
+    html
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::preferences::MarkdownMode;
     use crate::services::sample::SAMPLE_NOTE_HTML;
     use tempfile::tempdir;
 
@@ -95,15 +490,15 @@ mod tests {
         let prefs = SyncPreferences {
             markdown_enabled: true,
             markdown_path: Some(dir.path().to_string_lossy().to_string()),
-            include_metadata: true,
             apple_notes_enabled: false,
             apple_notes_title: "".into(),
             apple_notes_folder: "".into(),
+            ..SyncPreferences::default()
         };
 
-        export("<h1>Hello</h1><p>World</p>", &prefs).expect("export success");
+        export(&note(), "<h1>Hello</h1><p>World</p>", &prefs).expect("export success");
 
-        let output_path = dir.path().join(OUTPUT_FILENAME);
+        let output_path = dir.path().join("What The Note.md");
         assert!(output_path.exists(), "output markdown missing");
 
         let content = fs::read_to_string(output_path).expect("read file");
@@ -119,15 +514,16 @@ mod tests {
         let prefs = SyncPreferences {
             markdown_enabled: true,
             markdown_path: Some(dir.path().to_string_lossy().to_string()),
-            include_metadata: false,
+            markdown_metadata: MetadataStyle::None,
             apple_notes_enabled: false,
             apple_notes_title: "".into(),
             apple_notes_folder: "".into(),
+            ..SyncPreferences::default()
         };
 
-        export("<p>Just text</p>", &prefs).expect("export success");
+        export(&note(), "<p>Just text</p>", &prefs).expect("export success");
 
-        let output_path = dir.path().join(OUTPUT_FILENAME);
+        let output_path = dir.path().join("What The Note.md");
         let content = fs::read_to_string(output_path).expect("read file");
 
         assert!(!content.contains("source: What The Note"));
@@ -135,6 +531,250 @@ mod tests {
         assert!(content.contains("Just text"));
     }
 
+    fn note() -> NoteMeta {
+        NoteMeta::new("note-1".to_string(), "Groceries: week 1".to_string())
+    }
+
+    fn two_way_prefs(dir: &std::path::Path) -> SyncPreferences {
+        SyncPreferences {
+            markdown_enabled: true,
+            markdown_path: Some(dir.to_string_lossy().to_string()),
+            markdown_mode: MarkdownMode::TwoWay,
+            ..SyncPreferences::default()
+        }
+    }
+
+    #[test]
+    fn unchanged_export_is_not_an_external_edit() {
+        let dir = tempdir().expect("tempdir");
+        let prefs = two_way_prefs(dir.path());
+
+        export(&note(), "<h1>Hello</h1><p>World</p>", &prefs).expect("export success");
+
+        let content = fs::read_to_string(dir.path().join("What The Note.md")).expect("read file");
+        assert!(content.contains("| hash: "));
+        assert!(!content.contains(SYNC_WARNING));
+        assert!(read_external_edit(&note(), &prefs)
+            .expect("inspect")
+            .is_none());
+    }
+
+    #[test]
+    fn detects_and_accepts_external_edit() {
+        let dir = tempdir().expect("tempdir");
+        let prefs = two_way_prefs(dir.path());
+        export(&note(), "<p>Original</p>", &prefs).expect("export success");
+
+        let path = dir.path().join("What The Note.md");
+        let edited = fs::read_to_string(&path)
+            .expect("read file")
+            .replace("Original", "Edited in **vim**");
+        fs::write(&path, edited).expect("edit file");
+
+        let edit = read_external_edit(&note(), &prefs)
+            .expect("inspect")
+            .expect("edit detected");
+        assert!(edit.synced_at.is_some());
+        assert!(edit.is_based_on("<p>Original</p>"));
+        assert!(!edit.is_based_on("<p>Changed in the app</p>"));
+        assert!(edit.to_html().contains("<strong>vim</strong>"));
+
+        accept_external_edit(&edit, &note(), &prefs).expect("accept");
+        assert!(read_external_edit(&note(), &prefs)
+            .expect("inspect")
+            .is_none());
+        assert!(fs::read_to_string(&path)
+            .expect("read file")
+            .ends_with("Edited in **vim**"));
+    }
+
+    #[test]
+    fn external_edit_imports_without_scripts() {
+        let dir = tempdir().expect("tempdir");
+        let prefs = two_way_prefs(dir.path());
+        export(&note(), "<p>Original</p>", &prefs).expect("export success");
+
+        let path = dir.path().join("What The Note.md");
+        let edited = fs::read_to_string(&path).expect("read file").replace(
+            "Original",
+            "Edited <script>steal()</script><img src=\"x.png\" onerror=\"steal()\">",
+        );
+        fs::write(&path, edited).expect("edit file");
+
+        let html = read_external_edit(&note(), &prefs)
+            .expect("inspect")
+            .expect("edit detected")
+            .to_html();
+        assert!(html.contains("Edited"));
+        assert!(html.contains("<img src=\"x.png\">"));
+        assert!(!html.contains("script"));
+        assert!(!html.contains("onerror"));
+    }
+
+    #[test]
+    fn export_keeps_unimported_edit_as_conflict_copy() {
+        let dir = tempdir().expect("tempdir");
+        let prefs = two_way_prefs(dir.path());
+        export(&note(), "<p>Original</p>", &prefs).expect("export success");
+
+        let path = dir.path().join("What The Note.md");
+        let edited = fs::read_to_string(&path)
+            .expect("read file")
+            .replace("Original", "Outside edit");
+        fs::write(&path, edited).expect("edit file");
+
+        export(&note(), "<p>App edit</p>", &prefs).expect("export success");
+
+        let copies: Vec<String> = fs::read_dir(dir.path())
+            .expect("list dir")
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|name| name.contains("(conflict "))
+            .collect();
+        assert_eq!(copies.len(), 1);
+        let copy = fs::read_to_string(dir.path().join(&copies[0])).expect("read copy");
+        assert_eq!(copy, "Outside edit");
+    }
+
+    #[test]
+    fn expands_filename_template() {
+        let note = note();
+        let date = note.created_at.format("%Y-%m-%d").to_string();
+
+        assert_eq!(
+            files::render_filename("{date} {title}", &note, "md"),
+            format!("{} Groceries- week 1.md", date)
+        );
+        assert_eq!(
+            files::render_filename("../{id}.md", &note, "md"),
+            "-note-1.md"
+        );
+        assert_eq!(
+            files::render_filename("  ", &note, "md"),
+            "What The Note.md"
+        );
+    }
+
+    #[test]
+    fn renamed_note_moves_its_export() {
+        let dir = tempdir().expect("tempdir");
+        let prefs = SyncPreferences {
+            markdown_enabled: true,
+            markdown_path: Some(dir.path().to_string_lossy().to_string()),
+            markdown_filename: "{title}".to_string(),
+            ..SyncPreferences::default()
+        };
+        let before = note();
+        let mut after = note();
+        after.title = "Errands".to_string();
+
+        export(&before, "<p>Milk</p>", &prefs).expect("export");
+        rename_export(&before, &after, &prefs).expect("rename");
+
+        assert!(!dir.path().join("Groceries- week 1.md").exists());
+        let moved = fs::read_to_string(dir.path().join("Errands.md")).expect("moved file");
+        assert!(moved.contains("Milk"));
+    }
+
+    #[test]
+    fn exports_under_templated_filename() {
+        let dir = tempdir().expect("tempdir");
+        let prefs = SyncPreferences {
+            markdown_enabled: true,
+            markdown_path: Some(dir.path().to_string_lossy().to_string()),
+            markdown_filename: "{date} {title}".to_string(),
+            ..SyncPreferences::default()
+        };
+        let note = note();
+
+        export(&note, "<p>Milk</p>", &prefs).expect("export");
+
+        let name = format!(
+            "{} Groceries- week 1.md",
+            note.created_at.format("%Y-%m-%d")
+        );
+        let content = fs::read_to_string(dir.path().join(name)).expect("read file");
+        assert!(content.contains("Milk"));
+        assert!(!dir.path().join("What The Note.md").exists());
+    }
+
+    #[test]
+    fn rename_onto_another_export_keeps_both_files() {
+        let dir = tempdir().expect("tempdir");
+        let prefs = SyncPreferences {
+            markdown_enabled: true,
+            markdown_path: Some(dir.path().to_string_lossy().to_string()),
+            markdown_filename: "{title}".to_string(),
+            ..SyncPreferences::default()
+        };
+        let before = note();
+        let mut after = note();
+        after.title = "Errands".to_string();
+        let other = NoteMeta::new("note-2".to_string(), "Errands".to_string());
+
+        export(&before, "<p>Milk</p>", &prefs).expect("export");
+        export(&other, "<p>Post office</p>", &prefs).expect("export other");
+        rename_export(&before, &after, &prefs).expect("rename");
+
+        let kept = fs::read_to_string(dir.path().join("Groceries- week 1.md")).expect("old file");
+        assert!(kept.contains("Milk"));
+        let other = fs::read_to_string(dir.path().join("Errands.md")).expect("other file");
+        assert!(other.contains("Post office"));
+    }
+
+    #[test]
+    fn yaml_front_matter_round_trips() {
+        let dir = tempdir().expect("tempdir");
+        let prefs = SyncPreferences {
+            markdown_metadata: MetadataStyle::Yaml,
+            markdown_filename: "{title}".to_string(),
+            ..two_way_prefs(dir.path())
+        };
+
+        export(&note(), "<p>Buy milk #errands #home</p>", &prefs).expect("export success");
+
+        let path = dir.path().join("Groceries- week 1.md");
+        let content = fs::read_to_string(&path).expect("read file");
+        assert!(content.starts_with("---\ntitle: \"Groceries: week 1\"\nid: note-1\n"));
+        assert!(content.contains("tags: [\"errands\", \"home\"]"));
+        assert!(read_external_edit(&note(), &prefs)
+            .expect("inspect")
+            .is_none());
+
+        fs::write(&path, content.replace("milk", "oat milk")).expect("edit file");
+        let edit = read_external_edit(&note(), &prefs)
+            .expect("inspect")
+            .expect("edit detected");
+        assert!(edit.synced_at.is_some());
+        assert!(edit.body.starts_with("Buy oat milk"));
+    }
+
+    #[test]
+    fn journal_appends_entry_per_note() {
+        let dir = tempdir().expect("tempdir");
+        let prefs = SyncPreferences {
+            markdown_enabled: true,
+            markdown_path: Some(dir.path().to_string_lossy().to_string()),
+            markdown_mode: MarkdownMode::Journal,
+            markdown_journal_append: true,
+            ..SyncPreferences::default()
+        };
+        let now = Local::now();
+        let path = dir.path().join(format!("{}.md", now.format("%Y-%m-%d")));
+        fs::write(&path, "Morning pages\n").expect("seed file");
+
+        let other = NoteMeta::new("note-2".to_string(), "Other".to_string());
+        export_journal(&note(), "<p>First draft</p>", &prefs, now).expect("export");
+        export_journal(&other, "<p>Second note</p>", &prefs, now).expect("export");
+        export_journal(&note(), "<p>Final draft</p>", &prefs, now).expect("export");
+
+        let content = fs::read_to_string(&path).expect("read file");
+        assert!(content.starts_with("Morning pages\n\n"));
+        assert!(content.contains("Final draft"));
+        assert!(!content.contains("First draft"));
+        assert!(content.find("Final draft") < content.find("Second note"));
+    }
+
     #[test]
     fn sample_document_converts_to_markdown() {
         let markdown = convert_html_to_markdown(SAMPLE_NOTE_HTML);
@@ -1,46 +1,96 @@
 mod apple_notes;
+mod files;
+mod html;
 mod markdown;
+mod queue;
+mod registry;
+mod text;
+mod watcher;
 
-use crate::models::preferences::SyncPreferences;
-use serde::Serialize;
+use crate::models::note::NoteMeta;
+use crate::models::preferences::Preferences;
+use log::warn;
+use std::sync::OnceLock;
 
 pub use apple_notes::{
     check_permission as check_apple_notes_permission, list_folders as list_apple_notes_folders,
 };
+pub use html::sanitize_html;
+pub use markdown::convert_markdown_to_html;
+pub use queue::{SyncQueue, SyncStatus};
+pub use registry::{SyncCapabilities, SyncRegistry, SyncTarget};
+pub use watcher::MarkdownWatcher;
+
+/// A file format a note can be rendered to, matching the file targets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Markdown,
+    Html,
+    Text,
+}
 
 pub struct SyncService;
 
 impl SyncService {
-    pub fn sync_all(content: &str, prefs: &SyncPreferences) -> SyncResult<()> {
-        if !prefs.is_any_enabled() {
+    pub fn sync_all(note: &NoteMeta, content: &str, prefs: &Preferences) -> SyncResult<()> {
+        if !prefs.sync.is_any_enabled() {
             return Ok(());
         }
 
-        for outcome in Self::sync_outcomes(content, prefs) {
+        for outcome in Self::sync_outcomes(note, content, prefs) {
             outcome.result?;
         }
 
         Ok(())
     }
 
-    pub fn sync_outcomes(content: &str, prefs: &SyncPreferences) -> Vec<SyncOutcome> {
-        let mut results = Vec::new();
+    /// Exports to every enabled target, each reporting its own result.
+    pub fn sync_outcomes(note: &NoteMeta, content: &str, prefs: &Preferences) -> Vec<SyncOutcome> {
+        Self::registry()
+            .enabled(&prefs.sync)
+            .map(|target| SyncOutcome::of(target, target.export(note, content, prefs)))
+            .collect()
+    }
+
+    /// Runs each enabled target's end-to-end test.
+    pub fn test_outcomes(note: &NoteMeta, content: &str, prefs: &Preferences) -> Vec<SyncOutcome> {
+        Self::registry()
+            .enabled(&prefs.sync)
+            .map(|target| SyncOutcome::of(target, target.test(note, content, prefs)))
+            .collect()
+    }
 
-        if prefs.markdown_enabled {
-            results.push(SyncOutcome {
-                target: SyncTarget::Markdown,
-                result: markdown::export(content, prefs),
-            });
+    /// Renders the note exactly as the matching file target would write it.
+    pub fn render(
+        format: ExportFormat,
+        note: &NoteMeta,
+        content: &str,
+        prefs: &Preferences,
+    ) -> SyncResult<String> {
+        match format {
+            ExportFormat::Markdown => Ok(markdown::render(note, content, &prefs.sync)),
+            ExportFormat::Html => Ok(html::render(note, content, &prefs.sync, prefs.theme)),
+            ExportFormat::Text => text::render(note, content, &prefs.sync),
         }
+    }
 
-        if prefs.apple_notes_enabled {
-            results.push(SyncOutcome {
-                target: SyncTarget::AppleNotes,
-                result: apple_notes::export(content, prefs),
-            });
+    /// Lets every enabled target follow a note rename. Failures are only
+    /// logged; the next export writes under the new name regardless.
+    pub fn rename_all(before: &NoteMeta, after: &NoteMeta, prefs: &Preferences) {
+        for target in Self::registry().enabled(&prefs.sync) {
+            if let Err(err) = target.rename(before, after, prefs) {
+                warn!(
+                    "{} sync could not follow the rename: {}",
+                    target.label(),
+                    err
+                );
+            }
         }
+    }
 
-        results
+    pub fn registry() -> &'static SyncRegistry {
+        static REGISTRY: OnceLock<SyncRegistry> = OnceLock::new();
+        REGISTRY.get_or_init(SyncRegistry::builtin)
     }
 }
 
@@ -71,6 +121,19 @@ impl std::fmt::Display for SyncError {
     }
 }
 
+impl SyncError {
+    /// Whether trying again later could succeed. Missing configuration or a
+    /// denied permission needs the user to act first.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            SyncError::Io(_) | SyncError::AppleScript(_) => true,
+            SyncError::NotConfigured(_) | SyncError::PermissionDenied(_) => false,
+            #[cfg(not(target_os = "macos"))]
+            SyncError::NotImplemented(_) => false,
+        }
+    }
+}
+
 impl std::error::Error for SyncError {}
 
 impl From<std::io::Error> for SyncError {
@@ -79,23 +142,66 @@ impl From<std::io::Error> for SyncError {
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize)]
-#[serde(rename_all = "kebab-case")]
-pub enum SyncTarget {
-    Markdown,
-    AppleNotes,
+pub struct SyncOutcome {
+    pub target: &'static str,
+    pub label: &'static str,
+    pub result: SyncResult<()>,
 }
 
-impl SyncTarget {
-    pub fn label(&self) -> &'static str {
-        match self {
-            SyncTarget::Markdown => "Markdown",
-            SyncTarget::AppleNotes => "Apple Notes",
+impl SyncOutcome {
+    fn of(target: &dyn SyncTarget, result: SyncResult<()>) -> Self {
+        Self {
+            target: target.id(),
+            label: target.label(),
+            result,
         }
     }
 }
 
-pub struct SyncOutcome {
-    pub target: SyncTarget,
-    pub result: SyncResult<()>,
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::preferences::SyncPreferences;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn file_targets_write_each_note_to_its_own_file() {
+        let dir = tempdir().expect("tempdir");
+        let folder = Some(dir.path().to_string_lossy().to_string());
+        let prefs = Preferences {
+            sync: SyncPreferences {
+                markdown_enabled: true,
+                markdown_path: folder.clone(),
+                markdown_filename: "{title}".to_string(),
+                html_enabled: true,
+                html_path: folder.clone(),
+                html_filename: "{title}".to_string(),
+                text_enabled: true,
+                text_path: folder,
+                text_filename: "{title}".to_string(),
+                ..SyncPreferences::default()
+            },
+            ..Preferences::default()
+        };
+        let first = NoteMeta::new("note-1".to_string(), "Groceries".to_string());
+        let second = NoteMeta::new("note-2".to_string(), "Ideas".to_string());
+        let renamed = NoteMeta {
+            title: "Shopping".to_string(),
+            ..first.clone()
+        };
+
+        SyncService::sync_all(&first, "<p>Milk</p>", &prefs).expect("export first");
+        SyncService::sync_all(&second, "<p>Boat</p>", &prefs).expect("export second");
+        SyncService::rename_all(&first, &renamed, &prefs);
+
+        for extension in ["md", "html", "txt"] {
+            let read =
+                |stem: &str| fs::read_to_string(dir.path().join(format!("{}.{}", stem, extension)));
+
+            assert!(read("Groceries").is_err(), "{} export not moved", extension);
+            assert!(read("Shopping").expect("renamed").contains("Milk"));
+            assert!(read("Ideas").expect("second").contains("Boat"));
+        }
+    }
 }
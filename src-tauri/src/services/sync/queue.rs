@@ -0,0 +1,504 @@
+use super::{SyncOutcome, SyncService};
+use crate::error::ErrorCode;
+use crate::services::{persist, preferences::PreferencesService, storage::StorageService};
+use chrono::{DateTime, Duration, Utc};
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use tauri::{AppHandle, Emitter, Manager};
+
+const QUEUE_FILENAME: &str = "sync-queue.json";
+
+/// Quiet period after the last save before the export runs.
+const DEBOUNCE: Duration = Duration::milliseconds(1500);
+/// Upper bound on how long continuous typing can hold back an export.
+const MAX_DELAY: Duration = Duration::seconds(10);
+const RETRY_BASE: Duration = Duration::seconds(2);
+const RETRY_MAX: Duration = Duration::minutes(5);
+const MAX_ATTEMPTS: u32 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SyncState {
+    #[default]
+    Idle,
+    Pending,
+    Syncing,
+    Error,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SyncStatus {
+    pub state: SyncState,
+    pub last_synced_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub next_retry_at: Option<DateTime<Utc>>,
+}
+
+/// Export of one note waiting to run. Persisted so a restart doesn't lose it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PendingSync {
+    note_id: String,
+    /// Targets still to run; empty means every enabled target.
+    #[serde(default)]
+    targets: Vec<String>,
+    #[serde(default)]
+    attempt: u32,
+    requested_at: DateTime<Utc>,
+    due_at: DateTime<Utc>,
+}
+
+impl PendingSync {
+    fn new(note_id: &str, now: DateTime<Utc>) -> Self {
+        Self {
+            note_id: note_id.to_string(),
+            targets: Vec::new(),
+            attempt: 0,
+            requested_at: now,
+            due_at: now + DEBOUNCE,
+        }
+    }
+
+    /// Folds another save of the same note into this one, pushing the export
+    /// back without letting it slip past `MAX_DELAY`.
+    fn coalesce(&mut self, now: DateTime<Utc>) {
+        self.targets.clear();
+        self.attempt = 0;
+        self.due_at = (now + DEBOUNCE).min(self.requested_at + MAX_DELAY);
+    }
+
+    /// Schedules another attempt for the targets that failed transiently, or
+    /// returns `None` once the attempts are used up.
+    fn retry(&self, failed: Vec<String>, now: DateTime<Utc>) -> Option<Self> {
+        let attempt = self.attempt + 1;
+        if failed.is_empty() || attempt >= MAX_ATTEMPTS {
+            return None;
+        }
+
+        Some(Self {
+            note_id: self.note_id.clone(),
+            targets: failed,
+            attempt,
+            requested_at: self.requested_at,
+            due_at: now + backoff(attempt),
+        })
+    }
+
+    fn includes(&self, target: &str) -> bool {
+        self.targets.is_empty() || self.targets.iter().any(|id| id == target)
+    }
+}
+
+fn backoff(attempt: u32) -> Duration {
+    let factor = 1_i32 << attempt.min(16);
+    (RETRY_BASE * factor).min(RETRY_MAX)
+}
+
+struct QueueState {
+    /// At most one entry per note.
+    pending: Vec<PendingSync>,
+    /// The export running now. It stays in the queue file until `finish`.
+    running: Option<PendingSync>,
+    status: SyncStatus,
+}
+
+impl QueueState {
+    fn pending_for(&mut self, note_id: &str) -> Option<&mut PendingSync> {
+        self.pending
+            .iter_mut()
+            .find(|pending| pending.note_id == note_id)
+    }
+}
+
+struct Shared {
+    path: PathBuf,
+    state: Mutex<QueueState>,
+    wake: Condvar,
+}
+
+/// Runs exports on a background thread so saving never waits on disk or
+/// osascript. Rapid saves collapse into one export and failures are retried
+/// with backoff. Each note keeps its own pending export.
+pub struct SyncQueue {
+    shared: Arc<Shared>,
+}
+
+impl SyncQueue {
+    pub fn new(app_handle: &AppHandle) -> Result<Self, String> {
+        let data_dir = app_handle
+            .path()
+            .app_data_dir()
+            .map_err(|e| format!("Failed to get data directory: {}", e))?;
+
+        Self::open(&data_dir)
+    }
+
+    pub fn open(data_dir: &Path) -> Result<Self, String> {
+        fs::create_dir_all(data_dir)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+
+        let path = data_dir.join(QUEUE_FILENAME);
+        let pending = load_pending(&path).unwrap_or_else(|err| {
+            warn!("Dropping unreadable sync queue: {}", err);
+            Vec::new()
+        });
+
+        let status = SyncStatus {
+            state: if !pending.is_empty() {
+                SyncState::Pending
+            } else {
+                SyncState::Idle
+            },
+            ..SyncStatus::default()
+        };
+
+        Ok(Self {
+            shared: Arc::new(Shared {
+                path,
+                state: Mutex::new(QueueState {
+                    pending,
+                    running: None,
+                    status,
+                }),
+                wake: Condvar::new(),
+            }),
+        })
+    }
+
+    /// Queues an export of `note_id`, folding it into any export of the same
+    /// note already waiting.
+    pub fn enqueue(&self, app: &AppHandle, note_id: &str) -> Result<(), String> {
+        let now = Utc::now();
+        let mut state = self.shared.lock()?;
+
+        match state.pending_for(note_id) {
+            Some(pending) => pending.coalesce(now),
+            None => state.pending.push(PendingSync::new(note_id, now)),
+        }
+
+        self.shared.persist(&state);
+
+        let changed = state.status.state != SyncState::Pending;
+        state.status.state = SyncState::Pending;
+        state.status.next_retry_at = None;
+        let status = state.status.clone();
+        drop(state);
+
+        self.shared.wake.notify_all();
+
+        if changed {
+            emit_status(app, &status);
+        }
+
+        Ok(())
+    }
+
+    pub fn status(&self) -> Result<SyncStatus, String> {
+        Ok(self.shared.lock()?.status.clone())
+    }
+
+    pub fn start(&self, app: AppHandle) {
+        let shared = Arc::clone(&self.shared);
+
+        thread::spawn(move || {
+            while let Some(job) = shared.next_due() {
+                if let Some(status) = shared.set_state(SyncState::Syncing) {
+                    emit_status(&app, &status);
+                }
+
+                let outcomes = run(&app, &job);
+                let status = shared.finish(&job, outcomes);
+                emit_status(&app, &status);
+            }
+        });
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> Result<MutexGuard<'_, QueueState>, String> {
+        self.state
+            .lock()
+            .map_err(|e| format!("Failed to lock sync queue: {}", e))
+    }
+
+    /// Blocks until the earliest pending export is due and hands it over. It
+    /// is kept in the queue file until `finish` so a crash mid-export retries
+    /// it.
+    fn next_due(&self) -> Option<PendingSync> {
+        let mut state = self.state.lock().ok()?;
+
+        loop {
+            let earliest = state
+                .pending
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, pending)| pending.due_at)
+                .map(|(index, pending)| (index, pending.due_at));
+
+            let wait = match earliest {
+                None => None,
+                Some((index, due_at)) => {
+                    let now = Utc::now();
+                    if due_at <= now {
+                        let job = state.pending.remove(index);
+                        state.running = Some(job.clone());
+                        return Some(job);
+                    }
+                    Some((due_at - now).to_std().unwrap_or_default())
+                }
+            };
+
+            state = match wait {
+                None => self.wake.wait(state).ok()?,
+                Some(timeout) => self.wake.wait_timeout(state, timeout).ok()?.0,
+            };
+        }
+    }
+
+    fn set_state(&self, new_state: SyncState) -> Option<SyncStatus> {
+        let mut state = self.state.lock().ok()?;
+        state.status.state = new_state;
+        Some(state.status.clone())
+    }
+
+    fn finish(&self, job: &PendingSync, outcomes: Result<Vec<SyncOutcome>, String>) -> SyncStatus {
+        let now = Utc::now();
+        let Ok(mut state) = self.state.lock() else {
+            return SyncStatus::default();
+        };
+
+        let mut errors = Vec::new();
+        let mut failed = Vec::new();
+        let mut ran = false;
+
+        match outcomes {
+            Ok(outcomes) => {
+                for outcome in outcomes {
+                    ran = true;
+                    if let Err(err) = outcome.result {
+                        error!("Sync to {} failed: {}", outcome.label, err);
+                        errors.push(format!("{}: {}", outcome.label, err));
+                        if err.is_transient() {
+                            failed.push(outcome.target.to_string());
+                        }
+                    }
+                }
+            }
+            Err(err) => {
+                error!("Sync failed: {}", err);
+                errors.push(err);
+            }
+        }
+
+        // A save of the same note that arrived while exporting supersedes any retry
+        state.running = None;
+        if state.pending_for(&job.note_id).is_none() {
+            state.pending.extend(job.retry(failed, now));
+        }
+        self.persist(&state);
+
+        let next_retry_at = state
+            .pending
+            .iter()
+            .filter(|pending| pending.attempt > 0)
+            .map(|pending| pending.due_at)
+            .min();
+        let next_state = if next_retry_at.is_some() {
+            SyncState::Error
+        } else if !state.pending.is_empty() {
+            SyncState::Pending
+        } else if errors.is_empty() {
+            SyncState::Idle
+        } else {
+            SyncState::Error
+        };
+
+        // Nothing to report when no target ran, e.g. the note was deleted
+        let status = &mut state.status;
+        if !errors.is_empty() {
+            status.last_error = Some(errors.join("; "));
+        } else if ran {
+            status.last_synced_at = Some(now);
+            status.last_error = None;
+        }
+        status.state = next_state;
+        status.next_retry_at = next_retry_at;
+
+        status.clone()
+    }
+
+    fn persist(&self, state: &QueueState) {
+        // Newer entries first, so they win over the running one on reload
+        let entries: Vec<&PendingSync> = state.pending.iter().chain(&state.running).collect();
+
+        let result = if entries.is_empty() {
+            match fs::remove_file(&self.path) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+                _ => Ok(()),
+            }
+        } else {
+            serde_json::to_string_pretty(&entries)
+                .map_err(io::Error::other)
+                .and_then(|json| persist::write_atomic(&self.path, json))
+        };
+
+        if let Err(e) = result {
+            error!("Failed to save sync queue: {}", e);
+        }
+    }
+}
+
+fn load_pending(path: &Path) -> Result<Vec<PendingSync>, String> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(format!("Failed to read sync queue: {}", err)),
+    };
+
+    let stored: Vec<PendingSync> = serde_json::from_str(&contents)
+        .map_err(|e| format!("Failed to parse sync queue: {}", e))?;
+
+    // Whatever was waiting before the restart can go out straight away. An
+    // export cut short by the restart is dropped if the note was saved again.
+    let now = Utc::now();
+    let mut pending: Vec<PendingSync> = Vec::new();
+    for mut entry in stored {
+        if pending.iter().all(|kept| kept.note_id != entry.note_id) {
+            entry.due_at = entry.due_at.min(now);
+            pending.push(entry);
+        }
+    }
+
+    Ok(pending)
+}
+
+fn run(app: &AppHandle, job: &PendingSync) -> Result<Vec<SyncOutcome>, String> {
+    let storage = app.state::<StorageService>();
+
+    // A note deleted since it was saved has nothing left to export
+    let note = match storage.note(&job.note_id) {
+        Ok(note) => note,
+        Err(err) if err.is(ErrorCode::NotFound) => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+
+    let content = storage.read_note_by_id(&note.id)?;
+    let preferences = app.state::<PreferencesService>().get()?;
+
+    Ok(SyncService::registry()
+        .enabled(&preferences.sync)
+        .filter(|target| job.includes(target.id()))
+        .map(|target| SyncOutcome::of(target, target.export(&note, &content, &preferences)))
+        .collect())
+}
+
+fn emit_status(app: &AppHandle, status: &SyncStatus) {
+    if let Err(e) = app.emit("sync-status", status.clone()) {
+        error!("Failed to emit sync-status event: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn coalesces_saves_up_to_max_delay() {
+        let start = Utc::now();
+        let mut pending = PendingSync::new("a", start);
+
+        pending.coalesce(start + Duration::seconds(1));
+        assert_eq!(pending.due_at, start + Duration::seconds(1) + DEBOUNCE);
+
+        pending.coalesce(start + Duration::seconds(30));
+        assert_eq!(pending.due_at, start + MAX_DELAY);
+    }
+
+    #[test]
+    fn retries_failed_targets_with_backoff() {
+        let now = Utc::now();
+        let pending = PendingSync::new("a", now);
+
+        let retry = pending
+            .retry(vec!["apple-notes".to_string()], now)
+            .expect("retry");
+        assert_eq!(retry.attempt, 1);
+        assert_eq!(retry.due_at, now + RETRY_BASE * 2);
+        assert!(retry.includes("apple-notes"));
+        assert!(!retry.includes("markdown"));
+
+        assert!(pending.retry(Vec::new(), now).is_none());
+
+        let exhausted = PendingSync {
+            attempt: MAX_ATTEMPTS - 1,
+            ..retry
+        };
+        assert!(exhausted
+            .retry(vec!["apple-notes".to_string()], now)
+            .is_none());
+        assert_eq!(backoff(30), RETRY_MAX);
+    }
+
+    #[test]
+    fn pending_work_survives_restart() {
+        let dir = tempdir().expect("tempdir");
+        let queue = SyncQueue::open(dir.path()).expect("open");
+        {
+            let mut state = queue.shared.lock().expect("lock");
+            let later = Utc::now() + Duration::hours(1);
+            state.pending = vec![PendingSync::new("a", later), PendingSync::new("b", later)];
+            queue.shared.persist(&state);
+        }
+
+        let reopened = SyncQueue::open(dir.path()).expect("reopen");
+        let state = reopened.shared.lock().expect("lock");
+        let ids: Vec<&str> = state
+            .pending
+            .iter()
+            .map(|pending| pending.note_id.as_str())
+            .collect();
+
+        assert_eq!(ids, vec!["a", "b"]);
+        assert!(state
+            .pending
+            .iter()
+            .all(|pending| pending.due_at <= Utc::now()));
+        assert_eq!(state.status.state, SyncState::Pending);
+    }
+
+    #[test]
+    fn running_export_stays_queued_until_finished() {
+        let dir = tempdir().expect("tempdir");
+        let queue = SyncQueue::open(dir.path()).expect("open");
+        let earlier = Utc::now() - Duration::minutes(1);
+        queue.shared.lock().expect("lock").pending = vec![
+            PendingSync::new("a", earlier),
+            PendingSync::new("b", Utc::now()),
+        ];
+
+        let job = queue.shared.next_due().expect("due");
+        assert_eq!(job.note_id, "a");
+        {
+            // A save of another note while exporting rewrites the queue file
+            let state = queue.shared.lock().expect("lock");
+            queue.shared.persist(&state);
+        }
+        let saved = load_pending(&dir.path().join(QUEUE_FILENAME)).expect("load");
+        assert!(saved.iter().any(|pending| pending.note_id == "a"));
+
+        let status = queue.shared.finish(&job, Ok(Vec::new()));
+
+        assert_eq!(status.state, SyncState::Pending);
+        assert!(status.last_synced_at.is_none());
+        let state = queue.shared.lock().expect("lock");
+        assert_eq!(state.pending.len(), 1);
+        assert_eq!(state.pending[0].note_id, "b");
+        let saved = load_pending(&dir.path().join(QUEUE_FILENAME)).expect("load");
+        assert_eq!(saved.len(), 1);
+    }
+}
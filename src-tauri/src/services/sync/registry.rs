@@ -0,0 +1,111 @@
+use super::{
+    apple_notes::AppleNotesTarget, html::HtmlTarget, markdown::MarkdownTarget, text::TextTarget,
+    SyncResult,
+};
+use crate::models::note::NoteMeta;
+use crate::models::preferences::{Preferences, SyncPreferences};
+use serde::Serialize;
+
+/// What a sync target can do, so the UI can adapt without hard-coding targets.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct SyncCapabilities {
+    /// Edits made at the destination can flow back into the note.
+    pub two_way: bool,
+    /// The OS asks the user for permission before the first export.
+    pub requires_permission: bool,
+    /// The target works on the current platform.
+    pub supported: bool,
+}
+
+pub trait SyncTarget: Send + Sync {
+    /// Stable identifier used in outcomes and events, e.g. `"markdown"`.
+    fn id(&self) -> &'static str;
+
+    fn label(&self) -> &'static str;
+
+    fn capabilities(&self) -> SyncCapabilities;
+
+    fn is_enabled(&self, prefs: &SyncPreferences) -> bool;
+
+    fn export(&self, note: &NoteMeta, content: &str, prefs: &Preferences) -> SyncResult<()>;
+
+    /// Checks the target end to end. Defaults to a real export.
+    fn test(&self, note: &NoteMeta, content: &str, prefs: &Preferences) -> SyncResult<()> {
+        self.export(note, content, prefs)
+    }
+
+    /// Follows a note rename, for targets that name what they write after
+    /// the title. Defaults to doing nothing.
+    fn rename(
+        &self,
+        _before: &NoteMeta,
+        _after: &NoteMeta,
+        _prefs: &Preferences,
+    ) -> SyncResult<()> {
+        Ok(())
+    }
+}
+
+pub struct SyncRegistry {
+    targets: Vec<Box<dyn SyncTarget>>,
+}
+
+impl SyncRegistry {
+    pub fn empty() -> Self {
+        Self {
+            targets: Vec::new(),
+        }
+    }
+
+    pub fn builtin() -> Self {
+        let mut registry = Self::empty();
+        registry.register(Box::new(MarkdownTarget));
+        registry.register(Box::new(HtmlTarget));
+        registry.register(Box::new(TextTarget));
+        registry.register(Box::new(AppleNotesTarget));
+        registry
+    }
+
+    pub fn register(&mut self, target: Box<dyn SyncTarget>) {
+        self.targets.retain(|existing| existing.id() != target.id());
+        self.targets.push(target);
+    }
+
+    pub fn targets(&self) -> impl Iterator<Item = &dyn SyncTarget> {
+        self.targets.iter().map(|target| target.as_ref())
+    }
+
+    pub fn enabled<'a>(
+        &'a self,
+        prefs: &'a SyncPreferences,
+    ) -> impl Iterator<Item = &'a dyn SyncTarget> + 'a {
+        self.targets()
+            .filter(move |target| target.is_enabled(prefs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_every_enabled_target() {
+        let registry = SyncRegistry::builtin();
+        let prefs = SyncPreferences {
+            markdown_enabled: true,
+            apple_notes_enabled: true,
+            ..SyncPreferences::default()
+        };
+
+        let enabled: Vec<&str> = registry.enabled(&prefs).map(|target| target.id()).collect();
+        assert_eq!(enabled, vec!["markdown", "apple-notes"]);
+    }
+
+    #[test]
+    fn register_replaces_same_id() {
+        let mut registry = SyncRegistry::builtin();
+        registry.register(Box::new(MarkdownTarget));
+
+        assert_eq!(registry.targets().count(), 4);
+    }
+}
@@ -0,0 +1,179 @@
+use super::{files, SyncCapabilities, SyncResult, SyncTarget};
+use crate::models::note::NoteMeta;
+use crate::models::preferences::{MetadataStyle, Preferences, SyncPreferences};
+use crate::services::persist;
+use chrono::{DateTime, Utc};
+use log::debug;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+const SOURCE_LABEL: &str = "What The Note";
+const WRAP_WIDTH: usize = 80;
+
+pub struct TextTarget;
+
+impl SyncTarget for TextTarget {
+    fn id(&self) -> &'static str {
+        "text"
+    }
+
+    fn label(&self) -> &'static str {
+        "Plain text"
+    }
+
+    fn capabilities(&self) -> SyncCapabilities {
+        SyncCapabilities {
+            two_way: false,
+            requires_permission: false,
+            supported: true,
+        }
+    }
+
+    fn is_enabled(&self, prefs: &SyncPreferences) -> bool {
+        prefs.text_enabled
+    }
+
+    fn export(&self, note: &NoteMeta, content: &str, prefs: &Preferences) -> SyncResult<()> {
+        export(note, content, &prefs.sync)
+    }
+
+    fn rename(&self, before: &NoteMeta, after: &NoteMeta, prefs: &Preferences) -> SyncResult<()> {
+        files::move_export(
+            "Plain text",
+            &output_path(before, &prefs.sync)?,
+            &output_path(after, &prefs.sync)?,
+        )
+    }
+}
+
+pub fn export(note: &NoteMeta, content: &str, prefs: &SyncPreferences) -> SyncResult<()> {
+    fs::create_dir_all(resolve_base_path(prefs)?)?;
+
+    let output_path = output_path(note, prefs)?;
+    let rendered = render(note, content, prefs)?;
+
+    persist::write_atomic(&output_path, &rendered)?;
+    debug!(
+        "Plain text sync wrote {} bytes to {}",
+        rendered.len(),
+        output_path.display()
+    );
+
+    Ok(())
+}
+
+pub fn output_path(note: &NoteMeta, prefs: &SyncPreferences) -> SyncResult<PathBuf> {
+    Ok(resolve_base_path(prefs)?.join(files::render_filename(&prefs.text_filename, note, "txt")))
+}
+
+/// The text `export` would write, without touching the destination folder.
+pub fn render(note: &NoteMeta, content: &str, prefs: &SyncPreferences) -> SyncResult<String> {
+    Ok(render_document(
+        &convert_html_to_text(content)?,
+        note,
+        prefs,
+        Utc::now(),
+    ))
+}
+
+fn render_document(
+    text: &str,
+    note: &NoteMeta,
+    prefs: &SyncPreferences,
+    synced_at: DateTime<Utc>,
+) -> String {
+    let body = text.trim_matches('\n');
+
+    match prefs.text_metadata {
+        MetadataStyle::Yaml => format!(
+            "---\ntitle: {}\nid: {}\ncreated: {}\nupdated: {}\nsynced: {}\nsource: {}\n---\n\n{}\n",
+            files::yaml_string(&note.title),
+            note.id,
+            note.created_at.to_rfc3339(),
+            note.updated_at.to_rfc3339(),
+            synced_at.to_rfc3339(),
+            files::yaml_string(SOURCE_LABEL),
+            body
+        ),
+        MetadataStyle::HtmlComment => format!(
+            "source: {} | sync-date: {}\n\n{}\n",
+            SOURCE_LABEL,
+            synced_at.to_rfc3339(),
+            body
+        ),
+        MetadataStyle::None => format!("{}\n", body),
+    }
+}
+
+fn convert_html_to_text(content: &str) -> SyncResult<String> {
+    if content.trim().is_empty() {
+        return Ok(String::new());
+    }
+
+    let text = html2text::config::plain()
+        .allow_width_overflow()
+        .string_from_read(content.as_bytes(), WRAP_WIDTH)
+        .map_err(io::Error::other)?;
+
+    Ok(text)
+}
+
+fn resolve_base_path(prefs: &SyncPreferences) -> SyncResult<PathBuf> {
+    files::resolve_directory(
+        prefs.text_path.as_deref(),
+        "Plain text destination folder is not set",
+        "Plain text destination must be a directory",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn prefs(dir: &std::path::Path) -> SyncPreferences {
+        SyncPreferences {
+            text_enabled: true,
+            text_path: Some(dir.to_string_lossy().to_string()),
+            ..SyncPreferences::default()
+        }
+    }
+
+    #[test]
+    fn writes_plain_text_without_markup() {
+        let dir = tempdir().expect("tempdir");
+        let prefs = SyncPreferences {
+            text_metadata: MetadataStyle::None,
+            ..prefs(dir.path())
+        };
+        let note = NoteMeta::new("note-1".to_string(), "Groceries".to_string());
+
+        export(
+            &note,
+            "<h1>Hello</h1><p>World</p><ul><li>one</li></ul>",
+            &prefs,
+        )
+        .expect("export success");
+
+        let content = fs::read_to_string(dir.path().join("What The Note.txt")).expect("read file");
+        assert!(!content.contains('<'));
+        assert!(!content.contains("source: What The Note"));
+        assert!(content.contains("Hello"));
+        assert!(content.contains("World"));
+        assert!(content.contains("one"));
+    }
+
+    #[test]
+    fn writes_yaml_front_matter() {
+        let prefs = SyncPreferences {
+            text_metadata: MetadataStyle::Yaml,
+            ..SyncPreferences::default()
+        };
+        let note = NoteMeta::new("note-1".to_string(), "Groceries".to_string());
+
+        let content = render(&note, "<p>Milk</p>", &prefs).expect("render");
+        assert!(content.starts_with("---\ntitle: \"Groceries\"\nid: note-1\n"));
+        assert!(content.ends_with("---\n\nMilk\n"));
+    }
+}
@@ -0,0 +1,127 @@
+use super::markdown::{self, ExternalEdit};
+use crate::commands::note;
+use crate::models::preferences::SyncPreferences;
+use crate::services::{preferences::PreferencesService, storage::StorageService};
+use log::error;
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Emitter, Manager};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Serialize)]
+pub struct SyncConflict {
+    pub note_id: String,
+    pub conflict_path: String,
+}
+
+/// Polls the exported Markdown file while two-way sync is enabled and pulls
+/// edits made outside the app back into the active note.
+#[derive(Default)]
+pub struct MarkdownWatcher {
+    is_running: Arc<AtomicBool>,
+}
+
+impl MarkdownWatcher {
+    pub fn start(&self, app: AppHandle) {
+        if self.is_running.load(Ordering::SeqCst) {
+            return;
+        }
+
+        self.is_running.store(true, Ordering::SeqCst);
+
+        let is_running = Arc::clone(&self.is_running);
+
+        thread::spawn(move || {
+            let mut last_seen: Option<(PathBuf, SystemTime)> = None;
+
+            while is_running.load(Ordering::SeqCst) {
+                thread::sleep(POLL_INTERVAL);
+
+                let prefs = match app.state::<PreferencesService>().get() {
+                    Ok(prefs) if prefs.sync.is_markdown_two_way() => prefs.sync,
+                    _ => {
+                        last_seen = None;
+                        continue;
+                    }
+                };
+
+                let Ok(note) = app.state::<StorageService>().active_note() else {
+                    continue;
+                };
+                let Ok(path) = markdown::output_path(&note, &prefs) else {
+                    continue;
+                };
+                let Ok(modified) = fs::metadata(&path).and_then(|meta| meta.modified()) else {
+                    continue;
+                };
+
+                let current = Some((path, modified));
+                if current == last_seen {
+                    continue;
+                }
+                last_seen = current;
+
+                if let Err(e) = Self::check(&app, &prefs) {
+                    error!("Failed to check Markdown file for external edits: {}", e);
+                }
+            }
+        });
+    }
+
+    fn check(app: &AppHandle, prefs: &SyncPreferences) -> Result<(), String> {
+        let storage = app.state::<StorageService>();
+        let note = storage.active_note()?;
+
+        let Some(edit) = markdown::read_external_edit(&note, prefs).map_err(|e| e.to_string())?
+        else {
+            return Ok(());
+        };
+
+        // Compares content rather than times, so a rename alone isn't a conflict
+        let content = storage.read_note_by_id(&note.id)?;
+
+        if edit.is_based_on(&content) {
+            Self::import(app, &storage, &note.id, &edit, prefs)
+        } else {
+            let conflict_path = markdown::write_conflict_copy(&edit).map_err(|e| e.to_string())?;
+
+            app.emit(
+                "markdown-sync-conflict",
+                SyncConflict {
+                    note_id: note.id,
+                    conflict_path: conflict_path.to_string_lossy().to_string(),
+                },
+            )
+            .map_err(|e| format!("Failed to emit markdown-sync-conflict event: {}", e))
+        }
+    }
+
+    fn import(
+        app: &AppHandle,
+        storage: &StorageService,
+        note_id: &str,
+        edit: &ExternalEdit,
+        prefs: &SyncPreferences,
+    ) -> Result<(), String> {
+        let content = edit.to_html();
+        storage.write_note_by_id(note_id, &content)?;
+
+        // Re-read so the front matter carries the new `updated` time
+        let note = storage.active_note()?;
+        markdown::accept_external_edit(edit, &note, prefs).map_err(|e| e.to_string())?;
+
+        app.emit("note-content-changed", note_id.to_string())
+            .map_err(|e| format!("Failed to emit note-content-changed event: {}", e))?;
+
+        // Search and the other sync targets follow as if the note was saved here
+        note::after_save(app, note_id, &content).map_err(String::from)
+    }
+}
@@ -1,3 +1,4 @@
+use log::error;
 use tauri::{
     menu::{Menu, MenuItemBuilder},
     AppHandle, Emitter,
@@ -63,21 +64,21 @@ fn menu_handler(app: &AppHandle, event: tauri::menu::MenuEvent) {
     match event.id().as_ref() {
         "toggle" => {
             if let Err(e) = app.emit("toggle-window", ()) {
-                eprintln!("Failed to emit toggle-window event: {}", e);
+                error!("Failed to emit toggle-window event: {}", e);
             }
             // Also emit shortcut-triggered to lock window (same behavior as keyboard shortcut)
             if let Err(e) = app.emit("shortcut-triggered", ()) {
-                eprintln!("Failed to emit shortcut-triggered event: {}", e);
+                error!("Failed to emit shortcut-triggered event: {}", e);
             }
         }
         "preferences" => {
             if let Err(e) = app.emit("open-preferences", ()) {
-                eprintln!("Failed to emit open-preferences event: {}", e);
+                error!("Failed to emit open-preferences event: {}", e);
             }
         }
         "check-updates" => {
             if let Err(e) = app.emit("check-updates", ()) {
-                eprintln!("Failed to emit check-updates event: {}", e);
+                error!("Failed to emit check-updates event: {}", e);
             }
         }
         "quit" => {
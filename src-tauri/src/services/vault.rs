@@ -0,0 +1,303 @@
+use crate::error::{AppError, ErrorCode};
+use crate::services::persist::{self, Access};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng},
+    Key, XChaCha20Poly1305, XNonce,
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use zeroize::Zeroizing;
+
+const VAULT_FILENAME: &str = "vault.json";
+/// Marks a stored file as ciphertext; anything else is read as plaintext.
+const ENCRYPTED_PREFIX: &str = "wtn-enc-v1:";
+/// Encrypted with the key so a wrong passphrase is caught before any note is read.
+const CHECK_PLAINTEXT: &str = "what-the-note";
+const NONCE_LEN: usize = 24;
+const SALT_LEN: usize = 16;
+pub const MIN_PASSPHRASE_LEN: usize = 8;
+const LOCKED_MESSAGE: &str = "Notes are locked";
+
+/// Argon2id cost settings, stored with the vault so they can be raised later
+/// without breaking existing vaults.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct VaultFile {
+    kdf: KdfParams,
+    salt: String,
+    check: String,
+}
+
+/// A derived 256-bit key. Cloning shares the same zeroed-on-drop buffer.
+#[derive(Clone)]
+pub struct VaultKey(Arc<Zeroizing<[u8; 32]>>);
+
+impl VaultKey {
+    fn derive(passphrase: &str, salt: &[u8], params: KdfParams) -> Result<Self, String> {
+        let params = Params::new(
+            params.memory_kib,
+            params.iterations,
+            params.parallelism,
+            Some(32),
+        )
+        .map_err(|e| format!("Invalid key derivation settings: {}", e))?;
+
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+            .map_err(|e| format!("Failed to derive key: {}", e))?;
+
+        Ok(Self(Arc::new(key)))
+    }
+
+    fn cipher(&self) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(Key::from_slice(&self.0[..]))
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> Result<String, String> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher()
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| "Failed to encrypt note".to_string())?;
+
+        let mut payload = nonce.to_vec();
+        payload.extend_from_slice(&ciphertext);
+
+        Ok(format!("{}{}", ENCRYPTED_PREFIX, BASE64.encode(payload)))
+    }
+
+    pub fn decrypt(&self, stored: &str) -> Result<String, String> {
+        let encoded = stored
+            .trim_end()
+            .strip_prefix(ENCRYPTED_PREFIX)
+            .ok_or_else(|| "Data is not encrypted".to_string())?;
+        let payload = BASE64
+            .decode(encoded)
+            .map_err(|e| format!("Failed to decode encrypted data: {}", e))?;
+
+        if payload.len() < NONCE_LEN {
+            return Err("Encrypted data is truncated".to_string());
+        }
+        let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+
+        let plaintext = self
+            .cipher()
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| "Failed to decrypt: wrong passphrase or damaged data".to_string())?;
+
+        String::from_utf8(plaintext).map_err(|e| format!("Decrypted data is not text: {}", e))
+    }
+}
+
+pub fn is_encrypted(stored: &str) -> bool {
+    stored.starts_with(ENCRYPTED_PREFIX)
+}
+
+/// Encrypts `content` when a key is given; without one it is stored as is.
+pub fn encode(key: Option<&VaultKey>, content: &str) -> Result<String, String> {
+    match key {
+        Some(key) => key.encrypt(content),
+        None => Ok(content.to_string()),
+    }
+}
+
+/// Reverses [`encode`]. Plaintext passes through untouched, so files written
+/// before encryption was enabled stay readable until they are migrated.
+pub fn decode(key: Option<&VaultKey>, stored: &str) -> Result<String, String> {
+    if !is_encrypted(stored) {
+        return Ok(stored.to_string());
+    }
+
+    key.ok_or_else(|| LOCKED_MESSAGE.to_string())?
+        .decrypt(stored)
+}
+
+/// Passphrase-protected key for notes at rest. When no `vault.json` exists
+/// encryption is off and notes are stored as plaintext.
+pub struct Vault {
+    path: PathBuf,
+    file: Option<VaultFile>,
+    key: Option<VaultKey>,
+    last_used: Instant,
+}
+
+impl Vault {
+    pub fn open(data_dir: &Path, access: Access) -> Result<Self, String> {
+        let path = data_dir.join(VAULT_FILENAME);
+        let parse = |content: &str| {
+            serde_json::from_str::<VaultFile>(content)
+                .map_err(|e| format!("Failed to parse vault: {}", e))
+        };
+        let file = persist::read_recovering(&path, parse, access)?;
+
+        Ok(Self {
+            path,
+            file,
+            key: None,
+            last_used: Instant::now(),
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.file.is_some()
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.is_enabled() && self.key.is_none()
+    }
+
+    /// The key to encode notes with: `None` when encryption is off, an error
+    /// while locked. Each call counts as activity for auto-lock.
+    pub fn key(&mut self) -> Result<Option<VaultKey>, AppError> {
+        if !self.is_enabled() {
+            return Ok(None);
+        }
+
+        let key = self
+            .key
+            .clone()
+            .ok_or_else(|| AppError::new(ErrorCode::Locked, LOCKED_MESSAGE))?;
+        self.last_used = Instant::now();
+        Ok(Some(key))
+    }
+
+    /// Sets up a new vault and leaves it unlocked.
+    pub fn create(&mut self, passphrase: &str, kdf: KdfParams) -> Result<VaultKey, AppError> {
+        if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+            return Err(AppError::invalid(
+                "passphrase",
+                format!(
+                    "Passphrase must be at least {} characters",
+                    MIN_PASSPHRASE_LEN
+                ),
+            ));
+        }
+
+        let mut salt = [0u8; SALT_LEN];
+        OsRng
+            .try_fill_bytes(&mut salt)
+            .map_err(|e| format!("Failed to generate salt: {}", e))?;
+
+        let key = VaultKey::derive(passphrase, &salt, kdf)?;
+        let file = VaultFile {
+            kdf,
+            salt: BASE64.encode(salt),
+            check: key.encrypt(CHECK_PLAINTEXT)?,
+        };
+
+        let json = serde_json::to_string_pretty(&file)
+            .map_err(|e| format!("Failed to serialize vault: {}", e))?;
+        persist::write_with_backup(&self.path, json)
+            .map_err(|e| format!("Failed to write vault: {}", e))?;
+
+        self.file = Some(file);
+        self.key = Some(key.clone());
+        self.last_used = Instant::now();
+
+        Ok(key)
+    }
+
+    pub fn unlock(&mut self, passphrase: &str) -> Result<VaultKey, AppError> {
+        let file = self
+            .file
+            .as_ref()
+            .ok_or_else(|| AppError::new(ErrorCode::Conflict, "Encryption is not enabled"))?;
+
+        let salt = BASE64
+            .decode(&file.salt)
+            .map_err(|e| format!("Failed to decode vault salt: {}", e))?;
+        let key = VaultKey::derive(passphrase, &salt, file.kdf)?;
+
+        if key.decrypt(&file.check).ok().as_deref() != Some(CHECK_PLAINTEXT) {
+            return Err(
+                AppError::new(ErrorCode::PermissionDenied, "Incorrect passphrase")
+                    .with_field("passphrase"),
+            );
+        }
+
+        self.key = Some(key.clone());
+        self.last_used = Instant::now();
+
+        Ok(key)
+    }
+
+    pub fn lock(&mut self) {
+        self.key = None;
+    }
+
+    /// Forgets the vault entirely. Callers must have decrypted every note first.
+    pub fn remove(&mut self) -> Result<(), String> {
+        persist::remove_with_backup(&self.path)
+            .map_err(|e| format!("Failed to remove vault: {}", e))?;
+        self.file = None;
+        self.key = None;
+        Ok(())
+    }
+
+    pub fn idle_for(&self) -> Duration {
+        self.last_used.elapsed()
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn fast_kdf() -> KdfParams {
+    KdfParams {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn round_trips_and_rejects_wrong_passphrase() {
+        let dir = tempdir().expect("tempdir");
+        let mut vault = Vault::open(dir.path(), Access::ReadWrite).expect("open");
+        assert!(!vault.is_enabled());
+        assert!(vault.create("short", fast_kdf()).is_err());
+
+        let key = vault.create("correct horse", fast_kdf()).expect("create");
+        let stored = key.encrypt("<p>Secret</p>").expect("encrypt");
+        assert!(is_encrypted(&stored));
+        assert!(!stored.contains("Secret"));
+
+        let mut reopened = Vault::open(dir.path(), Access::ReadWrite).expect("reopen");
+        assert!(reopened.is_locked());
+        assert_eq!(
+            reopened.key().err().map(|e| e.code),
+            Some(ErrorCode::Locked)
+        );
+        assert_eq!(
+            reopened.unlock("wrong horse").err().map(|e| e.code),
+            Some(ErrorCode::PermissionDenied)
+        );
+
+        let key = reopened.unlock("correct horse").expect("unlock");
+        assert_eq!(key.decrypt(&stored).expect("decrypt"), "<p>Secret</p>");
+    }
+}
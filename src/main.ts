@@ -5,7 +5,8 @@ import { IdleDetector } from "./services/idle-detector";
 import { UpdateService } from "./services/update-service";
 import { listen } from "@tauri-apps/api/event";
 import { invoke } from "@tauri-apps/api/core";
-import type { Preferences } from "./types";
+import { errorMessage } from "./types";
+import type { NoteMeta, Preferences } from "./types";
 import { Editor } from "@tiptap/core";
 import StarterKit from "@tiptap/starter-kit";
 import { Color, TextStyle } from "@tiptap/extension-text-style";
@@ -16,11 +17,13 @@ import { createIcons, icons } from "lucide";
 // State
 let preferences: Preferences;
 let currentNote: string = "";
+let currentNoteId: string | undefined;
 let saveTimeout: number | null = null;
 const idleDetector = new IdleDetector();
 let windowLocked = false; // True when user has clicked in window or opened via shortcut - prevents auto-hide
 let isFading = false; // Prevents multiple fade operations
 let isMouseOverWindow = false; // True when mouse is hovering over window
+let notesLocked = false; // True while encrypted notes are locked and the editor is blanked
 
 // DOM elements
 let editor: Editor;
@@ -47,9 +50,13 @@ async function init() {
     return;
   }
 
-  // Load note content
+  // Load note content, unless encrypted notes are locked
   try {
-    currentNote = await NoteService.get();
+    notesLocked = (await NoteService.encryptionStatus()).locked;
+    currentNoteId = (await NoteService.active()).id;
+    if (!notesLocked) {
+      currentNote = await NoteService.get(currentNoteId);
+    }
   } catch (error) {
     console.error("Failed to load note:", error);
   }
@@ -86,6 +93,9 @@ async function init() {
   // Set up toolbar
   setupToolbar();
 
+  // Set up the unlock prompt for encrypted notes
+  setupLockOverlay();
+
   // Set up event listeners
   setupEventListeners();
 
@@ -133,6 +143,44 @@ async function init() {
     }, 100);
   });
 
+  // Listen for note switches - flush pending edits to the old note, then load the new one
+  await listen<NoteMeta>("active-note-changed", async (event) => {
+    if (event.payload.id === currentNoteId) return;
+
+    await flushPendingSave();
+    currentNoteId = event.payload.id;
+
+    try {
+      currentNote = await NoteService.get(currentNoteId);
+      editor.commands.setContent(currentNote, { emitUpdate: false });
+    } catch (error) {
+      console.error("Failed to load switched note:", error);
+    }
+  });
+
+  // Listen for restored revisions - replace the editor content without re-saving it
+  await listen<string>("note-content-changed", async (event) => {
+    if (event.payload !== currentNoteId) return;
+
+    if (saveTimeout !== null) {
+      clearTimeout(saveTimeout);
+      saveTimeout = null;
+    }
+
+    try {
+      currentNote = await NoteService.get(currentNoteId);
+      editor.commands.setContent(currentNote, { emitUpdate: false });
+    } catch (error) {
+      console.error("Failed to reload note:", error);
+    }
+  });
+
+  // Listen for auto-lock - drop the decrypted content from the editor
+  await listen("notes-locked", async () => {
+    await flushPendingSave();
+    showLockOverlay();
+  });
+
   // Listen for update check events from tray menu
   await listen("check-updates", () => {
     UpdateService.checkForUpdates(false);
@@ -142,7 +190,7 @@ async function init() {
   updateIdleDetector();
 
   // Focus editor if auto-focus is enabled
-  if (preferences.auto_focus) {
+  if (preferences.auto_focus && !notesLocked) {
     editor.commands.focus();
   }
 
@@ -157,6 +205,48 @@ function initializeIcons() {
   createIcons({ icons });
 }
 
+function setupLockOverlay() {
+  const overlay = document.getElementById("lock-overlay") as HTMLFormElement;
+  const passphrase = document.getElementById("unlock-passphrase") as HTMLInputElement;
+  const errorText = document.getElementById("unlock-error") as HTMLElement;
+
+  overlay.addEventListener("submit", async (e) => {
+    e.preventDefault();
+    errorText.textContent = "";
+
+    try {
+      await NoteService.unlock(passphrase.value);
+      passphrase.value = "";
+      currentNoteId = (await NoteService.active()).id;
+      currentNote = await NoteService.get(currentNoteId);
+    } catch (error) {
+      errorText.textContent = errorMessage(error);
+      return;
+    }
+
+    notesLocked = false;
+    overlay.hidden = true;
+    editor.setEditable(true);
+    editor.commands.setContent(currentNote, { emitUpdate: false });
+    editor.commands.focus();
+  });
+
+  if (notesLocked) {
+    showLockOverlay();
+  }
+}
+
+function showLockOverlay() {
+  notesLocked = true;
+  currentNote = "";
+  editor.commands.setContent("", { emitUpdate: false });
+  editor.setEditable(false);
+
+  const overlay = document.getElementById("lock-overlay") as HTMLFormElement;
+  overlay.hidden = false;
+  (document.getElementById("unlock-passphrase") as HTMLInputElement).focus();
+}
+
 function setupToolbar() {
   const buttons = toolbar.querySelectorAll(".toolbar-btn");
 
@@ -398,13 +488,20 @@ function debouncedSave() {
     clearTimeout(saveTimeout);
   }
 
-  saveTimeout = window.setTimeout(async () => {
-    try {
-      await NoteService.save(currentNote);
-    } catch (error) {
-      console.error("Failed to save note:", error);
-    }
-  }, 500);
+  saveTimeout = window.setTimeout(flushPendingSave, 500);
+}
+
+async function flushPendingSave() {
+  if (saveTimeout === null) return;
+
+  clearTimeout(saveTimeout);
+  saveTimeout = null;
+
+  try {
+    await NoteService.save(currentNote, currentNoteId);
+  } catch (error) {
+    console.error("Failed to save note:", error);
+  }
 }
 
 async function hideWindowWithFade() {
@@ -1,7 +1,17 @@
 import { PreferencesService } from "./services/preferences-service";
-import { PREFERENCE_DEFAULTS } from "./types";
-import type { Preferences, SyncPreferences, SyncTestResponse } from "./types";
+import { PREFERENCE_DEFAULTS, errorMessage, isAppError } from "./types";
+import type {
+  EncryptionStatus,
+  Preferences,
+  FieldError,
+  ShortcutAction,
+  ShortcutBinding,
+  SyncPreferences,
+  SyncStatus,
+  SyncTestResponse,
+} from "./types";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { openUrl } from "@tauri-apps/plugin-opener";
 import { getVersion } from '@tauri-apps/api/app';
 
@@ -9,10 +19,17 @@ let form: HTMLFormElement;
 let currentPreferences: Preferences = mergeWithDefaults();
 
 type PrimitivePreferenceValue = string | number | boolean | null;
-type RootPreferenceKey = Exclude<keyof Preferences, "sync">;
+type RootPreferenceKey = Exclude<keyof Preferences, "sync" | "shortcuts">;
 type SyncPreferenceKey = keyof SyncPreferences;
 type PreferencePath = RootPreferenceKey | `sync.${SyncPreferenceKey}`;
-type SyncTargetValue = "none" | "markdown" | "apple_notes";
+type SyncTargetValue = "markdown" | "html" | "text" | "apple_notes";
+
+const SYNC_TARGET_LABELS: Record<SyncTargetValue, string> = {
+  markdown: "Markdown",
+  html: "HTML",
+  text: "Plain text",
+  apple_notes: "Apple Notes",
+};
 
 interface PreferenceBinding {
   path: PreferencePath;
@@ -26,7 +43,6 @@ const preferenceBindings: PreferenceBinding[] = [
   { path: "launch_on_startup", name: "launch_on_startup", control: "checkbox" },
   { path: "show_on_launch", name: "show_on_launch", control: "checkbox" },
   { path: "shortcut_enabled", name: "shortcut_enabled", control: "checkbox" },
-  { path: "keyboard_shortcut", name: "keyboard_shortcut", control: "text" },
   {
     path: "text_size",
     name: "text_size",
@@ -65,6 +81,8 @@ const preferenceBindings: PreferenceBinding[] = [
     valueDisplayId: "hotcorner-size-value",
     formatDisplay: (value) => `${value}px`,
   },
+  { path: "placement_policy", name: "placement_policy", control: "select" },
+  { path: "placement_corner", name: "placement_corner", control: "select" },
   { path: "auto_focus", name: "auto_focus", control: "checkbox" },
   { path: "hide_on_blur", name: "hide_on_blur", control: "checkbox" },
   { path: "auto_hide_enabled", name: "auto_hide_enabled", control: "checkbox" },
@@ -82,10 +100,31 @@ const preferenceBindings: PreferenceBinding[] = [
     valueDisplayId: "fade-duration-value",
     formatDisplay: (value) => `${value}ms`,
   },
+  {
+    path: "auto_lock_minutes",
+    name: "auto_lock_minutes",
+    control: "range",
+    valueDisplayId: "auto-lock-value",
+    formatDisplay: (value) => (value === 0 ? "Never" : `${value} min`),
+  },
+  { path: "api_enabled", name: "api_enabled", control: "checkbox" },
+  { path: "api_transport", name: "api_transport", control: "select" },
+  { path: "api_port", name: "api_port", control: "range" },
+  { path: "log_level", name: "log_level", control: "select" },
   { path: "sync.markdown_path", name: "sync_markdown_path", control: "text" },
-  { path: "sync.include_metadata", name: "sync_include_metadata", control: "checkbox" },
+  { path: "sync.markdown_mode", name: "sync_markdown_mode", control: "select" },
+  { path: "sync.markdown_filename", name: "sync_markdown_filename", control: "text" },
+  { path: "sync.markdown_metadata", name: "sync_markdown_metadata", control: "select" },
+  { path: "sync.markdown_journal_append", name: "sync_markdown_journal_append", control: "checkbox" },
+  { path: "sync.html_path", name: "sync_html_path", control: "text" },
+  { path: "sync.html_filename", name: "sync_html_filename", control: "text" },
+  { path: "sync.html_metadata", name: "sync_html_metadata", control: "select" },
+  { path: "sync.text_path", name: "sync_text_path", control: "text" },
+  { path: "sync.text_filename", name: "sync_text_filename", control: "text" },
+  { path: "sync.text_metadata", name: "sync_text_metadata", control: "select" },
   { path: "sync.apple_notes_title", name: "sync_apple_notes_title", control: "text" },
   { path: "sync.apple_notes_folder", name: "sync_apple_notes_folder", control: "select" },
+  { path: "sync.apple_notes_metadata", name: "sync_apple_notes_metadata", control: "checkbox" },
 ];
 
 async function init() {
@@ -103,8 +142,8 @@ async function init() {
   // Set up tooltips positioning
   setupTooltips();
 
-  // Set up shortcut recording
-  setupShortcutRecording();
+  // Set up the shortcut list
+  setupShortcutBindings();
 
   // Load and display app version
   try {
@@ -133,6 +172,10 @@ async function init() {
   setupSyncTargetControls();
   setupAutoSave();
   setupSyncActions();
+  setupSyncStatus();
+  setupEncryption();
+  setupExternalEdits();
+  setupDiagnostics();
 }
 
 function setupTabs() {
@@ -181,18 +224,18 @@ function setupSyncTargetControls() {
     return;
   }
 
-  const radios = getSyncTargetRadios();
-  if (!radios) {
+  const checkboxes = getSyncTargetCheckboxes();
+  if (checkboxes.length === 0) {
     return;
   }
 
   const status = document.getElementById("sync-test-status");
 
   const updateSelection = (triggerLoad: boolean) => {
-    const selected = getSelectedSyncTarget();
+    const selected = getSelectedSyncTargets();
 
     targetSections.forEach((section) => {
-      const matches = section.dataset.target === selected;
+      const matches = selected.includes(section.dataset.target as SyncTargetValue);
       section.classList.toggle("selected", matches);
 
       const interactiveElements = section.querySelectorAll<
@@ -200,7 +243,7 @@ function setupSyncTargetControls() {
       >("input, select, textarea, button");
 
       interactiveElements.forEach((element) => {
-        if ((element as HTMLInputElement).type === "radio") {
+        if ((element as HTMLInputElement).name === "sync_target") {
           return;
         }
 
@@ -223,14 +266,14 @@ function setupSyncTargetControls() {
       status.className = "sync-status-message";
     }
 
-    if (selected === "apple_notes" && triggerLoad) {
+    if (selected.includes("apple_notes") && triggerLoad) {
       const currentValue = (
         form.elements.namedItem("sync_apple_notes_folder") as HTMLSelectElement | null
       )?.value ?? null;
       void loadAppleNotesFolders(currentValue);
     }
 
-    if (selected !== "apple_notes") {
+    if (!selected.includes("apple_notes")) {
       const select = document.getElementById(
         "apple-notes-folder-select"
       ) as HTMLSelectElement | null;
@@ -240,8 +283,8 @@ function setupSyncTargetControls() {
     }
   };
 
-  Array.from(radios).forEach((radio) => {
-    radio.addEventListener("change", () => {
+  checkboxes.forEach((checkbox) => {
+    checkbox.addEventListener("change", () => {
       updateSelection(true);
     });
   });
@@ -249,41 +292,22 @@ function setupSyncTargetControls() {
   updateSelection(false);
 }
 
-function getSyncTargetRadios(): RadioNodeList | null {
-  const element = form.elements.namedItem("sync_target");
-  return (element as RadioNodeList | null) ?? null;
+function getSyncTargetCheckboxes(): HTMLInputElement[] {
+  return Array.from(
+    form.querySelectorAll<HTMLInputElement>('input[name="sync_target"]')
+  );
 }
 
-function getSelectedSyncTarget(): SyncTargetValue {
-  const radios = getSyncTargetRadios();
-  if (!radios) {
-    return "none";
-  }
-
-  const selected = Array.from(radios).find(
-    (radio) => (radio as HTMLInputElement).checked
-  ) as HTMLInputElement | undefined;
-
-  if (!selected) {
-    return "none";
-  }
-
-  if (selected.value === "markdown" || selected.value === "apple_notes") {
-    return selected.value;
-  }
-
-  return "none";
+function getSelectedSyncTargets(): SyncTargetValue[] {
+  return getSyncTargetCheckboxes()
+    .filter((checkbox) => checkbox.checked)
+    .map((checkbox) => checkbox.value)
+    .filter((value): value is SyncTargetValue => value in SYNC_TARGET_LABELS);
 }
 
-function setSelectedSyncTarget(target: SyncTargetValue) {
-  const radios = getSyncTargetRadios();
-  if (!radios) {
-    return;
-  }
-
-  Array.from(radios).forEach((radio) => {
-    const input = radio as HTMLInputElement;
-    input.checked = input.value === target;
+function setSelectedSyncTargets(targets: SyncTargetValue[]) {
+  getSyncTargetCheckboxes().forEach((checkbox) => {
+    checkbox.checked = targets.includes(checkbox.value as SyncTargetValue);
   });
 }
 
@@ -404,6 +428,36 @@ function setupDependentSettings() {
     updateHotcornerSettings(); // Set initial state
   }
 
+  // Scripting API settings
+  const apiEnabled = document.getElementById("api-enabled") as HTMLInputElement;
+  const apiSettings = document.getElementById("api-settings");
+  const apiTransport = document.getElementById("api-transport-select") as HTMLSelectElement;
+  const apiPortGroup = document.getElementById("api-port-group");
+
+  if (apiEnabled && apiSettings && apiTransport && apiPortGroup) {
+    const updateApiSettings = () => {
+      apiSettings.classList.toggle("disabled", !apiEnabled.checked);
+      apiPortGroup.hidden = apiTransport.value !== "loopback";
+    };
+
+    apiEnabled.addEventListener("change", updateApiSettings);
+    apiTransport.addEventListener("change", updateApiSettings);
+    updateApiSettings(); // Set initial state
+  }
+
+  // Window placement settings
+  const placementPolicy = document.getElementById("placement-policy-select") as HTMLSelectElement;
+  const placementCornerGroup = document.getElementById("placement-corner-group");
+
+  if (placementPolicy && placementCornerGroup) {
+    const updatePlacementSettings = () => {
+      placementCornerGroup.hidden = placementPolicy.value !== "pinned-corner";
+    };
+
+    placementPolicy.addEventListener("change", updatePlacementSettings);
+    updatePlacementSettings(); // Set initial state
+  }
+
   // Auto-hide settings
   const autoHideEnabled = document.getElementById("auto-hide-enabled") as HTMLInputElement;
   const autoHideSettings = document.getElementById("auto-hide-settings");
@@ -423,6 +477,134 @@ function setupDependentSettings() {
 
 }
 
+function setupSyncStatus() {
+  const element = document.getElementById("sync-background-status");
+  if (!element) {
+    return;
+  }
+
+  const render = (status: SyncStatus) => {
+    const formatTime = (value: string) => new Date(value).toLocaleTimeString();
+    const lastSynced = status.last_synced_at
+      ? `Last synced ${formatTime(status.last_synced_at)}`
+      : "Not synced yet";
+
+    switch (status.state) {
+      case "pending":
+      case "syncing":
+        element.textContent = `Syncing… ${lastSynced}`;
+        element.className = "sync-status-message pending";
+        break;
+      case "error": {
+        const retry = status.next_retry_at
+          ? ` Retrying at ${formatTime(status.next_retry_at)}.`
+          : "";
+        element.textContent = `${status.last_error ?? "Sync failed"}.${retry}`;
+        element.className = "sync-status-message error";
+        break;
+      }
+      default:
+        element.textContent = lastSynced;
+        element.className = "sync-status-message";
+    }
+  };
+
+  invoke<SyncStatus>("get_sync_status")
+    .then(render)
+    .catch((error) => console.error("Failed to load sync status:", error));
+
+  void listen<SyncStatus>("sync-status", (event) => render(event.payload));
+}
+
+function setupEncryption() {
+  const passphrase = document.getElementById("encryption-passphrase") as HTMLInputElement | null;
+  const toggleButton = document.getElementById("encryption-toggle-btn") as HTMLButtonElement | null;
+  const lockButton = document.getElementById("encryption-lock-btn") as HTMLButtonElement | null;
+  const status = document.getElementById("encryption-status");
+  const autoLockSettings = document.getElementById("auto-lock-settings");
+
+  if (!passphrase || !toggleButton || !lockButton || !status) {
+    return;
+  }
+
+  let current: EncryptionStatus = { enabled: false, locked: false };
+
+  const render = (message?: string, variant: "success" | "error" | "" = "") => {
+    toggleButton.textContent = current.enabled ? "Disable" : "Enable";
+    lockButton.disabled = !current.enabled || current.locked;
+    autoLockSettings?.classList.toggle("disabled", !current.enabled);
+
+    const fallback = !current.enabled
+      ? "Notes are stored unencrypted"
+      : current.locked
+        ? "Encrypted · locked"
+        : "Encrypted · unlocked";
+    status.textContent = message ?? fallback;
+    status.className = `sync-status-message ${variant}`.trim();
+  };
+
+  const refresh = async () => {
+    current = await invoke<EncryptionStatus>("get_encryption_status");
+    render();
+  };
+
+  toggleButton.addEventListener("click", async () => {
+    const command = current.enabled ? "disable_encryption" : "enable_encryption";
+    toggleButton.disabled = true;
+
+    try {
+      await invoke(command, { passphrase: passphrase.value });
+      passphrase.value = "";
+      current = await invoke<EncryptionStatus>("get_encryption_status");
+      render(current.enabled ? "Notes encrypted" : "Encryption turned off", "success");
+    } catch (error) {
+      render(errorMessage(error), "error");
+    } finally {
+      toggleButton.disabled = false;
+    }
+  });
+
+  lockButton.addEventListener("click", async () => {
+    try {
+      await invoke("lock_notes");
+      await refresh();
+    } catch (error) {
+      render(errorMessage(error), "error");
+    }
+  });
+
+  void listen("notes-locked", () => void refresh());
+  void listen("notes-unlocked", () => void refresh());
+
+  refresh().catch((error) => console.error("Failed to load encryption status:", error));
+}
+
+function setupDiagnostics() {
+  const button = document.getElementById("export-diagnostics-btn") as HTMLButtonElement | null;
+  const status = document.getElementById("diagnostics-status");
+
+  if (!button || !status) {
+    return;
+  }
+
+  button.addEventListener("click", async () => {
+    button.disabled = true;
+    status.className = "sync-status-message pending";
+    status.textContent = "Exporting…";
+
+    try {
+      const path = await invoke<string>("export_diagnostics");
+      status.className = "sync-status-message success";
+      status.textContent = `Saved to ${path}`;
+    } catch (error) {
+      status.className = "sync-status-message error";
+      status.textContent = `Failed: ${errorMessage(error)}`;
+    } finally {
+      button.disabled = false;
+    }
+  });
+}
+
 function setupSyncActions() {
   const saveButton = document.getElementById("sync-save-button") as HTMLButtonElement | null;
   const saveAndTestButton = document.getElementById("sync-save-test-button") as HTMLButtonElement | null;
@@ -442,12 +624,8 @@ function setupSyncActions() {
     status.className = `sync-status-message ${variant}`;
   };
 
-  const getCurrentTargetLabel = (): string | null => {
-    const target = getSelectedSyncTarget();
-    if (target === "markdown") return "Markdown";
-    if (target === "apple_notes") return "Apple Notes";
-    return null;
-  };
+  const getSelectedTargetLabels = (): string[] =>
+    getSelectedSyncTargets().map((target) => SYNC_TARGET_LABELS[target]);
 
   const syncInputs = document.querySelectorAll<HTMLElement>(
     '[data-tab-content="sync"] input, [data-tab-content="sync"] select, [data-tab-content="sync"] textarea'
@@ -471,20 +649,23 @@ function setupSyncActions() {
         return;
       }
 
-      const targetLabel = getCurrentTargetLabel();
-      if (!targetLabel) {
+      const targetLabels = getSelectedTargetLabels();
+      if (targetLabels.length === 0) {
         updateStatus("Select a sync target before testing.", "error");
         return;
       }
 
-      updateStatus(`Saving and testing ${targetLabel}...`, "pending");
+      updateStatus(`Saving and testing ${targetLabels.join(" and ")}...`, "pending");
 
       const response = await invoke<SyncTestResponse>("test_sync");
-      const label = response.target ?? targetLabel;
-      updateStatus(`${label}: ${response.message}`, response.success ? "success" : "error");
+      const message =
+        response.results.length > 0
+          ? response.results.map((result) => `${result.label}: ${result.message}`).join(" · ")
+          : response.message;
+      updateStatus(message, response.success ? "success" : "error");
     } catch (error) {
       console.error("Failed to update sync preferences:", error);
-      updateStatus(`Failed: ${String(error)}`, "error");
+      updateStatus(`Failed: ${errorMessage(error)}`, "error");
     } finally {
       saveButton.disabled = false;
       saveAndTestButton.disabled = false;
@@ -594,15 +775,115 @@ async function loadAppleNotesFolders(selectedValue: string | null) {
     fallback.selected = true;
     select.appendChild(fallback);
     select.disabled = true;
-    hint.textContent = `Unable to load folders: ${String(error)}`;
+    hint.textContent = `Unable to load folders: ${errorMessage(error)}`;
     hint.classList.add("error");
   }
 }
 
-function setupShortcutRecording() {
-  const recordBtn = document.getElementById("record-shortcut-btn") as HTMLButtonElement;
-  const shortcutInput = document.getElementById("keyboard-shortcut-input") as HTMLInputElement;
-  const shortcutHint = document.getElementById("shortcut-hint") as HTMLElement;
+const SHORTCUT_ACTION_LABELS: Record<ShortcutAction, string> = {
+  "toggle-window": "Show/hide note",
+  "show-window": "Show note",
+  "hide-window": "Hide note",
+  "open-preferences": "Open preferences",
+  "sync-now": "Sync now",
+  "switch-theme": "Switch theme",
+  "capture-clipboard": "Append clipboard",
+  "capture-bullet": "Append clipboard as bullet",
+};
+
+function setupShortcutBindings() {
+  const addBtn = document.getElementById("add-shortcut-btn") as HTMLButtonElement;
+
+  addBtn.addEventListener("click", () => {
+    const row = createShortcutRow({ shortcut: "", action: "toggle-window" });
+    document.getElementById("shortcut-bindings")?.appendChild(row);
+    row.querySelector<HTMLInputElement>(".shortcut-input")?.click();
+  });
+}
+
+function renderShortcutBindings(bindings: ShortcutBinding[]) {
+  const list = document.getElementById("shortcut-bindings");
+  if (!list) return;
+
+  list.innerHTML = "";
+  bindings.forEach((binding) => list.appendChild(createShortcutRow(binding)));
+}
+
+function createShortcutRow(binding: ShortcutBinding): HTMLElement {
+  const container = document.createElement("div");
+  container.className = "shortcut-binding";
+
+  const row = document.createElement("div");
+  row.className = "shortcut-row";
+
+  const select = document.createElement("select");
+  select.className = "shortcut-action";
+  (Object.keys(SHORTCUT_ACTION_LABELS) as ShortcutAction[]).forEach((action) => {
+    const option = document.createElement("option");
+    option.value = action;
+    option.textContent = SHORTCUT_ACTION_LABELS[action];
+    select.appendChild(option);
+  });
+  select.value = binding.action;
+  select.addEventListener("change", () => savePreferences());
+
+  const input = document.createElement("input");
+  input.type = "text";
+  input.className = "shortcut-input";
+  input.placeholder = "Click to record";
+  input.readOnly = true;
+  input.value = binding.shortcut;
+
+  const recordBtn = document.createElement("button");
+  recordBtn.type = "button";
+  recordBtn.className = "record-btn";
+  recordBtn.textContent = "Record";
+
+  const removeBtn = document.createElement("button");
+  removeBtn.type = "button";
+  removeBtn.className = "record-btn";
+  removeBtn.textContent = "Remove";
+  removeBtn.addEventListener("click", () => {
+    container.remove();
+    savePreferences();
+  });
+
+  const hint = document.createElement("small");
+  hint.className = "shortcut-hint";
+
+  row.append(select, input, recordBtn, removeBtn);
+  container.append(row, hint);
+  setupShortcutRecording(input, recordBtn, hint);
+
+  return container;
+}
+
+function collectShortcutBindings(): ShortcutBinding[] {
+  return Array.from(document.querySelectorAll<HTMLElement>(".shortcut-binding"))
+    .map((container) => ({
+      shortcut: container.querySelector<HTMLInputElement>(".shortcut-input")!.value.trim(),
+      action: container.querySelector<HTMLSelectElement>(".shortcut-action")!.value as ShortcutAction,
+    }))
+    .filter((binding) => binding.shortcut !== "");
+}
+
+function showShortcutErrors(errors: FieldError[]) {
+  const status = document.getElementById("shortcut-status");
+  if (!status) return;
+
+  const messages = errors.map((error) => `${error.message}.`);
+  status.textContent = messages.length
+    ? `${messages.join(" ")} Kept the previous shortcuts.`
+    : "Click a shortcut and press your desired key combination";
+  status.classList.toggle("error", messages.length > 0);
+}
+
+function setupShortcutRecording(
+  shortcutInput: HTMLInputElement,
+  recordBtn: HTMLButtonElement,
+  shortcutHint: HTMLElement
+) {
+  const defaultHint = shortcutHint.textContent ?? "";
 
   let isRecording = false;
   let recordedKeys: Set<string> = new Set();
@@ -626,7 +907,7 @@ function setupShortcutRecording() {
     recordBtn.textContent = "Record";
     recordBtn.classList.remove("recording");
     shortcutInput.classList.remove("recording");
-    shortcutHint.textContent = "Click \"Record\" and press your desired key combination";
+    shortcutHint.textContent = defaultHint;
     shortcutHint.classList.remove("recording");
   };
 
@@ -705,8 +986,34 @@ function setupShortcutRecording() {
   });
 }
 
+/** Keeps the form in step with edits made to preferences.json by hand. */
+function setupExternalEdits() {
+  const status = document.getElementById("prefs-file-status");
+
+  void listen<Preferences>("preferences-updated", (event) => {
+    const updated = mergeWithDefaults(event.payload);
+    if (status) {
+      status.hidden = true;
+    }
+    if (JSON.stringify(updated) !== JSON.stringify(currentPreferences)) {
+      currentPreferences = updated;
+      loadPreferencesIntoForm(currentPreferences);
+    }
+  });
+
+  void listen<FieldError[]>("preferences-reload-failed", (event) => {
+    if (status) {
+      status.textContent =
+        "preferences.json was edited but not applied: " +
+        event.payload.map((e) => e.message).join("; ");
+      status.hidden = false;
+    }
+  });
+}
+
 function loadPreferencesIntoForm(preferences: Preferences) {
   applyPreferencesToForm(preferences);
+  renderShortcutBindings(preferences.shortcuts);
   void loadAppleNotesFolders(preferences.sync.apple_notes_folder);
 }
 
@@ -716,14 +1023,63 @@ async function savePreferences() {
     currentPreferences = mergeWithDefaults(latestPrefs);
     const updatedPrefs = buildPreferencesFromForm(currentPreferences);
 
-    await PreferencesService.update(updatedPrefs);
-    currentPreferences = clonePreferences(updatedPrefs);
+    const patch = diffPreferences(currentPreferences, updatedPrefs);
+    let warnings: FieldError[] = [];
+    if (Object.keys(patch).length) {
+      const saved = await PreferencesService.patch(patch);
+      currentPreferences = mergeWithDefaults(saved.preferences);
+      warnings = saved.warnings;
+    }
+    // Everything else was saved; rejected shortcuts were kept as they were
+    showShortcutErrors(warnings);
   } catch (error) {
     console.error("Failed to save preferences:", error);
-    alert("Failed to save preferences: " + error);
+    const errors: FieldError[] = isAppError(error)
+      ? error.fields ?? [{ field: error.field ?? null, message: error.message }]
+      : [{ field: null, message: String(error) }];
+    const isShortcutError = (e: FieldError) => e.field?.startsWith("shortcuts") ?? false;
+
+    // Nothing was saved, so show what still applies
+    applyPreferencesToForm(currentPreferences);
+    showShortcutErrors(errors.filter(isShortcutError));
+
+    const otherErrors = errors.filter((e) => !isShortcutError(e));
+    if (otherErrors.length) {
+      alert("Failed to save preferences: " + otherErrors.map((e) => e.message).join("\n"));
+    }
+  }
+
+  // Shortcuts come back normalized, or as they were if one was rejected
+  if (JSON.stringify(collectShortcutBindings()) !== JSON.stringify(currentPreferences.shortcuts)) {
+    renderShortcutBindings(currentPreferences.shortcuts);
   }
 }
 
+/** The fields of `updated` that differ from `base`, as a JSON merge patch. */
+function diffPreferences(base: Preferences, updated: Preferences): Record<string, unknown> {
+  const patch: Record<string, unknown> = {};
+  const changed = (a: unknown, b: unknown) => JSON.stringify(a) !== JSON.stringify(b);
+
+  (Object.keys(updated) as Array<keyof Preferences>).forEach((key) => {
+    if (key === "sync") {
+      const sync: Record<string, unknown> = {};
+      (Object.keys(updated.sync) as Array<keyof Preferences["sync"]>).forEach((syncKey) => {
+        if (changed(base.sync[syncKey], updated.sync[syncKey])) {
+          sync[syncKey] = updated.sync[syncKey];
+        }
+      });
+      if (Object.keys(sync).length) {
+        patch.sync = sync;
+      }
+    } else if (changed(base[key], updated[key])) {
+      // Arrays such as `shortcuts` are replaced whole
+      patch[key] = updated[key];
+    }
+  });
+
+  return patch;
+}
+
 function applyPreferencesToForm(preferences: Preferences) {
   preferenceBindings.forEach((binding) => {
     const element = form.elements.namedItem(binding.name);
@@ -777,17 +1133,26 @@ function applyPreferencesToForm(preferences: Preferences) {
     }
   });
 
-  const target: SyncTargetValue = preferences.sync.apple_notes_enabled
-    ? "apple_notes"
-    : preferences.sync.markdown_enabled
-    ? "markdown"
-    : "none";
+  const targets: SyncTargetValue[] = [];
+  if (preferences.sync.markdown_enabled) {
+    targets.push("markdown");
+  }
+  if (preferences.sync.html_enabled) {
+    targets.push("html");
+  }
+  if (preferences.sync.text_enabled) {
+    targets.push("text");
+  }
+  if (preferences.sync.apple_notes_enabled) {
+    targets.push("apple_notes");
+  }
 
-  setSelectedSyncTarget(target);
+  setSelectedSyncTargets(targets);
 }
 
 function buildPreferencesFromForm(base: Preferences): Preferences {
   const updated = clonePreferences(base);
+  updated.shortcuts = collectShortcutBindings();
 
   preferenceBindings.forEach((binding) => {
     const element = form.elements.namedItem(binding.name);
@@ -844,9 +1209,11 @@ function buildPreferencesFromForm(base: Preferences): Preferences {
     }
   });
 
-  const selectedTarget = getSelectedSyncTarget();
-  updated.sync.markdown_enabled = selectedTarget === "markdown";
-  updated.sync.apple_notes_enabled = selectedTarget === "apple_notes";
+  const selectedTargets = getSelectedSyncTargets();
+  updated.sync.markdown_enabled = selectedTargets.includes("markdown");
+  updated.sync.html_enabled = selectedTargets.includes("html");
+  updated.sync.text_enabled = selectedTargets.includes("text");
+  updated.sync.apple_notes_enabled = selectedTargets.includes("apple_notes");
 
   return updated;
 }
@@ -889,6 +1256,7 @@ function mergeWithDefaults(preferences?: Preferences): Preferences {
   const merged: Preferences = {
     ...PREFERENCE_DEFAULTS,
     ...preferences,
+    shortcuts: preferences.shortcuts ?? PREFERENCE_DEFAULTS.shortcuts,
     sync: {
       ...PREFERENCE_DEFAULTS.sync,
       ...(preferences.sync ?? PREFERENCE_DEFAULTS.sync),
@@ -901,6 +1269,7 @@ function mergeWithDefaults(preferences?: Preferences): Preferences {
 function clonePreferences(preferences: Preferences): Preferences {
   return {
     ...preferences,
+    shortcuts: preferences.shortcuts.map((binding) => ({ ...binding })),
     sync: { ...preferences.sync },
   };
 }
@@ -1,11 +1,64 @@
 import { invoke } from "@tauri-apps/api/core";
+import type { EncryptionStatus, NoteMeta, Revision, RevisionDiff, SearchHit } from "../types";
 
 export class NoteService {
-  static async get(): Promise<string> {
-    return await invoke<string>("get_note");
+  static async get(noteId?: string): Promise<string> {
+    return await invoke<string>("get_note", { noteId });
   }
 
-  static async save(content: string): Promise<void> {
-    await invoke("save_note", { content });
+  static async save(content: string, noteId?: string): Promise<void> {
+    await invoke("save_note", { content, noteId });
+  }
+
+  static async list(): Promise<NoteMeta[]> {
+    return await invoke<NoteMeta[]>("list_notes");
+  }
+
+  static async active(): Promise<NoteMeta> {
+    return await invoke<NoteMeta>("get_active_note");
+  }
+
+  static async create(title: string, activate = true): Promise<NoteMeta> {
+    return await invoke<NoteMeta>("create_note", { title, activate });
+  }
+
+  static async rename(noteId: string, title: string): Promise<NoteMeta> {
+    return await invoke<NoteMeta>("rename_note", { noteId, title });
+  }
+
+  static async remove(noteId: string): Promise<void> {
+    await invoke("delete_note", { noteId });
+  }
+
+  static async switchTo(noteId: string): Promise<NoteMeta> {
+    return await invoke<NoteMeta>("switch_note", { noteId });
+  }
+
+  static async revisions(noteId?: string): Promise<Revision[]> {
+    return await invoke<Revision[]>("list_revisions", { noteId });
+  }
+
+  static async diffRevision(revisionId: string, noteId?: string): Promise<RevisionDiff> {
+    return await invoke<RevisionDiff>("diff_revision", { revisionId, noteId });
+  }
+
+  static async restoreRevision(revisionId: string, noteId?: string): Promise<string> {
+    return await invoke<string>("restore_revision", { revisionId, noteId });
+  }
+
+  static async search(query: string, limit?: number): Promise<SearchHit[]> {
+    return await invoke<SearchHit[]>("search_notes", { query, limit });
+  }
+
+  static async encryptionStatus(): Promise<EncryptionStatus> {
+    return await invoke<EncryptionStatus>("get_encryption_status");
+  }
+
+  static async unlock(passphrase: string): Promise<void> {
+    await invoke("unlock_notes", { passphrase });
+  }
+
+  static async lock(): Promise<void> {
+    await invoke("lock_notes");
   }
 }
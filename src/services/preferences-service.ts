@@ -1,6 +1,6 @@
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
-import type { Preferences } from "../types";
+import type { Preferences, SavedPreferences } from "../types";
 
 export class PreferencesService {
   private static listeners: Array<(prefs: Preferences) => void> = [];
@@ -9,8 +9,21 @@ export class PreferencesService {
     return await invoke<Preferences>("get_preferences");
   }
 
-  static async update(preferences: Preferences): Promise<void> {
-    await invoke("update_preferences", { newPrefs: preferences });
+  /**
+   * Rejects with an `AppError` listing each problem in `fields`; nothing is
+   * changed. Resolves with the preferences as saved and the shortcuts that
+   * couldn't be registered and were kept as they were.
+   */
+  static async update(preferences: Preferences): Promise<SavedPreferences> {
+    return await invoke<SavedPreferences>("update_preferences", { newPrefs: preferences });
+  }
+
+  /**
+   * Changes only the fields in `patch`, a JSON merge patch, so a concurrent
+   * save of other fields isn't overwritten. Rejects like `update`.
+   */
+  static async patch(patch: Record<string, unknown>): Promise<SavedPreferences> {
+    return await invoke<SavedPreferences>("patch_preferences", { patch });
   }
 
   static onChange(callback: (prefs: Preferences) => void): () => void {
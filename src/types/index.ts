@@ -1,23 +1,102 @@
+export type ApiTransport = "socket" | "loopback";
+export type LogLevel = "error" | "warn" | "info" | "debug" | "trace";
 export type Corner = "TopLeft" | "TopRight" | "BottomLeft" | "BottomRight";
+export type PlacementPolicy =
+  | "last-position"
+  | "monitor-under-cursor"
+  | "near-cursor"
+  | "pinned-corner";
+export type MarkdownMode = "one-way" | "two-way" | "journal";
+export type MetadataStyle = "yaml" | "html-comment" | "none";
 export type Theme = "liquid-glass" | "gradient-cosmic" | "minimal" | "minimal-dark" | "sticky-note";
+export type ShortcutAction =
+  | "toggle-window"
+  | "show-window"
+  | "hide-window"
+  | "open-preferences"
+  | "sync-now"
+  | "switch-theme"
+  | "capture-clipboard"
+  | "capture-bullet";
+
+export interface ShortcutBinding {
+  shortcut: string;
+  action: ShortcutAction;
+}
+
+/** One reason preferences were rejected; `field` is a dotted key such as `shortcuts.1`. */
+export interface FieldError {
+  field: string | null;
+  message: string;
+}
+
+/** Preferences as saved; `warnings` lists changes kept at their previous values. */
+export interface SavedPreferences {
+  preferences: Preferences;
+  warnings: FieldError[];
+}
+
+export type ErrorCode =
+  | "not-found"
+  | "locked"
+  | "permission-denied"
+  | "invalid"
+  | "conflict"
+  | "not-configured"
+  | "not-implemented"
+  | "io"
+  | "script"
+  | "internal";
+
+/** What every command rejects with. Branch on `code`; `message` is for display. */
+export interface AppError {
+  code: ErrorCode;
+  message: string;
+  field?: string;
+  /** The note id, window label or sync target the error is about. */
+  target?: string;
+  /** Every problem, when preferences fail validation. */
+  fields?: FieldError[];
+}
+
+export function isAppError(error: unknown): error is AppError {
+  return typeof error === "object" && error !== null && "code" in error && "message" in error;
+}
+
+export function errorMessage(error: unknown): string {
+  return isAppError(error) ? error.message : String(error);
+}
 
 export interface SyncPreferences {
   markdown_enabled: boolean;
   markdown_path: string | null;
-  include_metadata: boolean;
+  markdown_mode: MarkdownMode;
+  markdown_filename: string;
+  markdown_metadata: MetadataStyle;
+  markdown_journal_append: boolean;
+  html_enabled: boolean;
+  html_path: string | null;
+  html_filename: string;
+  html_metadata: MetadataStyle;
+  text_enabled: boolean;
+  text_path: string | null;
+  text_filename: string;
+  text_metadata: MetadataStyle;
   apple_notes_enabled: boolean;
   apple_notes_title: string;
   apple_notes_folder: string;
+  apple_notes_metadata: boolean;
 }
 
 export interface Preferences {
+  schema_version: number;
   show_on_launch: boolean;
   launch_on_startup: boolean;
   hotcorner_enabled: boolean;
   hotcorner_corner: Corner;
   hotcorner_size: number;
   shortcut_enabled: boolean;
-  keyboard_shortcut: string;
+  shortcuts: ShortcutBinding[];
   auto_focus: boolean;
   auto_hide_enabled: boolean;
   auto_hide_delay_ms: number;
@@ -27,26 +106,44 @@ export interface Preferences {
   formatting_size: number;
   theme: Theme;
   transparency: number;
+  auto_lock_minutes: number;
+  api_enabled: boolean;
+  api_transport: ApiTransport;
+  api_port: number;
+  log_level: LogLevel;
+  placement_policy: PlacementPolicy;
+  placement_corner: Corner;
   window_x: number | null;
   window_y: number | null;
   window_width: number | null;
   window_height: number | null;
+  window_placements: WindowPlacement[];
   sync: SyncPreferences;
 }
 
+/** Window bounds saved for one monitor setup, in physical pixels. */
+export interface WindowPlacement {
+  layout: string;
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+}
+
 export interface WindowState {
   isVisible: boolean;
   lastInteractionTime: number;
 }
 
 export const PREFERENCE_DEFAULTS: Preferences = {
+  schema_version: 4,
   show_on_launch: false,
   launch_on_startup: true,
   hotcorner_enabled: true,
   hotcorner_corner: "BottomRight",
   hotcorner_size: 10,
   shortcut_enabled: true,
-  keyboard_shortcut: "Alt+Command+N",
+  shortcuts: [{ shortcut: "Alt+Command+N", action: "toggle-window" }],
   auto_focus: true,
   auto_hide_enabled: false,
   auto_hide_delay_ms: 1500,
@@ -56,22 +153,102 @@ export const PREFERENCE_DEFAULTS: Preferences = {
   formatting_size: 100,
   theme: "liquid-glass",
   transparency: 10,
+  auto_lock_minutes: 15,
+  api_enabled: false,
+  api_transport: "socket",
+  api_port: 47321,
+  log_level: "info",
+  placement_policy: "last-position",
+  placement_corner: "TopRight",
   window_x: null,
   window_y: null,
   window_width: null,
   window_height: null,
+  window_placements: [],
   sync: {
     markdown_enabled: false,
     markdown_path: null,
-    include_metadata: true,
+    markdown_mode: "one-way",
+    markdown_filename: "What The Note",
+    markdown_metadata: "html-comment",
+    markdown_journal_append: false,
+    html_enabled: false,
+    html_path: null,
+    html_filename: "What The Note",
+    html_metadata: "html-comment",
+    text_enabled: false,
+    text_path: null,
+    text_filename: "What The Note",
+    text_metadata: "html-comment",
     apple_notes_enabled: false,
     apple_notes_title: "What The Note",
     apple_notes_folder: "Notes",
+    apple_notes_metadata: true,
   },
 };
 
+export interface SyncTargetResult {
+  target: string;
+  label: string;
+  success: boolean;
+  message: string;
+  error?: AppError;
+}
+
 export interface SyncTestResponse {
   success: boolean;
-  target: string | null;
   message: string;
+  results: SyncTargetResult[];
+}
+
+export type SyncState = "idle" | "pending" | "syncing" | "error";
+
+export interface SyncStatus {
+  state: SyncState;
+  last_synced_at: string | null;
+  last_error: string | null;
+  next_retry_at: string | null;
+}
+
+export interface NoteMeta {
+  id: string;
+  title: string;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface Revision {
+  id: string;
+  saved_at: string;
+  bytes: number;
+  sealed: boolean;
+}
+
+export interface DiffLine {
+  kind: "equal" | "insert" | "delete";
+  text: string;
+}
+
+export interface RevisionDiff {
+  revision_id: string;
+  lines: DiffLine[];
+}
+
+export interface EncryptionStatus {
+  enabled: boolean;
+  locked: boolean;
+}
+
+export interface SnippetPart {
+  text: string;
+  highlight: boolean;
+}
+
+export interface SearchHit {
+  source: "note" | "file";
+  note_id: string | null;
+  path: string | null;
+  title: string;
+  score: number;
+  snippet: SnippetPart[];
 }